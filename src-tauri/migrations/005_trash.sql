@@ -0,0 +1,14 @@
+-- Files moved to the app-managed (or system) trash
+CREATE TABLE IF NOT EXISTS trash (
+  id TEXT PRIMARY KEY,
+  originalPath TEXT NOT NULL,
+  trashPath TEXT NOT NULL,
+  isDir INTEGER NOT NULL,
+  size INTEGER,
+  deletedBy TEXT NOT NULL,            -- 'user' or an agent id
+  location TEXT NOT NULL,             -- 'app' or 'system'
+  deletedAt TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_trash_deleted_at ON trash(deletedAt);
+CREATE INDEX IF NOT EXISTS idx_trash_original_path ON trash(originalPath);
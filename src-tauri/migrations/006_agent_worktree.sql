@@ -0,0 +1,2 @@
+-- Whether chats of an agent run in a git worktree of their own
+ALTER TABLE agent ADD COLUMN worktree INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,167 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex as StdMutex;
+use std::time::UNIX_EPOCH;
+
+/// Serializes the check-and-rename step of writes made through the backend,
+/// so an agent write and an editor save can't interleave between them
+static WRITE_LOCK: StdMutex<()> = StdMutex::new(());
+
+/// Error prefix the frontend can match on to tell conflicts from I/O failures
+pub const CONFLICT_ERROR_PREFIX: &str = "Conflict:";
+
+/// Identifies the on-disk version of a file
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileStamp {
+    /// Modification time in milliseconds since the Unix epoch
+    pub mtime: u64,
+    /// Hex encoded SHA-256 of the file content
+    pub hash: String,
+    pub size: u64,
+}
+
+/// Optional expectations about the current on-disk version of a file.
+/// When set, the write fails with a conflict if the file changed since the
+/// caller read it.
+#[derive(Debug, Default, Clone)]
+pub struct WritePrecondition {
+    pub expected_mtime: Option<u64>,
+    pub expected_hash: Option<String>,
+}
+
+impl WritePrecondition {
+    fn is_empty(&self) -> bool {
+        self.expected_mtime.is_none() && self.expected_hash.is_none()
+    }
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn mtime_millis(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Build a stamp for content that was just read from `path`
+pub fn stamp_for(path: &Path, content: &[u8]) -> Result<FileStamp, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("Failed to read metadata: {}", e))?;
+    Ok(FileStamp {
+        mtime: mtime_millis(&metadata),
+        hash: hash_bytes(content),
+        size: content.len() as u64,
+    })
+}
+
+/// Read the current stamp of a file from disk
+pub fn file_stamp(path: &Path) -> Result<FileStamp, String> {
+    let content = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    stamp_for(path, &content)
+}
+
+fn check_precondition(path: &Path, precondition: &WritePrecondition) -> Result<(), String> {
+    if precondition.is_empty() {
+        return Ok(());
+    }
+
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(format!(
+                "{} {} was deleted on disk since it was read",
+                CONFLICT_ERROR_PREFIX,
+                path.display()
+            ));
+        }
+        Err(e) => return Err(format!("Failed to read metadata: {}", e)),
+    };
+
+    if let Some(expected_mtime) = precondition.expected_mtime {
+        // Only trust an mtime mismatch when no hash was given; some editors
+        // and tools touch files without changing them
+        if mtime_millis(&metadata) != expected_mtime && precondition.expected_hash.is_none() {
+            return Err(format!(
+                "{} {} was modified on disk since it was read",
+                CONFLICT_ERROR_PREFIX,
+                path.display()
+            ));
+        }
+    }
+
+    if let Some(expected_hash) = &precondition.expected_hash {
+        let current = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+        if !hash_bytes(&current).eq_ignore_ascii_case(expected_hash) {
+            return Err(format!(
+                "{} {} was modified on disk since it was read",
+                CONFLICT_ERROR_PREFIX,
+                path.display()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Write `content` to `path` through a temp file in the same directory that is
+/// renamed over the target, so readers never observe a partially written file.
+/// Permissions of an existing target are carried over to the new file.
+pub fn write_atomic(
+    path: &Path,
+    content: &[u8],
+    precondition: &WritePrecondition,
+) -> Result<FileStamp, String> {
+    // Follow symlinks so the link itself stays in place
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let parent = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    // Check if the parent directory exists, create it if it doesn't
+    if !parent.exists() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create parent directory: {}", e))?;
+    }
+
+    let file_name = target
+        .file_name()
+        .ok_or_else(|| format!("Invalid file path: {}", path.display()))?
+        .to_string_lossy();
+    let temp_path = parent.join(format!(".{}.{}.tmp", file_name, nanoid::nanoid!(8)));
+
+    let write_temp = || -> io::Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(content)?;
+        file.sync_all()
+    };
+    if let Err(e) = write_temp() {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Failed to write file: {}", e));
+    }
+
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let result = check_precondition(path, precondition).and_then(|_| {
+        if let Ok(metadata) = fs::metadata(&target) {
+            fs::set_permissions(&temp_path, metadata.permissions())
+                .map_err(|e| format!("Failed to preserve permissions: {}", e))?;
+        }
+        fs::rename(&temp_path, &target).map_err(|e| format!("Failed to write file: {}", e))
+    });
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    stamp_for(path, content)
+}
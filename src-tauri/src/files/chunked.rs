@@ -0,0 +1,444 @@
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex as StdMutex, RwLock as StdRwLock};
+use std::time::SystemTime;
+use tauri::http::{header, response::Builder, Request, Response, StatusCode};
+use tauri::Manager;
+use tauri_plugin_fs::FsExt;
+
+use crate::files::encoding;
+
+/// URI scheme that serves local files to the webview with HTTP range support.
+/// Use `raven-file://localhost/<percent-encoded path>` (or
+/// `http://raven-file.localhost/<path>` on Windows). Only files inside a root
+/// registered with `allow_protocol_root`, or inside the Tauri fs scope but
+/// outside the app data directory, are served.
+pub const FILE_PROTOCOL: &str = "raven-file";
+
+/// Every Nth line start is remembered, so seeking to a line reads at most N-1 lines
+const CHECKPOINT_INTERVAL: usize = 1024;
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+const MAX_CACHED_INDEXES: usize = 32;
+/// Bytes inspected by `sniff_file`
+const SNIFF_LEN: usize = 8192;
+/// Largest body returned by one protocol response; bigger files are served in ranges
+const MAX_PROTOCOL_CHUNK: u64 = 8 * 1024 * 1024;
+
+struct LineIndex {
+    len: u64,
+    modified: Option<SystemTime>,
+    /// Byte offset of line 0, CHECKPOINT_INTERVAL, 2 * CHECKPOINT_INTERVAL, ...
+    checkpoints: Vec<u64>,
+    /// Number of lines as counted by `str::lines`
+    line_count: usize,
+}
+
+/// Canonical workspace roots the file protocol may serve from, once per
+/// registration so that each `revoke_protocol_root` undoes one allow
+static PROTOCOL_ROOTS: LazyLock<StdRwLock<Vec<PathBuf>>> =
+    LazyLock::new(|| StdRwLock::new(Vec::new()));
+
+static LINE_INDEXES: LazyLock<StdMutex<HashMap<PathBuf, Arc<LineIndex>>>> =
+    LazyLock::new(|| StdMutex::new(HashMap::new()));
+
+fn build_line_index(path: &Path, metadata: &fs::Metadata) -> io::Result<LineIndex> {
+    let mut reader = BufReader::with_capacity(READ_BUFFER_SIZE, File::open(path)?);
+    let mut checkpoints = vec![0];
+    let mut newlines = 0;
+    let mut offset = 0u64;
+    let mut last_byte = None;
+
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        for (i, byte) in buf.iter().enumerate() {
+            if *byte == b'\n' {
+                newlines += 1;
+                if newlines % CHECKPOINT_INTERVAL == 0 {
+                    checkpoints.push(offset + i as u64 + 1);
+                }
+            }
+        }
+        last_byte = buf.last().copied();
+        let n = buf.len();
+        offset += n as u64;
+        reader.consume(n);
+    }
+
+    let line_count = match last_byte {
+        None => 0,
+        Some(b'\n') => newlines,
+        Some(_) => newlines + 1,
+    };
+
+    Ok(LineIndex {
+        len: offset,
+        modified: metadata.modified().ok(),
+        checkpoints,
+        line_count,
+    })
+}
+
+/// Get the cached line index for a file, rebuilding it if the file changed
+fn line_index(path: &Path) -> Result<Arc<LineIndex>, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("Failed to read metadata: {}", e))?;
+    let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    {
+        let indexes = LINE_INDEXES.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(index) = indexes.get(&key) {
+            if index.len == metadata.len() && index.modified == metadata.modified().ok() {
+                return Ok(index.clone());
+            }
+        }
+    }
+
+    let index = Arc::new(
+        build_line_index(path, &metadata).map_err(|e| format!("Failed to index file: {}", e))?,
+    );
+
+    let mut indexes = LINE_INDEXES.lock().unwrap_or_else(|e| e.into_inner());
+    if indexes.len() >= MAX_CACHED_INDEXES && !indexes.contains_key(&key) {
+        if let Some(evicted) = indexes.keys().next().cloned() {
+            indexes.remove(&evicted);
+        }
+    }
+    indexes.insert(key, index.clone());
+    Ok(index)
+}
+
+pub fn line_count(path: &Path) -> Result<usize, String> {
+    line_index(path).map(|index| index.line_count)
+}
+
+/// Read lines `start..end` (0-based, end exclusive) joined with "\n", without
+/// loading the rest of the file
+pub fn read_lines(path: &Path, start: usize, end: usize) -> Result<String, String> {
+    let index = line_index(path)?;
+
+    // Ensure start and end are within bounds
+    let start = start.min(index.line_count);
+    let end = end.min(index.line_count);
+
+    if start >= end {
+        return Ok(String::new());
+    }
+
+    let checkpoint = start / CHECKPOINT_INTERVAL;
+    let mut file = File::open(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    file.seek(SeekFrom::Start(index.checkpoints[checkpoint]))
+        .map_err(|e| format!("Failed to seek file: {}", e))?;
+    let mut reader = BufReader::with_capacity(READ_BUFFER_SIZE, file);
+
+    let mut line = Vec::new();
+    for _ in checkpoint * CHECKPOINT_INTERVAL..start {
+        line.clear();
+        reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+    }
+
+    let mut lines = Vec::with_capacity(end - start);
+    for _ in start..end {
+        line.clear();
+        let n = reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        if line.last() == Some(&b'\n') {
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+        }
+        lines.push(String::from_utf8_lossy(&line).into_owned());
+    }
+
+    Ok(lines.join("\n"))
+}
+
+fn read_range(path: &Path, offset: u64, length: u64) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut bytes = Vec::with_capacity(length.min(MAX_PROTOCOL_CHUNK) as usize);
+    file.take(length).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Read up to `length` bytes starting at `offset`
+pub fn read_bytes(path: &Path, offset: u64, length: u64) -> Result<Vec<u8>, String> {
+    read_range(path, offset, length).map_err(|e| format!("Failed to read file: {}", e))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSniff {
+    pub size: u64,
+    pub is_binary: bool,
+    /// Detected text encoding, None for binary files
+    pub encoding: Option<String>,
+    pub mime_type: String,
+}
+
+pub(crate) fn looks_binary(head: &[u8]) -> bool {
+    if encoding::sniff_utf16(head).is_some() {
+        return false;
+    }
+    if head.contains(&0) {
+        return true;
+    }
+    // Text rarely has control characters other than whitespace and escapes
+    let control = head
+        .iter()
+        .filter(|b| **b < 0x20 && !matches!(**b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    control * 10 > head.len()
+}
+
+fn mime_type(path: &Path, head: &[u8], is_binary: bool) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+    ];
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return mime;
+    }
+    if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("svg") => "image/svg+xml",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("mp3") => "audio/mpeg",
+        Some("wav") => "audio/wav",
+        Some("json") => "application/json",
+        Some("html" | "htm") => "text/html",
+        Some("css") => "text/css",
+        Some("js" | "mjs") => "text/javascript",
+        _ if is_binary => "application/octet-stream",
+        _ => "text/plain",
+    }
+}
+
+/// Classify a file as text or binary from its first few kilobytes
+pub fn sniff_file(path: &Path) -> Result<FileSniff, String> {
+    let size = fs::metadata(path)
+        .map_err(|e| format!("Failed to read metadata: {}", e))?
+        .len();
+    let head = read_bytes(path, 0, SNIFF_LEN as u64)?;
+    let is_binary = looks_binary(&head);
+
+    Ok(FileSniff {
+        size,
+        is_binary,
+        encoding: (!is_binary).then(|| encoding::decode(&head).1.encoding),
+        mime_type: mime_type(path, &head, is_binary).to_string(),
+    })
+}
+
+/// Parse a single `bytes=` range into an inclusive (start, end) pair.
+/// Returns Err(()) when the range can't be satisfied.
+fn parse_range(value: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    // Only the first range of a multi-range request is served
+    let (start, end) = spec.split(',').next()?.trim().split_once('-')?;
+
+    let range = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 || len == 0 {
+                return Some(Err(()));
+            }
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (start, end) => {
+            let start: u64 = start.parse().ok()?;
+            let end = if end.is_empty() {
+                len.saturating_sub(1)
+            } else {
+                end.parse::<u64>().ok()?.min(len.saturating_sub(1))
+            };
+            if start >= len || start > end {
+                return Some(Err(()));
+            }
+            (start, end)
+        }
+    };
+
+    Some(Ok(range))
+}
+
+/// Let the file protocol serve files under `root`, e.g. an opened workspace
+pub fn allow_protocol_root(root: &Path) -> Result<(), String> {
+    let root = root
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", root.display(), e))?;
+    PROTOCOL_ROOTS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(root);
+    Ok(())
+}
+
+pub fn revoke_protocol_root(root: &Path) {
+    let Ok(root) = root.canonicalize() else {
+        return;
+    };
+    let mut roots = PROTOCOL_ROOTS.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(index) = roots.iter().position(|allowed| *allowed == root) {
+        roots.remove(index);
+    }
+}
+
+/// Whether `path` (already canonical) may be served: inside a registered root,
+/// or inside the fs scope but not the app's own data such as its database
+fn is_servable(app: &tauri::AppHandle, path: &Path) -> bool {
+    if PROTOCOL_ROOTS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .any(|root| path.starts_with(root))
+    {
+        return true;
+    }
+    let in_app_data = app
+        .path()
+        .app_data_dir()
+        .ok()
+        .and_then(|dir| dir.canonicalize().ok())
+        .is_some_and(|dir| path.starts_with(dir));
+    !in_app_data
+        && app
+            .try_fs_scope()
+            .is_some_and(|scope| scope.is_allowed(path))
+}
+
+/// Finish a response, falling back to a bare 500 if the builder was given an
+/// invalid header
+fn respond(builder: Builder, body: Vec<u8>) -> Response<Vec<u8>> {
+    builder.body(body).unwrap_or_else(|e| {
+        let mut response = Response::new(format!("Failed to build response: {}", e).into_bytes());
+        *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        response
+    })
+}
+
+fn error_response(status: StatusCode, message: String) -> Response<Vec<u8>> {
+    respond(
+        Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, "text/plain"),
+        message.into_bytes(),
+    )
+}
+
+/// Serve a local file for the `raven-file` protocol, honoring `Range` headers.
+/// Ranged responses are capped at MAX_PROTOCOL_CHUNK bytes and clients read
+/// large files by following the returned `Content-Range`; a request without a
+/// `Range` gets the whole file.
+pub fn handle_file_protocol(
+    app: &tauri::AppHandle,
+    request: &Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    if request.method() != tauri::http::Method::GET && request.method() != tauri::http::Method::HEAD
+    {
+        return respond(
+            Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(header::ALLOW, "GET, HEAD"),
+            Vec::new(),
+        );
+    }
+
+    let decoded = percent_decode_str(request.uri().path()).decode_utf8_lossy();
+    // "/C:/Users/..." -> "C:/Users/..."
+    #[cfg(windows)]
+    let decoded = decoded.trim_start_matches('/');
+    // Resolves "..", symlinks and the like before the scope check
+    let path = match Path::new(decoded.as_ref() as &str).canonicalize() {
+        Ok(path) => path,
+        Err(e) => return error_response(StatusCode::NOT_FOUND, format!("{}", e)),
+    };
+    if !is_servable(app, &path) {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            "Path is outside the allowed roots".to_string(),
+        );
+    }
+
+    let metadata = match fs::metadata(&path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        Ok(_) => return error_response(StatusCode::NOT_FOUND, "Not a file".to_string()),
+        Err(e) => return error_response(StatusCode::NOT_FOUND, format!("{}", e)),
+    };
+    let len = metadata.len();
+
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_range(value, len));
+
+    let (start, end, partial) = match range {
+        Some(Ok((start, end))) => (start, end.min(start + MAX_PROTOCOL_CHUNK - 1), true),
+        Some(Err(())) => {
+            return respond(
+                Response::builder()
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{}", len)),
+                Vec::new(),
+            );
+        }
+        None => (0, len.saturating_sub(1), false),
+    };
+
+    let body = if len == 0 || request.method() == tauri::http::Method::HEAD {
+        Vec::new()
+    } else {
+        match read_range(&path, start, end - start + 1) {
+            Ok(bytes) => bytes,
+            Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e)),
+        }
+    };
+
+    let head = read_range(&path, 0, SNIFF_LEN as u64).unwrap_or_default();
+    let mime = mime_type(&path, &head, looks_binary(&head));
+
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, mime)
+        .header(header::ACCEPT_RANGES, "bytes");
+    if partial {
+        builder = builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", start, end, len),
+            )
+            .header(header::CONTENT_LENGTH, end - start + 1);
+    } else {
+        builder = builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, len);
+    }
+
+    respond(builder, body)
+}
@@ -0,0 +1,376 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex as StdMutex;
+use tauri::Emitter;
+
+use crate::files::atomic::{self, FileStamp, WritePrecondition};
+use crate::files::encoding::{self, TextFile};
+use crate::files::watcher;
+
+/// The document changed on disk and the buffer had no unsaved edits
+pub const DOCUMENT_CHANGED_EVENT: &str = "document_changed";
+/// The document changed on disk while the buffer had unsaved edits
+pub const DOCUMENT_CONFLICT_EVENT: &str = "document_conflict";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeRegionKind {
+    /// Only the disk version changed these lines
+    Disk,
+    /// Only the buffer changed these lines
+    Buffer,
+    /// Both changed these lines the same way
+    Both,
+    Conflict,
+}
+
+/// A run of lines that differs from the base in the disk version, the buffer
+/// or both. Line numbers are 1-based and point at where the run starts in
+/// each version.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeRegion {
+    pub kind: MergeRegionKind,
+    pub base_line: usize,
+    pub disk_line: usize,
+    pub buffer_line: usize,
+    pub base: String,
+    pub disk: String,
+    pub buffer: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreeWayDiff {
+    pub regions: Vec<MergeRegion>,
+    pub conflicts: usize,
+    /// Both sides' changes combined, when none of them conflict
+    pub merged: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentChangedPayload {
+    pub path: String,
+    /// "modified" or "removed"
+    pub change: &'static str,
+    pub content: Option<String>,
+    pub stamp: Option<FileStamp>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentConflictPayload {
+    pub path: String,
+    pub disk_content: String,
+    pub disk_stamp: FileStamp,
+    pub diff: ThreeWayDiff,
+}
+
+struct OpenDocument {
+    /// Content as of the last open or save, the common ancestor for merges
+    base: String,
+    stamp: FileStamp,
+    /// Unsaved edits, when the buffer differs from `base`
+    buffer: Option<String>,
+    /// Hash of the disk version last reported, so it is reported only once
+    reported_hash: Option<String>,
+    subscription: String,
+    window: tauri::Window,
+}
+
+static OPEN_DOCUMENTS: std::sync::LazyLock<StdMutex<HashMap<PathBuf, OpenDocument>>> =
+    std::sync::LazyLock::new(|| StdMutex::new(HashMap::new()));
+
+/// Base lines `start..end` replaced by new lines
+type LineChange<'a> = (usize, usize, Vec<&'a str>);
+
+/// Line changes of `other` against `base`
+fn line_changes<'a>(base: &str, other: &'a str) -> Result<Vec<LineChange<'a>>, String> {
+    let mut options = git2::DiffOptions::new();
+    options.context_lines(0).force_text(true);
+    let patch = git2::Patch::from_buffers(
+        base.as_bytes(),
+        None,
+        other.as_bytes(),
+        None,
+        Some(&mut options),
+    )
+    .map_err(|e| format!("Failed to diff document: {}", e))?;
+
+    let other_lines: Vec<&str> = other.split_inclusive('\n').collect();
+    let mut changes = Vec::new();
+    for index in 0..patch.num_hunks() {
+        let (hunk, _) = patch
+            .hunk(index)
+            .map_err(|e| format!("Failed to diff document: {}", e))?;
+        let (old_lines, new_lines) = (hunk.old_lines() as usize, hunk.new_lines() as usize);
+        // A side without lines reports the line it comes after
+        let start = hunk.old_start() as usize - usize::from(old_lines > 0);
+        let new_start = hunk.new_start() as usize - usize::from(new_lines > 0);
+        changes.push((
+            start,
+            start + old_lines,
+            other_lines[new_start..new_start + new_lines].to_vec(),
+        ));
+    }
+    Ok(changes)
+}
+
+/// Base lines `start..end` with `changes` applied
+fn apply_changes(base: &[&str], start: usize, end: usize, changes: &[LineChange]) -> String {
+    let mut text = String::new();
+    let mut cursor = start;
+    for (change_start, change_end, lines) in changes {
+        text.extend(base[cursor..*change_start].iter().copied());
+        text.extend(lines.iter().copied());
+        cursor = *change_end;
+    }
+    text.extend(base[cursor..end].iter().copied());
+    text
+}
+
+/// Three-way line diff of `disk` and `buffer` against their common `base`.
+/// Changes on both sides that overlap or touch are grouped into one region.
+pub fn three_way_diff(base: &str, disk: &str, buffer: &str) -> Result<ThreeWayDiff, String> {
+    let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let disk_changes = line_changes(base, disk)?;
+    let buffer_changes = line_changes(base, buffer)?;
+
+    let mut regions = Vec::new();
+    let mut merged = String::new();
+    let (mut d, mut b) = (0, 0);
+    let mut cursor = 0;
+    let (mut disk_line, mut buffer_line) = (1, 1);
+
+    loop {
+        let start = match (disk_changes.get(d), buffer_changes.get(b)) {
+            (Some(dc), Some(bc)) => dc.0.min(bc.0),
+            (Some(dc), None) => dc.0,
+            (None, Some(bc)) => bc.0,
+            (None, None) => break,
+        };
+        let (first_d, first_b) = (d, b);
+        let mut end = start;
+        loop {
+            let mut grew = false;
+            while let Some(change) = disk_changes.get(d).filter(|c| c.0 <= end) {
+                end = end.max(change.1);
+                d += 1;
+                grew = true;
+            }
+            while let Some(change) = buffer_changes.get(b).filter(|c| c.0 <= end) {
+                end = end.max(change.1);
+                b += 1;
+                grew = true;
+            }
+            if !grew {
+                break;
+            }
+        }
+
+        let unchanged = start - cursor;
+        merged.extend(base_lines[cursor..start].iter().copied());
+        disk_line += unchanged;
+        buffer_line += unchanged;
+
+        let disk_text = apply_changes(&base_lines, start, end, &disk_changes[first_d..d]);
+        let buffer_text = apply_changes(&base_lines, start, end, &buffer_changes[first_b..b]);
+        let kind = match (d > first_d, b > first_b) {
+            (true, false) => MergeRegionKind::Disk,
+            (false, true) => MergeRegionKind::Buffer,
+            _ if disk_text == buffer_text => MergeRegionKind::Both,
+            _ => MergeRegionKind::Conflict,
+        };
+        merged.push_str(match kind {
+            MergeRegionKind::Buffer => &buffer_text,
+            _ => &disk_text,
+        });
+
+        let region = MergeRegion {
+            kind,
+            base_line: start + 1,
+            disk_line,
+            buffer_line,
+            base: base_lines[start..end].concat(),
+            disk: disk_text,
+            buffer: buffer_text,
+        };
+        disk_line += region.disk.split_inclusive('\n').count();
+        buffer_line += region.buffer.split_inclusive('\n').count();
+        regions.push(region);
+        cursor = end;
+    }
+    merged.extend(base_lines[cursor..].iter().copied());
+
+    let conflicts = regions
+        .iter()
+        .filter(|r| r.kind == MergeRegionKind::Conflict)
+        .count();
+    Ok(ThreeWayDiff {
+        regions,
+        conflicts,
+        merged: (conflicts == 0).then_some(merged),
+    })
+}
+
+/// Read a document and track it, so external changes to it are reported as
+/// `document_changed` or `document_conflict` events to `window`. Opening a
+/// tracked document again reloads it and drops its unsaved edits.
+pub fn open(path: &Path, window: tauri::Window) -> Result<TextFile, String> {
+    let file = encoding::read_text_file(path)?;
+
+    let mut documents = OPEN_DOCUMENTS.lock().unwrap_or_else(|e| e.into_inner());
+    let subscription = match documents.remove(path) {
+        Some(document) => document.subscription,
+        None => {
+            let parent = path.parent().unwrap_or(Path::new("."));
+            let name = path
+                .file_name()
+                .ok_or_else(|| format!("Not a file: {}", path.display()))?;
+            watcher::subscribe(parent, &name.to_string_lossy(), window.clone())?
+        }
+    };
+    documents.insert(
+        path.to_path_buf(),
+        OpenDocument {
+            base: file.content.clone(),
+            stamp: file.stamp.clone(),
+            buffer: None,
+            reported_hash: None,
+            subscription,
+            window,
+        },
+    );
+    Ok(file)
+}
+
+/// Record the unsaved content of a document's buffer; `None` once it is clean
+pub fn set_buffer(path: &Path, content: Option<String>) -> Result<(), String> {
+    let mut documents = OPEN_DOCUMENTS.lock().unwrap_or_else(|e| e.into_inner());
+    let document = documents
+        .get_mut(path)
+        .ok_or_else(|| format!("Document not open: {}", path.display()))?;
+    document.buffer = content.filter(|content| *content != document.base);
+    Ok(())
+}
+
+/// Save a document, failing with a conflict if the file changed on disk since
+/// it was opened or last saved, unless `force` is set
+pub fn save(path: &Path, content: &str, force: bool) -> Result<FileStamp, String> {
+    let mut documents = OPEN_DOCUMENTS.lock().unwrap_or_else(|e| e.into_inner());
+    let document = documents
+        .get_mut(path)
+        .ok_or_else(|| format!("Document not open: {}", path.display()))?;
+
+    let precondition = WritePrecondition {
+        expected_mtime: None,
+        expected_hash: (!force).then(|| document.stamp.hash.clone()),
+    };
+    // The lock is held so the watcher sees the new hash when the write lands
+    let stamp = encoding::write_text_file(path, content, None, &precondition)?;
+    document.base = content.to_string();
+    document.stamp = stamp.clone();
+    document.buffer = None;
+    document.reported_hash = None;
+    Ok(stamp)
+}
+
+/// Stop tracking a document
+pub fn close(path: &Path) -> Result<(), String> {
+    let document = OPEN_DOCUMENTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(path);
+    match document {
+        Some(document) => watcher::unsubscribe(&document.subscription),
+        None => Ok(()),
+    }
+}
+
+fn check_document(path: &Path, document: &mut OpenDocument) -> Result<(), String> {
+    let display_path = path.to_string_lossy().to_string();
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            if document.reported_hash.as_deref() != Some("") {
+                document.reported_hash = Some(String::new());
+                let _ = document.window.emit(
+                    DOCUMENT_CHANGED_EVENT,
+                    DocumentChangedPayload {
+                        path: display_path,
+                        change: "removed",
+                        content: None,
+                        stamp: None,
+                    },
+                );
+            }
+            return Ok(());
+        }
+        Err(e) => return Err(format!("Failed to read file: {}", e)),
+    };
+
+    let hash = atomic::hash_bytes(&bytes);
+    if hash == document.stamp.hash {
+        // Our own save, a touch, or a change that was reverted
+        document.reported_hash = None;
+        return Ok(());
+    }
+    if document.reported_hash.as_ref() == Some(&hash) {
+        return Ok(());
+    }
+
+    let stamp = atomic::stamp_for(path, &bytes)?;
+    let (disk_content, _, _) = encoding::decode(&bytes);
+    match &document.buffer {
+        Some(buffer) if *buffer != disk_content => {
+            let diff = three_way_diff(&document.base, &disk_content, buffer)?;
+            document.reported_hash = Some(hash);
+            let _ = document.window.emit(
+                DOCUMENT_CONFLICT_EVENT,
+                DocumentConflictPayload {
+                    path: display_path,
+                    disk_content,
+                    disk_stamp: stamp,
+                    diff,
+                },
+            );
+        }
+        _ => {
+            // Nothing to lose: the buffer follows the disk
+            document.base = disk_content.clone();
+            document.stamp = stamp.clone();
+            document.buffer = None;
+            document.reported_hash = None;
+            let _ = document.window.emit(
+                DOCUMENT_CHANGED_EVENT,
+                DocumentChangedPayload {
+                    path: display_path,
+                    change: "modified",
+                    content: Some(disk_content),
+                    stamp: Some(stamp),
+                },
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Compare tracked documents among `paths` with their disk content and report
+/// the ones that really changed. Called by the watcher for every batch.
+pub(crate) fn disk_changed(paths: &[&Path]) {
+    let mut documents = OPEN_DOCUMENTS.lock().unwrap_or_else(|e| e.into_inner());
+    if documents.is_empty() {
+        return;
+    }
+    for path in paths {
+        if let Some(document) = documents.get_mut(*path) {
+            if let Err(e) = check_document(path, document) {
+                eprintln!("Failed to check document {}: {}", path.display(), e);
+            }
+        }
+    }
+}
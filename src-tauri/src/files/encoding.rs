@@ -0,0 +1,243 @@
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+use crate::files::atomic::{self, FileStamp, WritePrecondition};
+
+/// How many leading bytes are inspected when sniffing BOM-less UTF-16
+const UTF16_SNIFF_LEN: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    /// Both styles appear; content is written back untouched
+    Mixed,
+    /// The file has no line breaks
+    None,
+}
+
+/// Encoding metadata detected on read and used to round-trip the file on write
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextEncoding {
+    /// WHATWG encoding name, e.g. "UTF-8", "UTF-16LE", "GBK", "Shift_JIS", "windows-1252"
+    pub encoding: String,
+    pub has_bom: bool,
+    pub line_ending: LineEnding,
+}
+
+impl Default for TextEncoding {
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8.name().to_string(),
+            has_bom: false,
+            line_ending: LineEnding::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextFile {
+    pub content: String,
+    #[serde(flatten)]
+    pub encoding: TextEncoding,
+    /// True when some bytes could not be decoded and were replaced with U+FFFD
+    pub had_errors: bool,
+    pub stamp: FileStamp,
+}
+
+pub fn detect_line_ending(text: &str) -> LineEnding {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    match (lf, crlf) {
+        (0, 0) => LineEnding::None,
+        (_, 0) => LineEnding::Lf,
+        (0, _) => LineEnding::Crlf,
+        _ => LineEnding::Mixed,
+    }
+}
+
+/// Guess UTF-16 without a BOM from the distribution of zero bytes, which is
+/// lopsided for text that is mostly ASCII
+pub(crate) fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(UTF16_SNIFF_LEN) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+
+    let pairs = sample.len() / 2;
+    let even_zeros = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|b| **b == 0)
+        .count();
+
+    if odd_zeros * 10 >= pairs * 4 && even_zeros * 20 < pairs {
+        Some(UTF_16LE)
+    } else if even_zeros * 10 >= pairs * 4 && odd_zeros * 20 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Encoding of `bytes` and the length of its BOM, if any
+pub(crate) fn detect_encoding(bytes: &[u8]) -> (&'static Encoding, usize) {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        return (encoding, bom_len);
+    }
+    // ASCII-heavy UTF-16 is also valid UTF-8, so sniff it first
+    if let Some(encoding) = sniff_utf16(bytes) {
+        return (encoding, 0);
+    }
+    match std::str::from_utf8(bytes) {
+        // A sequence cut off at the very end still counts, since callers may
+        // pass only the head of a file
+        Ok(_) => return (UTF_8, 0),
+        Err(e) if e.error_len().is_none() => return (UTF_8, 0),
+        Err(_) => {}
+    }
+
+    let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+    detector.feed(bytes, true);
+    (detector.guess(None, Utf8Detection::Allow), 0)
+}
+
+/// Decode raw file bytes, detecting the BOM, encoding and line endings
+pub fn decode(bytes: &[u8]) -> (String, TextEncoding, bool) {
+    let (encoding, bom_len) = detect_encoding(bytes);
+    let (content, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+    let line_ending = detect_line_ending(&content);
+
+    (
+        content.into_owned(),
+        TextEncoding {
+            encoding: encoding.name().to_string(),
+            has_bom: bom_len > 0,
+            line_ending,
+        },
+        had_errors,
+    )
+}
+
+fn convert_line_endings<'a>(content: &'a str, line_ending: LineEnding) -> Cow<'a, str> {
+    match line_ending {
+        LineEnding::Lf if content.contains("\r\n") => Cow::Owned(content.replace("\r\n", "\n")),
+        LineEnding::Crlf => {
+            let normalized = content.replace("\r\n", "\n");
+            if normalized.contains('\n') {
+                Cow::Owned(normalized.replace('\n', "\r\n"))
+            } else {
+                Cow::Borrowed(content)
+            }
+        }
+        _ => Cow::Borrowed(content),
+    }
+}
+
+/// Encode text for writing, applying the requested line endings and BOM.
+/// Fails with an "Unrepresentable:" error when the content has characters the
+/// encoding cannot represent, so the editor can offer saving as UTF-8.
+pub fn encode(content: &str, target: &TextEncoding) -> Result<Vec<u8>, String> {
+    let encoding = Encoding::for_label(target.encoding.as_bytes())
+        .ok_or_else(|| format!("Unknown encoding: {}", target.encoding))?;
+    encode_with(content, encoding, target).ok_or_else(|| {
+        format!(
+            "Unrepresentable: content contains characters that cannot be represented in {}",
+            encoding.name()
+        )
+    })
+}
+
+/// Encode text with the encoding detected for an existing file, switching to
+/// UTF-8 when the detected encoding cannot represent it. Detection on short
+/// files easily guesses a legacy encoding, which must not block saving.
+pub fn encode_detected(content: &str, detected: &TextEncoding) -> Result<Vec<u8>, String> {
+    let encoding = Encoding::for_label(detected.encoding.as_bytes())
+        .ok_or_else(|| format!("Unknown encoding: {}", detected.encoding))?;
+    Ok(encode_with(content, encoding, detected).unwrap_or_else(|| {
+        convert_line_endings(content, detected.line_ending)
+            .into_owned()
+            .into_bytes()
+    }))
+}
+
+/// `None` when `content` is not representable in `encoding`
+fn encode_with(
+    content: &str,
+    encoding: &'static Encoding,
+    target: &TextEncoding,
+) -> Option<Vec<u8>> {
+    let content = convert_line_endings(content, target.line_ending);
+
+    let mut bytes = Vec::with_capacity(content.len() + 3);
+    // encoding_rs only decodes UTF-16, so those are encoded by hand
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let little_endian = encoding == UTF_16LE;
+        if target.has_bom {
+            bytes.extend_from_slice(if little_endian {
+                &[0xFF, 0xFE]
+            } else {
+                &[0xFE, 0xFF]
+            });
+        }
+        for unit in content.encode_utf16() {
+            if little_endian {
+                bytes.extend_from_slice(&unit.to_le_bytes());
+            } else {
+                bytes.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+        return Some(bytes);
+    }
+
+    if target.has_bom && encoding == UTF_8 {
+        bytes.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+    }
+    let (encoded, _, had_errors) = encoding.encode(&content);
+    if had_errors {
+        return None;
+    }
+    bytes.extend_from_slice(&encoded);
+    Some(bytes)
+}
+
+pub fn read_text_file(path: &Path) -> Result<TextFile, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let stamp = atomic::stamp_for(path, &bytes)?;
+    let (content, encoding, had_errors) = decode(&bytes);
+    Ok(TextFile {
+        content,
+        encoding,
+        had_errors,
+        stamp,
+    })
+}
+
+/// Write text, keeping the encoding and line endings of the existing file when
+/// `encoding` is not given, or switching to UTF-8 when the existing encoding
+/// cannot represent the content. New files are written as UTF-8 with the
+/// content's own line endings.
+pub fn write_text_file(
+    path: &Path,
+    content: &str,
+    encoding: Option<TextEncoding>,
+    precondition: &WritePrecondition,
+) -> Result<FileStamp, String> {
+    let bytes = match encoding {
+        Some(encoding) => encode(content, &encoding)?,
+        None => match fs::read(path) {
+            Ok(existing) => encode_detected(content, &decode(&existing).1)?,
+            Err(_) => encode(content, &TextEncoding::default())?,
+        },
+    };
+    atomic::write_atomic(path, &bytes, precondition)
+}
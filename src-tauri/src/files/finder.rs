@@ -0,0 +1,447 @@
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_LIMIT: usize = 200;
+
+/// Indexes that no watcher keeps up to date are rebuilt after this long
+const UNWATCHED_INDEX_TTL: Duration = Duration::from_secs(10);
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_SEGMENT: i64 = 10;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 5;
+const BONUS_BASENAME: i64 = 24;
+const BONUS_BASENAME_EXACT: i64 = 48;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+
+/// Paths longer than this are matched on their basename only, to bound the DP
+const MAX_SCORED_LEN: usize = 512;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileSuggestion {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    pub score: i64,
+    /// Char indices into `path` that matched the query, for highlighting
+    pub match_indices: Vec<usize>,
+}
+
+struct FileIndex {
+    /// Relative path -> is_dir
+    entries: BTreeMap<String, bool>,
+    built_at: Instant,
+}
+
+struct IndexSlot {
+    index: Option<FileIndex>,
+    /// Number of active watchers delivering events for this root
+    watchers: usize,
+}
+
+static FILE_INDEXES: std::sync::LazyLock<StdMutex<HashMap<PathBuf, Arc<StdMutex<IndexSlot>>>>> =
+    std::sync::LazyLock::new(|| StdMutex::new(HashMap::new()));
+
+fn normalize_root(root: &Path) -> PathBuf {
+    root.components().collect()
+}
+
+fn slot_for(root: &Path) -> Arc<StdMutex<IndexSlot>> {
+    FILE_INDEXES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(normalize_root(root))
+        .or_insert_with(|| {
+            Arc::new(StdMutex::new(IndexSlot {
+                index: None,
+                watchers: 0,
+            }))
+        })
+        .clone()
+}
+
+fn walker(path: &Path) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(path);
+    builder
+        .hidden(true)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true);
+    builder
+}
+
+fn relative(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?.to_string_lossy().to_string();
+    (!relative.is_empty()).then_some(relative)
+}
+
+fn build_index(root: &Path) -> FileIndex {
+    let entries = walker(root)
+        .build()
+        .flatten()
+        .filter_map(|entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            relative(root, entry.path()).map(|path| (path, is_dir))
+        })
+        .collect();
+    FileIndex {
+        entries,
+        built_at: Instant::now(),
+    }
+}
+
+fn remove_subtree(entries: &mut BTreeMap<String, bool>, relative: &str) {
+    entries.remove(relative);
+    let prefix = format!("{}{}", relative, MAIN_SEPARATOR);
+    let nested: Vec<String> = entries
+        .range(prefix.clone()..)
+        .take_while(|(path, _)| path.starts_with(&prefix))
+        .map(|(path, _)| path.clone())
+        .collect();
+    for path in nested {
+        entries.remove(&path);
+    }
+}
+
+/// Add `path` (and its subtree) to the index unless it is hidden or ignored
+fn add_path(root: &Path, entries: &mut BTreeMap<String, bool>, path: &Path) {
+    let Some(relative_path) = relative(root, path) else {
+        return;
+    };
+    let Some(parent) = path.parent() else {
+        return;
+    };
+    // A parent missing from the index means it is ignored itself
+    if let Some(parent_relative) = relative(root, parent) {
+        if entries.get(&parent_relative) != Some(&true) {
+            return;
+        }
+    }
+
+    // Walking the parent one level deep applies the same ignore rules as a full walk
+    let entry = walker(parent)
+        .max_depth(Some(1))
+        .build()
+        .flatten()
+        .find(|entry| entry.path() == path);
+    let Some(entry) = entry else {
+        return;
+    };
+
+    let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+    entries.insert(relative_path, is_dir);
+    if is_dir {
+        for nested in walker(path).build().flatten().skip(1) {
+            if let Some(nested_relative) = relative(root, nested.path()) {
+                let nested_is_dir = nested.file_type().is_some_and(|t| t.is_dir());
+                entries.insert(nested_relative, nested_is_dir);
+            }
+        }
+    }
+}
+
+/// Apply created, removed or renamed paths reported by a watcher to every
+/// cached index that contains them
+pub fn update_index(paths: &[PathBuf]) {
+    let slots: Vec<(PathBuf, Arc<StdMutex<IndexSlot>>)> = FILE_INDEXES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(root, slot)| (root.clone(), slot.clone()))
+        .collect();
+
+    for (root, slot) in slots {
+        let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(index) = slot.index.as_mut() else {
+            continue;
+        };
+        for path in paths {
+            let Some(relative_path) = relative(&root, path) else {
+                continue;
+            };
+            remove_subtree(&mut index.entries, &relative_path);
+            if path.symlink_metadata().is_ok() {
+                add_path(&root, &mut index.entries, path);
+            }
+        }
+    }
+}
+
+/// Mark `root` as kept up to date by a watcher, so its index never expires
+pub fn watch_started(root: &Path) {
+    slot_for(root)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .watchers += 1;
+}
+
+pub fn watch_stopped(root: &Path) {
+    let slot = slot_for(root);
+    let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+    slot.watchers = slot.watchers.saturating_sub(1);
+}
+
+fn char_bonus(prev: Option<char>, current: char) -> i64 {
+    match prev {
+        None | Some('/') | Some('\\') => BONUS_SEGMENT,
+        Some('_' | '-' | '.' | ' ') => BONUS_BOUNDARY,
+        Some(prev) if prev.is_lowercase() && current.is_uppercase() => BONUS_CAMEL,
+        Some(prev) if !prev.is_ascii_digit() && current.is_ascii_digit() => BONUS_CAMEL,
+        _ => 0,
+    }
+}
+
+fn chars_equal(a: char, b: char, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        a == b
+    } else {
+        a == b || a.to_lowercase().eq(b.to_lowercase())
+    }
+}
+
+fn is_subsequence(text: &[char], query: &[char], case_sensitive: bool) -> bool {
+    let mut remaining = query.iter().peekable();
+    for &c in text {
+        match remaining.peek() {
+            Some(&&q) if chars_equal(c, q, case_sensitive) => {
+                remaining.next();
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+    remaining.peek().is_none()
+}
+
+/// Best alignment of `query` in `text`, scored fzf-style: matches at segment
+/// starts, after separators and on camelCase humps score higher, consecutive
+/// matches are rewarded and gaps are penalized.
+fn fuzzy_match(text: &[char], query: &[char], case_sensitive: bool) -> Option<(i64, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    if !is_subsequence(text, query, case_sensitive) {
+        return None;
+    }
+
+    let n = text.len();
+    let m = query.len();
+    const NONE: i64 = i64::MIN / 2;
+    // score[i][j]: best score with query[i] matched at text[j]
+    let mut score = vec![vec![NONE; n]; m];
+    // from[i][j]: where query[i - 1] was matched on that best path
+    let mut from = vec![vec![usize::MAX; n]; m];
+
+    for i in 0..m {
+        // Best previous-row score reachable through a gap, with its column
+        let mut gap: (i64, usize) = (NONE, usize::MAX);
+        for j in 0..n {
+            if i > 0 && j >= 2 && score[i - 1][j - 2] > NONE {
+                let candidate = score[i - 1][j - 2] - PENALTY_GAP_START;
+                gap = if candidate >= gap.0 - PENALTY_GAP_EXTENSION {
+                    (candidate, j - 2)
+                } else {
+                    (gap.0 - PENALTY_GAP_EXTENSION, gap.1)
+                };
+            }
+            if !chars_equal(text[j], query[i], case_sensitive) {
+                continue;
+            }
+            let base = SCORE_MATCH + char_bonus(j.checked_sub(1).map(|k| text[k]), text[j]);
+            if i == 0 {
+                score[i][j] = base;
+                continue;
+            }
+            let consecutive = (j >= 1 && score[i - 1][j - 1] > NONE)
+                .then(|| score[i - 1][j - 1] + BONUS_CONSECUTIVE);
+            match consecutive {
+                Some(value) if value >= gap.0 => {
+                    score[i][j] = value + base;
+                    from[i][j] = j - 1;
+                }
+                _ if gap.0 > NONE => {
+                    score[i][j] = gap.0 + base;
+                    from[i][j] = gap.1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let (mut column, best) = score[m - 1]
+        .iter()
+        .enumerate()
+        .max_by_key(|(j, s)| (**s, std::cmp::Reverse(*j)))?;
+    let best = *best;
+    if best <= NONE {
+        return None;
+    }
+
+    let mut positions = vec![0; m];
+    for i in (0..m).rev() {
+        positions[i] = column;
+        column = from[i][column];
+    }
+    Some((best, positions))
+}
+
+/// Score `path` against whitespace separated query terms; every term has to
+/// match. Terms with uppercase letters are case sensitive.
+fn score_path(path: &str, terms: &[Vec<char>]) -> Option<(i64, Vec<usize>)> {
+    let chars: Vec<char> = path.chars().collect();
+    let basename_start = chars
+        .iter()
+        .rposition(|c| *c == '/' || *c == '\\')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let basename = &chars[basename_start..];
+
+    let mut total = 0;
+    let mut indices = Vec::new();
+    for term in terms {
+        let case_sensitive = term.iter().any(|c| c.is_uppercase());
+
+        // Prefer matching within the file name over spreading across the path
+        let in_basename = fuzzy_match(basename, term, case_sensitive).map(|(score, positions)| {
+            let exact = basename.len() == term.len();
+            let bonus = if exact {
+                BONUS_BASENAME_EXACT
+            } else {
+                BONUS_BASENAME
+            };
+            (
+                score + bonus,
+                positions.into_iter().map(|p| p + basename_start).collect(),
+            )
+        });
+        let in_path = if chars.len() <= MAX_SCORED_LEN {
+            fuzzy_match(&chars, term, case_sensitive)
+        } else {
+            None
+        };
+
+        let (score, positions): (i64, Vec<usize>) = match (in_basename, in_path) {
+            (Some(a), Some(b)) => {
+                if a.0 >= b.0 {
+                    a
+                } else {
+                    b
+                }
+            }
+            (Some(a), None) => a,
+            (None, Some(b)) => b,
+            (None, None) => return None,
+        };
+        total += score;
+        indices.extend(positions);
+    }
+
+    indices.sort_unstable();
+    indices.dedup();
+    Some((total, indices))
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+fn suggestion(path: &str, is_dir: bool, score: i64, match_indices: Vec<usize>) -> FileSuggestion {
+    let name = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+    FileSuggestion {
+        name,
+        path: path.to_string(),
+        is_dir,
+        score,
+        match_indices,
+    }
+}
+
+fn search_index(
+    entries: &BTreeMap<String, bool>,
+    pattern: &str,
+    limit: usize,
+) -> Result<Vec<FileSuggestion>, String> {
+    let pattern = pattern.trim();
+
+    if pattern.is_empty() {
+        // Shallow entries first
+        let mut results: Vec<(&String, &bool)> = entries.iter().collect();
+        results.sort_by_key(|(path, _)| (path.matches(['/', '\\']).count(), path.len()));
+        return Ok(results
+            .into_iter()
+            .take(limit)
+            .map(|(path, is_dir)| suggestion(path, *is_dir, 0, Vec::new()))
+            .collect());
+    }
+
+    if is_glob(pattern) {
+        let glob =
+            glob::Pattern::new(pattern).map_err(|e| format!("Invalid glob pattern: {}", e))?;
+        let options = glob::MatchOptions {
+            case_sensitive: false,
+            require_literal_separator: false,
+            require_literal_leading_dot: false,
+        };
+        let mut results: Vec<FileSuggestion> = entries
+            .iter()
+            .filter(|(path, _)| glob.matches_with(path, options))
+            .map(|(path, is_dir)| suggestion(path, *is_dir, 0, Vec::new()))
+            .collect();
+        results.sort_by(|a, b| {
+            a.path
+                .len()
+                .cmp(&b.path.len())
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        results.truncate(limit);
+        return Ok(results);
+    }
+
+    let terms: Vec<Vec<char>> = pattern
+        .split_whitespace()
+        .map(|term| term.chars().collect())
+        .collect();
+    let mut results: Vec<FileSuggestion> = entries
+        .iter()
+        .filter_map(|(path, is_dir)| {
+            score_path(path, &terms)
+                .map(|(score, indices)| suggestion(path, *is_dir, score, indices))
+        })
+        .collect();
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.path.len().cmp(&b.path.len()))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    results.truncate(limit);
+    Ok(results)
+}
+
+/// Find files and directories under `root` matching `pattern`, either a glob
+/// (when it contains `*`, `?` or `[`) or a fuzzy query, best matches first.
+/// The walk is cached per root and kept current by the file watcher.
+pub fn find_files(root: &Path, pattern: &str, limit: usize) -> Result<Vec<FileSuggestion>, String> {
+    let slot = slot_for(root);
+    let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+
+    let stale = match &slot.index {
+        None => true,
+        Some(index) => slot.watchers == 0 && index.built_at.elapsed() > UNWATCHED_INDEX_TTL,
+    };
+    if stale {
+        slot.index = Some(build_index(root));
+    }
+
+    let index = slot.index.as_ref().unwrap();
+    search_index(&index.entries, pattern, limit)
+}
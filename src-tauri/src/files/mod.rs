@@ -0,0 +1,10 @@
+pub mod atomic;
+pub mod chunked;
+pub mod documents;
+pub mod encoding;
+pub mod finder;
+pub mod patch;
+pub mod search;
+pub mod transfer;
+pub mod trash;
+pub mod watcher;
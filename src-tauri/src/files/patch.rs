@@ -0,0 +1,1222 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::files::atomic::{self, WritePrecondition};
+use crate::files::encoding::{self, LineEnding, TextEncoding};
+
+/// Context lines that may be dropped from each end of a hunk when it doesn't match
+const DEFAULT_FUZZ: usize = 2;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyPatchRequest {
+    pub base_path: String,
+    /// Unified diff, possibly touching several files
+    pub patch: Option<String>,
+    /// Structured edits, applied after the patch
+    pub edits: Option<Vec<EditOperation>>,
+    pub fuzz: Option<usize>,
+    /// Validate and report per-hunk results without touching the disk
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum EditOperation {
+    /// Replace `search` with `replace`; `search` must match exactly once unless `all` is set
+    #[serde(rename_all = "camelCase")]
+    Replace {
+        path: String,
+        search: String,
+        replace: String,
+        #[serde(default)]
+        all: bool,
+    },
+    Create {
+        path: String,
+        content: String,
+    },
+    Delete {
+        path: String,
+    },
+    Rename {
+        from: String,
+        to: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HunkStatus {
+    Applied,
+    AppliedWithFuzz,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkResult {
+    pub index: usize,
+    pub status: HunkStatus,
+    /// 1-based line in the original file where the hunk was applied
+    pub line: Option<usize>,
+    /// Distance in lines from where the hunk header said it would be
+    pub offset: isize,
+    /// Number of context lines ignored at each end to make the hunk match
+    pub fuzz: usize,
+    /// True when the hunk only matched after ignoring whitespace differences
+    pub whitespace_insensitive: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileAction {
+    Modify,
+    Create,
+    Delete,
+    Rename,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilePatchResult {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub action: FileAction,
+    pub hunks: Vec<HunkResult>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyPatchResult {
+    /// True when every hunk and edit matched and the changes were written
+    /// (or would have been, for a dry run)
+    pub applied: bool,
+    pub files: Vec<FilePatchResult>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineKind {
+    Context,
+    Remove,
+    Add,
+}
+
+#[derive(Debug, Default)]
+struct Hunk {
+    old_start: usize,
+    old_count: Option<usize>,
+    new_count: Option<usize>,
+    lines: Vec<(LineKind, String)>,
+    /// "\ No newline at end of file" after the last old / new line
+    old_no_eol: bool,
+    new_no_eol: bool,
+}
+
+#[derive(Debug, Default)]
+struct FilePatch {
+    old_path: Option<String>,
+    new_path: Option<String>,
+    is_create: bool,
+    is_delete: bool,
+    is_rename: bool,
+    hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    fn action(&self) -> FileAction {
+        if self.is_create || self.old_path.is_none() {
+            FileAction::Create
+        } else if self.is_delete || self.new_path.is_none() {
+            FileAction::Delete
+        } else if self.is_rename || self.old_path != self.new_path {
+            FileAction::Rename
+        } else {
+            FileAction::Modify
+        }
+    }
+
+    fn display_path(&self) -> String {
+        self.new_path
+            .clone()
+            .or_else(|| self.old_path.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Strip the "a/" / "b/" prefix and trailing timestamp from a ---/+++ path
+fn parse_header_path(raw: &str, prefix: &str) -> Option<String> {
+    let raw = raw.split('\t').next().unwrap_or(raw).trim_end();
+    let raw = raw
+        .strip_prefix('"')
+        .and_then(|r| r.strip_suffix('"'))
+        .unwrap_or(raw);
+    if raw == "/dev/null" {
+        return None;
+    }
+    Some(raw.strip_prefix(prefix).unwrap_or(raw).to_string())
+}
+
+fn parse_hunk_header(line: &str) -> Option<Hunk> {
+    let ranges = line.strip_prefix("@@ ")?;
+    let ranges = &ranges[..ranges.find(" @@")?];
+    let (old, new) = ranges.split_once(' ')?;
+    let parse_range = |range: &str| -> Option<(usize, Option<usize>)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok())),
+            None => Some((range.parse().ok()?, Some(1))),
+        }
+    };
+    let (old_start, old_count) = parse_range(old.strip_prefix('-')?)?;
+    let (_, new_count) = parse_range(new.strip_prefix('+')?)?;
+    Some(Hunk {
+        old_start,
+        old_count,
+        new_count,
+        ..Default::default()
+    })
+}
+
+impl Hunk {
+    /// Whether the declared line counts are used up. Hunks without usable
+    /// counts are never considered complete.
+    fn is_complete(&self) -> bool {
+        match (self.old_count, self.new_count) {
+            (Some(old_count), Some(new_count)) => {
+                let old = self
+                    .lines
+                    .iter()
+                    .filter(|(k, _)| *k != LineKind::Add)
+                    .count();
+                let new = self
+                    .lines
+                    .iter()
+                    .filter(|(k, _)| *k != LineKind::Remove)
+                    .count();
+                old >= old_count && new >= new_count
+            }
+            _ => false,
+        }
+    }
+}
+
+fn finish_hunk(file: &mut FilePatch, mut hunk: Hunk) {
+    // Blank lines after the declared counts are separators, not context
+    if let (Some(old_count), Some(new_count)) = (hunk.old_count, hunk.new_count) {
+        loop {
+            let old = hunk
+                .lines
+                .iter()
+                .filter(|(k, _)| *k != LineKind::Add)
+                .count();
+            let new = hunk
+                .lines
+                .iter()
+                .filter(|(k, _)| *k != LineKind::Remove)
+                .count();
+            match hunk.lines.last() {
+                Some((LineKind::Context, text))
+                    if text.is_empty() && old > old_count && new > new_count =>
+                {
+                    hunk.lines.pop();
+                }
+                _ => break,
+            }
+        }
+    }
+    if !hunk.lines.is_empty() {
+        file.hunks.push(hunk);
+    }
+}
+
+/// Lenient unified diff parser. Hunk line counts are only used to trim
+/// trailing blank lines, so slightly malformed diffs still parse.
+fn parse_unified_diff(patch: &str) -> Result<Vec<FilePatch>, String> {
+    let mut files = Vec::new();
+    let mut file: Option<FilePatch> = None;
+    let mut hunk: Option<Hunk> = None;
+
+    let flush =
+        |file: &mut Option<FilePatch>, hunk: &mut Option<Hunk>, files: &mut Vec<FilePatch>| {
+            if let Some(mut f) = file.take() {
+                if let Some(h) = hunk.take() {
+                    finish_hunk(&mut f, h);
+                }
+                files.push(f);
+            }
+        };
+
+    for line in patch.lines() {
+        if let Some(current) = hunk.as_mut() {
+            // "--- " / "+++ " only start a new file once this hunk is complete
+            let in_body = !current.is_complete();
+            let parsed = match line.chars().next() {
+                Some(' ') => Some((LineKind::Context, &line[1..])),
+                Some('-') if in_body || !line.starts_with("--- ") => {
+                    Some((LineKind::Remove, &line[1..]))
+                }
+                Some('+') if in_body || !line.starts_with("+++ ") => {
+                    Some((LineKind::Add, &line[1..]))
+                }
+                // Some generators drop the leading space on blank context lines
+                None => Some((LineKind::Context, "")),
+                _ => None,
+            };
+            if let Some((kind, text)) = parsed {
+                current.lines.push((kind, text.to_string()));
+                continue;
+            }
+            if line.starts_with('\\') {
+                match current.lines.last().map(|(k, _)| *k) {
+                    Some(LineKind::Remove) => current.old_no_eol = true,
+                    Some(LineKind::Add) => current.new_no_eol = true,
+                    _ => {
+                        current.old_no_eol = true;
+                        current.new_no_eol = true;
+                    }
+                }
+                continue;
+            }
+            if let Some(f) = file.as_mut() {
+                finish_hunk(f, hunk.take().unwrap());
+            }
+        }
+
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            flush(&mut file, &mut hunk, &mut files);
+            let mut next = FilePatch::default();
+            if let Some((old, new)) = rest.split_once(" b/") {
+                next.old_path = parse_header_path(old, "a/");
+                next.new_path = Some(new.to_string());
+            }
+            file = Some(next);
+        } else if let Some(rest) = line.strip_prefix("--- ") {
+            // A new ---/+++ pair without "diff --git" starts another file
+            if file.as_ref().is_none_or(|f| !f.hunks.is_empty()) {
+                flush(&mut file, &mut hunk, &mut files);
+                file = Some(FilePatch::default());
+            }
+            let f = file.as_mut().unwrap();
+            f.old_path = parse_header_path(rest, "a/");
+            f.is_create |= f.old_path.is_none();
+        } else if let Some(rest) = line.strip_prefix("+++ ") {
+            let f = file.get_or_insert_with(FilePatch::default);
+            f.new_path = parse_header_path(rest, "b/");
+            f.is_delete |= f.new_path.is_none();
+        } else if let Some(rest) = line.strip_prefix("rename from ") {
+            let f = file.get_or_insert_with(FilePatch::default);
+            f.old_path = Some(rest.to_string());
+            f.is_rename = true;
+        } else if let Some(rest) = line.strip_prefix("rename to ") {
+            let f = file.get_or_insert_with(FilePatch::default);
+            f.new_path = Some(rest.to_string());
+            f.is_rename = true;
+        } else if line.starts_with("new file mode ") {
+            if let Some(f) = file.as_mut() {
+                f.is_create = true;
+                f.old_path = None;
+            }
+        } else if line.starts_with("deleted file mode ") {
+            if let Some(f) = file.as_mut() {
+                f.is_delete = true;
+                f.new_path = None;
+            }
+        } else if line.starts_with("@@ ") {
+            let f = file
+                .as_mut()
+                .ok_or_else(|| format!("Hunk without a file header: {}", line))?;
+            if let Some(h) = hunk.take() {
+                finish_hunk(f, h);
+            }
+            hunk = Some(
+                parse_hunk_header(line).ok_or_else(|| format!("Invalid hunk header: {}", line))?,
+            );
+        }
+    }
+    flush(&mut file, &mut hunk, &mut files);
+
+    if files.is_empty() {
+        return Err("No file changes found in patch".to_string());
+    }
+    Ok(files)
+}
+
+/// Parse a git-style patch with libgit2, which handles quoted paths and
+/// extended headers. Returns None when libgit2 rejects the patch (e.g. hunk
+/// counts are off) so the lenient parser can take over.
+fn parse_git_diff(patch: &str) -> Option<Vec<FilePatch>> {
+    if !patch.contains("diff --git ") {
+        return None;
+    }
+    let diff = git2::Diff::from_buffer(patch.as_bytes()).ok()?;
+    let mut files = Vec::new();
+
+    for index in 0..diff.deltas().len() {
+        let delta = diff.get_delta(index)?;
+        let path_of = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().to_string());
+        let mut file = FilePatch {
+            old_path: path_of(delta.old_file()),
+            new_path: path_of(delta.new_file()),
+            ..Default::default()
+        };
+        match delta.status() {
+            git2::Delta::Added => {
+                file.is_create = true;
+                file.old_path = None;
+            }
+            git2::Delta::Deleted => {
+                file.is_delete = true;
+                file.new_path = None;
+            }
+            git2::Delta::Renamed => file.is_rename = true,
+            git2::Delta::Modified => {}
+            // Copies, type changes and binary patches are not supported
+            _ => return None,
+        }
+
+        if let Some(patch) = git2::Patch::from_diff(&diff, index).ok()? {
+            for hunk_index in 0..patch.num_hunks() {
+                let (header, line_count) = patch.hunk(hunk_index).ok()?;
+                let mut hunk = Hunk {
+                    old_start: header.old_start() as usize,
+                    old_count: Some(header.old_lines() as usize),
+                    new_count: Some(header.new_lines() as usize),
+                    ..Default::default()
+                };
+                for line_index in 0..line_count {
+                    let line = patch.line_in_hunk(hunk_index, line_index).ok()?;
+                    let text = String::from_utf8_lossy(line.content());
+                    let text = text.strip_suffix('\n').unwrap_or(&text);
+                    let text = text.strip_suffix('\r').unwrap_or(text).to_string();
+                    match line.origin() {
+                        ' ' => hunk.lines.push((LineKind::Context, text)),
+                        '-' => hunk.lines.push((LineKind::Remove, text)),
+                        '+' => hunk.lines.push((LineKind::Add, text)),
+                        // "\ No newline at end of file" markers
+                        '<' => hunk.old_no_eol = true,
+                        '>' => hunk.new_no_eol = true,
+                        '=' => {
+                            hunk.old_no_eol = true;
+                            hunk.new_no_eol = true;
+                        }
+                        _ => {}
+                    }
+                }
+                file.hunks.push(hunk);
+            }
+        }
+        files.push(file);
+    }
+
+    (!files.is_empty()).then_some(files)
+}
+
+/// Working copy of a file while changes are planned
+struct VirtualFile {
+    /// Bytes on disk before any change, None if the file didn't exist
+    original: Option<Vec<u8>>,
+    encoding: TextEncoding,
+    /// Current lines without terminators, None when deleted
+    lines: Option<Vec<String>>,
+    trailing_newline: bool,
+}
+
+impl VirtualFile {
+    fn content(&self) -> Option<String> {
+        self.lines.as_ref().map(|lines| {
+            let mut content = lines.join("\n");
+            if self.trailing_newline && !lines.is_empty() {
+                content.push('\n');
+            }
+            content
+        })
+    }
+
+    fn set_content(&mut self, content: &str) {
+        let (lines, trailing_newline) = split_lines(content, self.encoding.line_ending);
+        self.lines = Some(lines);
+        self.trailing_newline = trailing_newline;
+    }
+}
+
+fn split_lines(content: &str, line_ending: LineEnding) -> (Vec<String>, bool) {
+    if content.is_empty() {
+        return (Vec::new(), false);
+    }
+    let trailing_newline = content.ends_with('\n');
+    let body = content.strip_suffix('\n').unwrap_or(content);
+    let lines = body
+        .split('\n')
+        .map(|line| match line_ending {
+            // Mixed files keep their carriage returns so they round-trip
+            LineEnding::Mixed => line.to_string(),
+            _ => line.strip_suffix('\r').unwrap_or(line).to_string(),
+        })
+        .collect();
+    (lines, trailing_newline)
+}
+
+/// In-memory view of the files a patch touches, committed all at once
+struct VirtualFs {
+    base: PathBuf,
+    files: BTreeMap<PathBuf, VirtualFile>,
+}
+
+impl VirtualFs {
+    fn new(base: &Path) -> Self {
+        Self {
+            base: base.to_path_buf(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Resolve a patch path inside the base directory, rejecting escapes
+    fn resolve(&self, relative: &str) -> Result<PathBuf, String> {
+        let relative = Path::new(relative);
+        if relative.is_absolute()
+            || relative
+                .components()
+                .any(|c| matches!(c, Component::ParentDir | Component::Prefix(_)))
+        {
+            return Err(format!(
+                "Path {} is outside of the base directory",
+                relative.display()
+            ));
+        }
+        Ok(self.base.join(relative))
+    }
+
+    fn load(&mut self, relative: &str) -> Result<&mut VirtualFile, String> {
+        let path = self.resolve(relative)?;
+        if !self.files.contains_key(&path) {
+            let file = match fs::read(&path) {
+                Ok(bytes) => {
+                    let (content, encoding, _) = encoding::decode(&bytes);
+                    let (lines, trailing_newline) = split_lines(&content, encoding.line_ending);
+                    VirtualFile {
+                        original: Some(bytes),
+                        encoding,
+                        lines: Some(lines),
+                        trailing_newline,
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => VirtualFile {
+                    original: None,
+                    encoding: TextEncoding::default(),
+                    lines: None,
+                    trailing_newline: true,
+                },
+                Err(e) => return Err(format!("Failed to read {}: {}", relative, e)),
+            };
+            self.files.insert(path.clone(), file);
+        }
+        Ok(self.files.get_mut(&path).unwrap())
+    }
+
+    fn existing(&mut self, relative: &str) -> Result<&mut VirtualFile, String> {
+        let file = self.load(relative)?;
+        if file.lines.is_none() {
+            return Err(format!("{} does not exist", relative));
+        }
+        Ok(file)
+    }
+
+    fn create(&mut self, relative: &str, content: &str) -> Result<(), String> {
+        let file = self.load(relative)?;
+        if file.lines.is_some() {
+            return Err(format!("{} already exists", relative));
+        }
+        file.set_content(content);
+        Ok(())
+    }
+
+    fn delete(&mut self, relative: &str) -> Result<(), String> {
+        self.existing(relative)?.lines = None;
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), String> {
+        let source = self.existing(from)?;
+        let lines = source.lines.take();
+        let trailing_newline = source.trailing_newline;
+        let encoding = source.encoding.clone();
+
+        let target = self.load(to)?;
+        if target.lines.is_some() {
+            return Err(format!("Cannot rename {} to {}: target exists", from, to));
+        }
+        target.lines = lines;
+        target.trailing_newline = trailing_newline;
+        target.encoding = encoding;
+        Ok(())
+    }
+
+    /// Write every changed file. On failure, files already written are put back.
+    fn commit(self) -> Result<(), String> {
+        let mut writes = Vec::new();
+        let mut deletes = Vec::new();
+        for (path, file) in &self.files {
+            match file.content() {
+                Some(content) => {
+                    let bytes = encoding::encode_detected(&content, &file.encoding)?;
+                    if file.original.as_deref() != Some(&bytes[..]) {
+                        writes.push((path, bytes, file.original.as_ref()));
+                    }
+                }
+                None if file.original.is_some() => deletes.push((path, file.original.as_ref())),
+                None => {}
+            }
+        }
+
+        let mut done: Vec<(&PathBuf, Option<&Vec<u8>>)> = Vec::new();
+        let mut result = Ok(());
+        for (path, bytes, original) in writes {
+            if let Err(e) = atomic::write_atomic(path, &bytes, &WritePrecondition::default()) {
+                result = Err(e);
+                break;
+            }
+            done.push((path, original));
+        }
+        if result.is_ok() {
+            for (path, original) in deletes {
+                if let Err(e) = fs::remove_file(path) {
+                    result = Err(format!("Failed to delete {}: {}", path.display(), e));
+                    break;
+                }
+                done.push((path, original));
+            }
+        }
+
+        if result.is_err() {
+            for (path, original) in done.into_iter().rev() {
+                let _ = match original {
+                    Some(bytes) => {
+                        atomic::write_atomic(path, bytes, &WritePrecondition::default()).map(|_| ())
+                    }
+                    None => fs::remove_file(path).map_err(|e| e.to_string()),
+                };
+            }
+        }
+        result
+    }
+}
+
+fn lines_match(actual: &[String], expected: &[&str], ignore_whitespace: bool) -> bool {
+    actual.len() == expected.len()
+        && actual.iter().zip(expected).all(|(a, e)| {
+            if ignore_whitespace {
+                a.split_whitespace().eq(e.split_whitespace())
+            } else {
+                a == e
+            }
+        })
+}
+
+/// Find `needle` in `lines[min_start..]`, trying positions closest to `expected` first
+fn find_block(
+    lines: &[String],
+    needle: &[&str],
+    expected: usize,
+    min_start: usize,
+    ignore_whitespace: bool,
+) -> Option<usize> {
+    if lines.len() < needle.len() {
+        return None;
+    }
+    let max_start = lines.len() - needle.len();
+    if min_start > max_start {
+        return None;
+    }
+    let expected = expected.clamp(min_start, max_start);
+
+    let candidates = (0..=max_start - min_start).flat_map(|distance| {
+        let after = expected.checked_add(distance).filter(|p| *p <= max_start);
+        let before = expected
+            .checked_sub(distance)
+            .filter(|p| *p >= min_start && distance > 0);
+        after.into_iter().chain(before)
+    });
+
+    for start in candidates {
+        if lines_match(
+            &lines[start..start + needle.len()],
+            needle,
+            ignore_whitespace,
+        ) {
+            return Some(start);
+        }
+    }
+    None
+}
+
+/// Apply hunks in order to `file`. A hunk that doesn't match is reported as
+/// failed and left out, and the remaining hunks are still tried.
+fn apply_hunks(file: &mut VirtualFile, hunks: &[Hunk], max_fuzz: usize) -> Vec<HunkResult> {
+    let mut results = Vec::new();
+    let lines = file.lines.get_or_insert_with(Vec::new);
+    // Lines added minus lines removed by earlier hunks
+    let mut delta: isize = 0;
+    // Offset of the previous hunk, assumed to carry over to the next one
+    let mut drift: isize = 0;
+    let mut min_start = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let leading = hunk
+            .lines
+            .iter()
+            .take_while(|(k, _)| *k == LineKind::Context)
+            .count();
+        let trailing = hunk
+            .lines
+            .iter()
+            .rev()
+            .take_while(|(k, _)| *k == LineKind::Context)
+            .count()
+            .min(hunk.lines.len() - leading);
+        // A hunk with no old lines inserts after `old_start` rather than at it
+        let old_index = if hunk.old_count == Some(0) {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let header_position = old_index as isize + delta;
+        let expected = (header_position + drift).max(0) as usize;
+
+        let mut found = None;
+        'search: for fuzz in 0..=max_fuzz {
+            let skip_start = fuzz.min(leading);
+            let skip_end = fuzz.min(trailing);
+            if fuzz > 0 && skip_start < fuzz && skip_end < fuzz {
+                break;
+            }
+            let body = &hunk.lines[skip_start..hunk.lines.len() - skip_end];
+            let old: Vec<&str> = body
+                .iter()
+                .filter(|(k, _)| *k != LineKind::Add)
+                .map(|(_, t)| t.as_str())
+                .collect();
+
+            for ignore_whitespace in [false, true] {
+                let position = if old.is_empty() {
+                    // Pure insertion: trust the header
+                    Some(
+                        (expected + skip_start)
+                            .min(lines.len())
+                            .max(min_start.min(lines.len())),
+                    )
+                } else {
+                    find_block(
+                        lines,
+                        &old,
+                        expected + skip_start,
+                        min_start,
+                        ignore_whitespace,
+                    )
+                };
+                if let Some(position) = position {
+                    found = Some((
+                        position,
+                        body,
+                        old.len(),
+                        fuzz,
+                        ignore_whitespace,
+                        skip_start,
+                    ));
+                    break 'search;
+                }
+            }
+        }
+
+        match found {
+            Some((position, body, old_len, fuzz, ignore_whitespace, skip_start)) => {
+                let new: Vec<String> = body
+                    .iter()
+                    .filter(|(k, _)| *k != LineKind::Remove)
+                    .map(|(_, t)| t.clone())
+                    .collect();
+                let new_len = new.len();
+                let hunk_start = position as isize - skip_start as isize;
+                let offset = hunk_start - header_position;
+                let original_line = (hunk_start - delta).max(0) as usize + 1;
+
+                lines.splice(position..position + old_len, new);
+                delta += new_len as isize - old_len as isize;
+                drift = offset;
+                min_start = position + new_len;
+
+                results.push(HunkResult {
+                    index,
+                    status: if fuzz > 0 || ignore_whitespace {
+                        HunkStatus::AppliedWithFuzz
+                    } else {
+                        HunkStatus::Applied
+                    },
+                    line: Some(original_line),
+                    offset,
+                    fuzz,
+                    whitespace_insensitive: ignore_whitespace,
+                    message: None,
+                });
+            }
+            None => {
+                results.push(HunkResult {
+                    index,
+                    status: HunkStatus::Failed,
+                    line: None,
+                    offset: 0,
+                    fuzz: 0,
+                    whitespace_insensitive: false,
+                    message: Some(format!(
+                        "Context for hunk at line {} was not found",
+                        hunk.old_start
+                    )),
+                });
+            }
+        }
+    }
+
+    let last_applied = results
+        .last()
+        .is_some_and(|r| r.status != HunkStatus::Failed);
+    if let Some(last) = hunks.last().filter(|_| last_applied) {
+        if last.new_no_eol {
+            file.trailing_newline = false;
+        } else if last.old_no_eol {
+            file.trailing_newline = true;
+        }
+    }
+    results
+}
+
+fn apply_file_patch(
+    vfs: &mut VirtualFs,
+    patch: &FilePatch,
+    max_fuzz: usize,
+) -> Result<Vec<HunkResult>, String> {
+    match patch.action() {
+        FileAction::Create => {
+            let path = patch.display_path();
+            vfs.create(&path, "")?;
+            let file = vfs.load(&path)?;
+            file.trailing_newline = true;
+            Ok(apply_hunks(file, &patch.hunks, 0))
+        }
+        FileAction::Delete => {
+            let path = patch.old_path.clone().unwrap_or_default();
+            let results = apply_hunks(vfs.existing(&path)?, &patch.hunks, max_fuzz);
+            if results.iter().all(|r| r.status != HunkStatus::Failed) {
+                vfs.delete(&path)?;
+            }
+            Ok(results)
+        }
+        FileAction::Rename => {
+            let from = patch.old_path.clone().unwrap_or_default();
+            let to = patch.display_path();
+            vfs.rename(&from, &to)?;
+            Ok(apply_hunks(vfs.existing(&to)?, &patch.hunks, max_fuzz))
+        }
+        FileAction::Modify => {
+            let path = patch.display_path();
+            Ok(apply_hunks(vfs.existing(&path)?, &patch.hunks, max_fuzz))
+        }
+    }
+}
+
+/// Replace `search` in `file`, falling back to a whitespace-insensitive line match
+fn apply_replace(
+    file: &mut VirtualFile,
+    search: &str,
+    replace: &str,
+    all: bool,
+) -> Result<HunkResult, String> {
+    if search.is_empty() {
+        return Err("Search text must not be empty".to_string());
+    }
+    let content = file.content().unwrap_or_default();
+    let matches = content.matches(search).count();
+
+    if matches > 0 {
+        if matches > 1 && !all {
+            return Err(format!(
+                "Search text matches {} times; make it unique or set all",
+                matches
+            ));
+        }
+        let line = content[..content.find(search).unwrap()]
+            .matches('\n')
+            .count()
+            + 1;
+        let updated = if all {
+            content.replace(search, replace)
+        } else {
+            content.replacen(search, replace, 1)
+        };
+        file.set_content(&updated);
+        return Ok(HunkResult {
+            index: 0,
+            status: HunkStatus::Applied,
+            line: Some(line),
+            offset: 0,
+            fuzz: 0,
+            whitespace_insensitive: false,
+            message: None,
+        });
+    }
+
+    // Whitespace-only text would match every blank line once whitespace is ignored
+    if search.trim().is_empty() {
+        return Err("Search text was not found".to_string());
+    }
+    let needle: Vec<&str> = search
+        .strip_suffix('\n')
+        .unwrap_or(search)
+        .split('\n')
+        .collect();
+    let lines = file.lines.get_or_insert_with(Vec::new);
+    let position = find_block(lines, &needle, 0, 0, true)
+        .ok_or_else(|| "Search text was not found".to_string())?;
+    if !all && find_block(lines, &needle, 0, position + 1, true).is_some() {
+        return Err("Search text matches more than once; make it unique or set all".to_string());
+    }
+
+    // An empty replacement removes the matched lines rather than blanking them
+    let replacement: Vec<String> = if replace.is_empty() {
+        Vec::new()
+    } else {
+        replace
+            .strip_suffix('\n')
+            .unwrap_or(replace)
+            .split('\n')
+            .map(|l| l.to_string())
+            .collect()
+    };
+    let mut start = Some(position);
+    while let Some(position) = start {
+        lines.splice(position..position + needle.len(), replacement.clone());
+        start = if all {
+            find_block(lines, &needle, 0, position + replacement.len(), true)
+        } else {
+            None
+        };
+    }
+
+    Ok(HunkResult {
+        index: 0,
+        status: HunkStatus::AppliedWithFuzz,
+        line: Some(position + 1),
+        offset: 0,
+        fuzz: 0,
+        whitespace_insensitive: true,
+        message: None,
+    })
+}
+
+fn apply_edit(vfs: &mut VirtualFs, edit: &EditOperation) -> FilePatchResult {
+    let (path, old_path, action, outcome) = match edit {
+        EditOperation::Replace {
+            path,
+            search,
+            replace,
+            all,
+        } => (
+            path,
+            None,
+            FileAction::Modify,
+            vfs.existing(path)
+                .and_then(|file| apply_replace(file, search, replace, *all))
+                .map(|r| vec![r]),
+        ),
+        EditOperation::Create { path, content } => (
+            path,
+            None,
+            FileAction::Create,
+            vfs.create(path, content).map(|_| Vec::new()),
+        ),
+        EditOperation::Delete { path } => (
+            path,
+            None,
+            FileAction::Delete,
+            vfs.delete(path).map(|_| Vec::new()),
+        ),
+        EditOperation::Rename { from, to } => (
+            to,
+            Some(from.clone()),
+            FileAction::Rename,
+            vfs.rename(from, to).map(|_| Vec::new()),
+        ),
+    };
+
+    let (hunks, error) = match outcome {
+        Ok(hunks) => (hunks, None),
+        Err(e) => (
+            vec![HunkResult {
+                index: 0,
+                status: HunkStatus::Failed,
+                line: None,
+                offset: 0,
+                fuzz: 0,
+                whitespace_insensitive: false,
+                message: Some(e.clone()),
+            }],
+            Some(e),
+        ),
+    };
+
+    FilePatchResult {
+        path: path.clone(),
+        old_path,
+        action,
+        hunks,
+        error,
+    }
+}
+
+/// Apply a unified diff and/or structured edits under `base_path`. Nothing is
+/// written unless every hunk and edit applies.
+pub fn apply_patch(request: ApplyPatchRequest) -> Result<ApplyPatchResult, String> {
+    let base = Path::new(&request.base_path);
+    if !base.is_dir() {
+        return Err(format!("Directory does not exist: {}", request.base_path));
+    }
+    if request.patch.is_none() && request.edits.is_none() {
+        return Err("Either patch or edits is required".to_string());
+    }
+
+    let max_fuzz = request.fuzz.unwrap_or(DEFAULT_FUZZ);
+    let mut vfs = VirtualFs::new(base);
+    let mut files = Vec::new();
+
+    if let Some(patch) = &request.patch {
+        let file_patches = match parse_git_diff(patch) {
+            Some(file_patches) => file_patches,
+            None => parse_unified_diff(patch)?,
+        };
+        for file_patch in &file_patches {
+            let (hunks, error) = match apply_file_patch(&mut vfs, file_patch, max_fuzz) {
+                Ok(hunks) => {
+                    let error = hunks
+                        .iter()
+                        .find(|h| h.status == HunkStatus::Failed)
+                        .and_then(|h| h.message.clone());
+                    (hunks, error)
+                }
+                Err(e) => (Vec::new(), Some(e)),
+            };
+            files.push(FilePatchResult {
+                path: file_patch.display_path(),
+                old_path: file_patch
+                    .old_path
+                    .clone()
+                    .filter(|p| Some(p) != file_patch.new_path.as_ref()),
+                action: file_patch.action(),
+                hunks,
+                error,
+            });
+        }
+    }
+
+    for edit in request.edits.iter().flatten() {
+        files.push(apply_edit(&mut vfs, edit));
+    }
+
+    let applied = files.iter().all(|f| f.error.is_none());
+    if applied && !request.dry_run {
+        vfs.commit()?;
+    }
+
+    Ok(ApplyPatchResult { applied, files })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Working copy of `content` as if it had been read from disk
+    fn file(content: &str) -> VirtualFile {
+        let mut file = VirtualFile {
+            original: Some(content.as_bytes().to_vec()),
+            encoding: TextEncoding::default(),
+            lines: None,
+            trailing_newline: true,
+        };
+        file.set_content(content);
+        file
+    }
+
+    fn single_file_patch(patch: &str) -> FilePatch {
+        let mut files = parse_unified_diff(patch).unwrap();
+        assert_eq!(files.len(), 1);
+        files.remove(0)
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("raven-patch-{}", nanoid::nanoid!(8)));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parses_files_without_git_headers() {
+        let files = parse_unified_diff(
+            "--- a/one.txt\n+++ b/one.txt\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n\
+             --- /dev/null\n+++ b/two.txt\n@@ -0,0 +1 @@\n+new\n",
+        )
+        .unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].action(), FileAction::Modify);
+        assert_eq!(files[0].display_path(), "one.txt");
+        assert_eq!(
+            files[0].hunks[0].lines,
+            vec![
+                (LineKind::Context, "a".to_string()),
+                (LineKind::Remove, "b".to_string()),
+                (LineKind::Add, "c".to_string()),
+            ]
+        );
+        assert_eq!(files[1].action(), FileAction::Create);
+        assert_eq!(files[1].display_path(), "two.txt");
+    }
+
+    #[test]
+    fn parses_blank_context_without_leading_space() {
+        let file = single_file_patch("--- a/f\n+++ b/f\n@@ -1,3 +1,3 @@\n a\n\n-b\n+c\n");
+        assert_eq!(file.hunks[0].lines[1], (LineKind::Context, String::new()));
+        assert_eq!(file.hunks[0].lines.len(), 4);
+    }
+
+    #[test]
+    fn drops_blank_lines_after_the_declared_counts() {
+        let file = single_file_patch("--- a/f\n+++ b/f\n@@ -1 +1 @@\n-a\n+b\n\n\n");
+        assert_eq!(file.hunks[0].lines.len(), 2);
+    }
+
+    #[test]
+    fn parses_missing_newline_markers() {
+        let file = single_file_patch(
+            "--- a/f\n+++ b/f\n@@ -1 +1 @@\n-a\n\\ No newline at end of file\n+b\n",
+        );
+        assert!(file.hunks[0].old_no_eol);
+        assert!(!file.hunks[0].new_no_eol);
+    }
+
+    #[test]
+    fn rejects_hunks_without_a_file() {
+        assert!(parse_unified_diff("@@ -1 +1 @@\n-a\n+b\n").is_err());
+        assert!(parse_unified_diff("just some text\n").is_err());
+    }
+
+    #[test]
+    fn parses_git_renames_and_deletes() {
+        let files = parse_git_diff(
+            "diff --git a/old.txt b/new.txt\nsimilarity index 100%\n\
+             rename from old.txt\nrename to new.txt\n\
+             diff --git a/gone.txt b/gone.txt\ndeleted file mode 100644\n\
+             index 7898192..0000000\n--- a/gone.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-a\n",
+        )
+        .unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].action(), FileAction::Rename);
+        assert_eq!(files[0].old_path.as_deref(), Some("old.txt"));
+        assert_eq!(files[0].display_path(), "new.txt");
+        assert_eq!(files[1].action(), FileAction::Delete);
+        assert_eq!(files[1].old_path.as_deref(), Some("gone.txt"));
+    }
+
+    #[test]
+    fn applies_hunks_at_an_offset() {
+        let patch = single_file_patch("--- a/f\n+++ b/f\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n");
+        let mut target = file("x\ny\na\nb\nc\n");
+        let results = apply_hunks(&mut target, &patch.hunks, DEFAULT_FUZZ);
+        assert_eq!(results[0].status, HunkStatus::Applied);
+        assert_eq!(results[0].offset, 2);
+        assert_eq!(results[0].line, Some(3));
+        assert_eq!(target.content().unwrap(), "x\ny\na\nB\nc\n");
+    }
+
+    #[test]
+    fn applies_hunks_with_fuzz() {
+        let patch = single_file_patch(
+            "--- a/f\n+++ b/f\n@@ -1,5 +1,5 @@\n stale\n a\n-b\n+B\n c\n stale\n",
+        );
+        let mut target = file("first\na\nb\nc\nlast\n");
+        let results = apply_hunks(&mut target, &patch.hunks, DEFAULT_FUZZ);
+        assert_eq!(results[0].status, HunkStatus::AppliedWithFuzz);
+        assert_eq!(results[0].fuzz, 1);
+        assert_eq!(target.content().unwrap(), "first\na\nB\nc\nlast\n");
+
+        let mut target = file("first\na\nb\nc\nlast\n");
+        let results = apply_hunks(&mut target, &patch.hunks, 0);
+        assert_eq!(results[0].status, HunkStatus::Failed);
+        assert_eq!(target.content().unwrap(), "first\na\nb\nc\nlast\n");
+    }
+
+    #[test]
+    fn applies_hunks_ignoring_whitespace() {
+        let patch =
+            single_file_patch("--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n fn a() {\n-  b();\n+  c();\n");
+        let mut target = file("fn  a()  {\n\tb();\n");
+        let results = apply_hunks(&mut target, &patch.hunks, 0);
+        assert_eq!(results[0].status, HunkStatus::AppliedWithFuzz);
+        assert!(results[0].whitespace_insensitive);
+        assert_eq!(target.content().unwrap(), "fn a() {\n  c();\n");
+    }
+
+    #[test]
+    fn keeps_applying_after_a_failed_hunk() {
+        let patch =
+            single_file_patch("--- a/f\n+++ b/f\n@@ -1 +1 @@\n-missing\n+x\n@@ -3 +3 @@\n-c\n+C\n");
+        let mut target = file("a\nb\nc\n");
+        let results = apply_hunks(&mut target, &patch.hunks, DEFAULT_FUZZ);
+        assert_eq!(results[0].status, HunkStatus::Failed);
+        assert_eq!(results[1].status, HunkStatus::Applied);
+        assert_eq!(target.content().unwrap(), "a\nb\nC\n");
+    }
+
+    #[test]
+    fn replaces_ignoring_whitespace() {
+        let mut target = file("a\n  b\n\nc\n");
+        let result = apply_replace(&mut target, "b \n\n", "", false).unwrap();
+        assert!(result.whitespace_insensitive);
+        assert_eq!(target.content().unwrap(), "a\nc\n");
+
+        let mut target = file("a\n  b\nc\n");
+        apply_replace(&mut target, " b \n", "B\n", false).unwrap();
+        assert_eq!(target.content().unwrap(), "a\nB\nc\n");
+    }
+
+    #[test]
+    fn refuses_to_create_an_existing_empty_file() {
+        let dir = temp_dir();
+        fs::write(dir.join("empty.txt"), "").unwrap();
+        let mut vfs = VirtualFs::new(&dir);
+        assert!(vfs.create("empty.txt", "content").is_err());
+        assert!(vfs.create("new.txt", "content").is_ok());
+        assert!(vfs.create("new.txt", "content").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rolls_back_written_files_when_a_write_fails() {
+        let dir = temp_dir();
+        fs::write(dir.join("a.txt"), "a\n").unwrap();
+        fs::write(dir.join("b.txt"), "b\n").unwrap();
+        let mut vfs = VirtualFs::new(&dir);
+        vfs.existing("a.txt").unwrap().set_content("changed\n");
+        vfs.existing("b.txt").unwrap().set_content("changed\n");
+        vfs.create("c.txt", "new\n").unwrap();
+
+        // b.txt turns into a directory after planning, so writing it fails
+        fs::remove_file(dir.join("b.txt")).unwrap();
+        fs::create_dir_all(dir.join("b.txt").join("inner")).unwrap();
+
+        assert!(vfs.commit().is_err());
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a\n");
+        assert!(!dir.join("c.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
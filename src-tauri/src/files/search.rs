@@ -0,0 +1,452 @@
+use ignore::overrides::OverrideBuilder;
+use ignore::{WalkBuilder, WalkState};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use tauri::Emitter;
+
+use crate::files::atomic::{self, WritePrecondition};
+use crate::files::{chunked, encoding};
+
+pub const SEARCH_RESULT_EVENT: &str = "search_result";
+pub const SEARCH_COMPLETE_EVENT: &str = "search_complete";
+
+const DEFAULT_CONTEXT_LINES: usize = 2;
+const DEFAULT_MAX_RESULTS: usize = 10_000;
+/// Files larger than this are skipped
+const MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+/// Very long lines (minified code) are cut in results
+const MAX_LINE_PREVIEW: usize = 1000;
+
+/// Cancellation flags of running searches, by search id
+static SEARCH_JOBS: std::sync::LazyLock<StdMutex<HashMap<String, Arc<AtomicBool>>>> =
+    std::sync::LazyLock::new(|| StdMutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchOptions {
+    pub query: String,
+    #[serde(default)]
+    pub is_regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Globs relative to the search root; when set, only matching files are searched
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub context_lines: Option<usize>,
+    pub max_results: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMatch {
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+    /// Length of the match in characters
+    pub length: usize,
+    pub text: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResultPayload {
+    search_id: String,
+    path: String,
+    matches: Vec<SearchMatch>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchCompletePayload {
+    search_id: String,
+    files_searched: usize,
+    files_matched: usize,
+    total_matches: usize,
+    /// True when the search stopped at `max_results`
+    truncated: bool,
+    cancelled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplacePreviewLine {
+    pub line: usize,
+    pub original: String,
+    pub replaced: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileReplaceResult {
+    pub path: String,
+    pub replacements: usize,
+    pub lines: Vec<ReplacePreviewLine>,
+    pub error: Option<String>,
+}
+
+fn build_regex(options: &SearchOptions) -> Result<Regex, String> {
+    if options.query.is_empty() {
+        return Err("Search query is empty".to_string());
+    }
+    let mut pattern = if options.is_regex {
+        options.query.clone()
+    } else {
+        regex::escape(&options.query)
+    };
+    if options.whole_word {
+        pattern = format!(r"\b(?:{})\b", pattern);
+    }
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| format!("Invalid search pattern: {}", e))
+}
+
+fn build_walker(root: &Path, options: &SearchOptions) -> Result<WalkBuilder, String> {
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in options.include.iter().flatten() {
+        overrides
+            .add(glob)
+            .map_err(|e| format!("Invalid include glob {}: {}", glob, e))?;
+    }
+    for glob in options.exclude.iter().flatten() {
+        overrides
+            .add(&format!("!{}", glob))
+            .map_err(|e| format!("Invalid exclude glob {}: {}", glob, e))?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| format!("Invalid glob: {}", e))?;
+
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .hidden(true)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .overrides(overrides);
+    Ok(builder)
+}
+
+/// Read a file as text, skipping binaries and very large files
+fn read_searchable(path: &Path) -> Option<(String, Vec<u8>)> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file() || metadata.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    if chunked::looks_binary(&bytes) {
+        return None;
+    }
+    let (content, _, _) = encoding::decode(&bytes);
+    Some((content, bytes))
+}
+
+fn split_lines(content: &str) -> Vec<&str> {
+    content
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect()
+}
+
+fn preview(line: &str) -> String {
+    if line.len() <= MAX_LINE_PREVIEW {
+        line.to_string()
+    } else {
+        line.chars().take(MAX_LINE_PREVIEW).collect()
+    }
+}
+
+fn find_matches(content: &str, regex: &Regex, context: usize, limit: usize) -> Vec<SearchMatch> {
+    let lines = split_lines(content);
+    let mut matches = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        for found in regex.find_iter(line) {
+            if found.as_str().is_empty() {
+                continue;
+            }
+            if matches.len() >= limit {
+                return matches;
+            }
+            matches.push(SearchMatch {
+                line: index + 1,
+                column: line[..found.start()].chars().count() + 1,
+                length: found.as_str().chars().count(),
+                text: preview(line),
+                before: lines[index.saturating_sub(context)..index]
+                    .iter()
+                    .map(|l| preview(l))
+                    .collect(),
+                after: lines[(index + 1).min(lines.len())..(index + 1 + context).min(lines.len())]
+                    .iter()
+                    .map(|l| preview(l))
+                    .collect(),
+            });
+        }
+    }
+    matches
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Search file contents under `root` in the background. Matches are streamed
+/// per file through `SEARCH_RESULT_EVENT` and a summary is sent through
+/// `SEARCH_COMPLETE_EVENT`; the returned id can be passed to `cancel_search`.
+pub fn start_search(
+    app: tauri::AppHandle,
+    root: PathBuf,
+    options: SearchOptions,
+) -> Result<String, String> {
+    if !root.is_dir() {
+        return Err(format!("Directory does not exist: {}", root.display()));
+    }
+    let regex = build_regex(&options)?;
+    let walker = build_walker(&root, &options)?;
+
+    let search_id = nanoid::nanoid!();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    SEARCH_JOBS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(search_id.clone(), cancelled.clone());
+
+    let id = search_id.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let context = options.context_lines.unwrap_or(DEFAULT_CONTEXT_LINES);
+        let max_results = options.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+        let files_searched = AtomicUsize::new(0);
+        let files_matched = AtomicUsize::new(0);
+        let total_matches = AtomicUsize::new(0);
+        let truncated = AtomicBool::new(false);
+
+        walker.build_parallel().run(|| {
+            Box::new(|entry| {
+                if cancelled.load(Ordering::Relaxed) || truncated.load(Ordering::Relaxed) {
+                    return WalkState::Quit;
+                }
+                let Ok(entry) = entry else {
+                    return WalkState::Continue;
+                };
+                if !entry.file_type().is_some_and(|t| t.is_file()) {
+                    return WalkState::Continue;
+                }
+                let Some((content, _)) = read_searchable(entry.path()) else {
+                    return WalkState::Continue;
+                };
+                files_searched.fetch_add(1, Ordering::Relaxed);
+
+                let remaining = max_results.saturating_sub(total_matches.load(Ordering::Relaxed));
+                let matches = find_matches(&content, &regex, context, remaining);
+                if matches.is_empty() {
+                    return WalkState::Continue;
+                }
+
+                let count = matches.len();
+                let previous = total_matches.fetch_add(count, Ordering::Relaxed);
+                files_matched.fetch_add(1, Ordering::Relaxed);
+                let _ = app.emit(
+                    SEARCH_RESULT_EVENT,
+                    SearchResultPayload {
+                        search_id: id.clone(),
+                        path: relative_path(&root, entry.path()),
+                        matches,
+                    },
+                );
+
+                if previous + count >= max_results {
+                    truncated.store(true, Ordering::Relaxed);
+                    return WalkState::Quit;
+                }
+                WalkState::Continue
+            })
+        });
+
+        SEARCH_JOBS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id);
+        let _ = app.emit(
+            SEARCH_COMPLETE_EVENT,
+            SearchCompletePayload {
+                search_id: id.clone(),
+                files_searched: files_searched.into_inner(),
+                files_matched: files_matched.into_inner(),
+                total_matches: total_matches.into_inner().min(max_results),
+                truncated: truncated.into_inner(),
+                cancelled: cancelled.load(Ordering::Relaxed),
+            },
+        );
+    });
+
+    Ok(search_id)
+}
+
+pub fn cancel_search(search_id: &str) -> Result<(), String> {
+    let jobs = SEARCH_JOBS.lock().unwrap_or_else(|e| e.into_inner());
+    let cancelled = jobs
+        .get(search_id)
+        .ok_or_else(|| format!("Search {} not found", search_id))?;
+    cancelled.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Replace the non-empty matches of `regex`, the same ones the search counts.
+/// Returns the new text and the number of replacements.
+fn replace_content(
+    content: &str,
+    regex: &Regex,
+    replacement: &str,
+    is_regex: bool,
+) -> (String, usize) {
+    let mut replaced = String::with_capacity(content.len());
+    let mut count = 0;
+    let mut last = 0;
+    for captures in regex.captures_iter(content) {
+        let Some(found) = captures.get(0).filter(|m| !m.as_str().is_empty()) else {
+            continue;
+        };
+        replaced.push_str(&content[last..found.start()]);
+        // Capture references like $1 are only expanded for regex searches
+        if is_regex {
+            captures.expand(replacement, &mut replaced);
+        } else {
+            replaced.push_str(replacement);
+        }
+        last = found.end();
+        count += 1;
+    }
+    replaced.push_str(&content[last..]);
+    (replaced, count)
+}
+
+fn replace_in_file(
+    path: &Path,
+    regex: &Regex,
+    replacement: &str,
+    is_regex: bool,
+    preview: bool,
+) -> Result<Option<(usize, Vec<ReplacePreviewLine>)>, String> {
+    let Some((content, bytes)) = read_searchable(path) else {
+        return Ok(None);
+    };
+
+    // Replace line by line so matches are exactly the ones the search reports
+    let mut replacements = 0;
+    let mut lines = Vec::new();
+    let mut updated = String::with_capacity(content.len());
+    for (index, segment) in content.split_inclusive('\n').enumerate() {
+        let body = segment.trim_end_matches(['\r', '\n']);
+        let terminator = &segment[body.len()..];
+        let (replaced, count) = replace_content(body, regex, replacement, is_regex);
+        if count == 0 {
+            updated.push_str(segment);
+            continue;
+        }
+        updated.push_str(&replaced);
+        updated.push_str(terminator);
+        replacements += count;
+        lines.push(ReplacePreviewLine {
+            line: index + 1,
+            original: body.to_string(),
+            replaced,
+        });
+    }
+    if replacements == 0 {
+        return Ok(None);
+    }
+
+    if !preview {
+        // Fail instead of clobbering the file if it changed since it was read
+        let precondition = WritePrecondition {
+            expected_mtime: None,
+            expected_hash: Some(atomic::hash_bytes(&bytes)),
+        };
+        encoding::write_text_file(path, &updated, None, &precondition)?;
+    }
+    Ok(Some((replacements, lines)))
+}
+
+/// Replace matches of `options` with `replacement` in every searched file, or
+/// only in `paths` (relative to `root`) when given. With `preview` set nothing
+/// is written and the affected lines are returned before and after.
+pub fn replace_in_files(
+    root: &Path,
+    options: &SearchOptions,
+    replacement: &str,
+    paths: Option<Vec<String>>,
+    preview: bool,
+) -> Result<Vec<FileReplaceResult>, String> {
+    if !root.is_dir() {
+        return Err(format!("Directory does not exist: {}", root.display()));
+    }
+    let regex = build_regex(options)?;
+
+    let mut results = Vec::new();
+    let files: Vec<(String, PathBuf)> = match paths {
+        Some(paths) => {
+            let canonical_root = root
+                .canonicalize()
+                .map_err(|e| format!("Failed to resolve {}: {}", root.display(), e))?;
+            // An absolute or ".." path would otherwise escape the root
+            let mut files = Vec::new();
+            for path in paths {
+                match root.join(&path).canonicalize() {
+                    Ok(file) if file.starts_with(&canonical_root) => files.push((path, file)),
+                    Ok(_) => results.push(FileReplaceResult {
+                        path,
+                        replacements: 0,
+                        lines: Vec::new(),
+                        error: Some("Path is outside the search root".to_string()),
+                    }),
+                    Err(e) => results.push(FileReplaceResult {
+                        path,
+                        replacements: 0,
+                        lines: Vec::new(),
+                        error: Some(format!("Failed to resolve path: {}", e)),
+                    }),
+                }
+            }
+            files
+        }
+        None => build_walker(root, options)?
+            .build()
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .map(|entry| (relative_path(root, entry.path()), entry.into_path()))
+            .collect(),
+    };
+
+    for (path, file) in files {
+        match replace_in_file(&file, &regex, replacement, options.is_regex, preview) {
+            Ok(Some((replacements, lines))) => results.push(FileReplaceResult {
+                path,
+                replacements,
+                lines,
+                error: None,
+            }),
+            Ok(None) => {}
+            Err(error) => results.push(FileReplaceResult {
+                path,
+                replacements: 0,
+                lines: Vec::new(),
+                error: Some(error),
+            }),
+        }
+    }
+    Ok(results)
+}
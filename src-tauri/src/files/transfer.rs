@@ -0,0 +1,497 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+pub const FILE_TRANSFER_EVENT: &str = "file_transfer_progress";
+
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Cancellation flags of running transfer jobs, by job id
+static TRANSFER_JOBS: std::sync::LazyLock<StdMutex<HashMap<String, Arc<AtomicBool>>>> =
+    std::sync::LazyLock::new(|| StdMutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferOperation {
+    Copy,
+    Move,
+}
+
+/// What to do when the destination already has an entry with the same name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Replace the existing entry
+    Overwrite,
+    /// Leave the existing entry and don't transfer the source
+    Skip,
+    /// Transfer under a free name such as "name (1).ext"
+    #[default]
+    Rename,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferItemStatus {
+    Done,
+    Skipped,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferItemResult {
+    pub source: String,
+    pub destination: Option<String>,
+    pub status: TransferItemStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileTransferPayload {
+    job_id: String,
+    operation: TransferOperation,
+    /// "progress", "completed" or "cancelled"
+    event: String,
+    total_bytes: u64,
+    processed_bytes: u64,
+    total_files: u64,
+    processed_files: u64,
+    current_path: Option<String>,
+    results: Option<Vec<TransferItemResult>>,
+}
+
+enum TransferError {
+    Cancelled,
+    Io(io::Error),
+}
+
+impl From<io::Error> for TransferError {
+    fn from(e: io::Error) -> Self {
+        TransferError::Io(e)
+    }
+}
+
+struct Progress {
+    app: tauri::AppHandle,
+    job_id: String,
+    operation: TransferOperation,
+    cancelled: Arc<AtomicBool>,
+    total_bytes: u64,
+    total_files: u64,
+    processed_bytes: u64,
+    processed_files: u64,
+    last_emit: Instant,
+}
+
+impl Progress {
+    fn check_cancelled(&self) -> Result<(), TransferError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            Err(TransferError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn advance(&mut self, bytes: u64, files: u64, current: &Path) {
+        self.processed_bytes += bytes;
+        self.processed_files += files;
+        if self.last_emit.elapsed() >= PROGRESS_INTERVAL {
+            self.last_emit = Instant::now();
+            self.emit("progress", Some(current), None);
+        }
+    }
+
+    fn emit(&self, event: &str, current: Option<&Path>, results: Option<Vec<TransferItemResult>>) {
+        let _ = self.app.emit(
+            FILE_TRANSFER_EVENT,
+            FileTransferPayload {
+                job_id: self.job_id.clone(),
+                operation: self.operation,
+                event: event.to_string(),
+                total_bytes: self.total_bytes,
+                processed_bytes: self.processed_bytes,
+                total_files: self.total_files,
+                processed_files: self.processed_files,
+                current_path: current.map(|p| p.to_string_lossy().to_string()),
+                results,
+            },
+        );
+    }
+}
+
+/// Total size and file count of a path, without following symlinks
+fn measure(path: &Path) -> (u64, u64) {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return (0, 0);
+    };
+    if !metadata.is_dir() {
+        return (metadata.len(), 1);
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| measure(&entry.path()))
+                .fold((0, 0), |acc, item| (acc.0 + item.0, acc.1 + item.1))
+        })
+        .unwrap_or((0, 0))
+}
+
+/// Delete a file, symlink or directory tree without following symlinks
+pub fn remove_path(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// A hidden, unused name next to `path` for staging or backing up an entry
+fn sibling_temp_path(path: &Path, purpose: &str) -> PathBuf {
+    let parent = path.parent().unwrap_or(Path::new(""));
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    parent.join(format!(".{}.{}.{}", name, nanoid::nanoid!(8), purpose))
+}
+
+/// Put the fully transferred `staged` entry in place of `target`. The old
+/// entry is set aside first and only deleted once the swap succeeded.
+fn replace_with(staged: &Path, target: &Path) -> io::Result<()> {
+    let backup = sibling_temp_path(target, "bak");
+    fs::rename(target, &backup)?;
+    if let Err(e) = fs::rename(staged, target) {
+        let _ = fs::rename(&backup, target);
+        return Err(e);
+    }
+    if let Err(e) = remove_path(&backup) {
+        eprintln!(
+            "Failed to remove replaced entry {}: {}",
+            backup.display(),
+            e
+        );
+    }
+    Ok(())
+}
+
+/// "name (1).ext", "name (2).ext", ... next to `path`
+fn unique_path(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or(Path::new(""));
+    let is_dir = path.is_dir();
+    let stem = if is_dir {
+        path.file_name()
+    } else {
+        path.file_stem()
+    }
+    .map(|s| s.to_string_lossy().to_string())
+    .unwrap_or_default();
+    let extension = if is_dir {
+        None
+    } else {
+        path.extension().map(|e| e.to_string_lossy().to_string())
+    };
+
+    (1..)
+        .map(|n| {
+            let name = match &extension {
+                Some(extension) => format!("{} ({}).{}", stem, n, extension),
+                None => format!("{} ({})", stem, n),
+            };
+            parent.join(name)
+        })
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+        .unwrap()
+}
+
+fn copy_file(from: &Path, to: &Path, progress: &mut Progress) -> Result<(), TransferError> {
+    let mut reader = fs::File::open(from)?;
+    let mut writer = fs::File::create(to)?;
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+    loop {
+        progress.check_cancelled()?;
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read])?;
+        progress.advance(read as u64, 0, from);
+    }
+    fs::set_permissions(to, reader.metadata()?.permissions())?;
+    progress.advance(0, 1, from);
+    Ok(())
+}
+
+fn copy_tree(from: &Path, to: &Path, progress: &mut Progress) -> Result<(), TransferError> {
+    progress.check_cancelled()?;
+    let metadata = fs::symlink_metadata(from)?;
+
+    if metadata.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &to.join(entry.file_name()), progress)?;
+        }
+        fs::set_permissions(to, metadata.permissions())?;
+        Ok(())
+    } else if metadata.file_type().is_symlink() {
+        copy_symlink(from, to)?;
+        progress.advance(metadata.len(), 1, from);
+        Ok(())
+    } else {
+        copy_file(from, to, progress)
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to).map(|_| ())
+}
+
+/// Rename `from` to `to`, falling back to copy + delete when they are on
+/// different filesystems
+pub fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            if let Err(e) = copy_tree_plain(from, to) {
+                let _ = remove_path(to);
+                return Err(e);
+            }
+            remove_path(from)
+        }
+        result => result,
+    }
+}
+
+fn copy_tree_plain(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+    if metadata.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_tree_plain(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else if metadata.file_type().is_symlink() {
+        copy_symlink(from, to)
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+fn transfer_item(
+    source: &Path,
+    destination_dir: &Path,
+    conflict: ConflictPolicy,
+    progress: &mut Progress,
+) -> TransferItemResult {
+    let result = |destination: Option<&Path>, status, error: Option<String>| TransferItemResult {
+        source: source.to_string_lossy().to_string(),
+        destination: destination.map(|p| p.to_string_lossy().to_string()),
+        status,
+        error,
+    };
+    let (item_bytes, item_files) = measure(source);
+
+    let Some(name) = source.file_name() else {
+        return result(
+            None,
+            TransferItemStatus::Failed,
+            Some("Invalid source path".into()),
+        );
+    };
+    if fs::symlink_metadata(source).is_err() {
+        return result(
+            None,
+            TransferItemStatus::Failed,
+            Some("Source does not exist".into()),
+        );
+    }
+    let mut target = destination_dir.join(name);
+
+    if source.is_dir() {
+        let source_abs = fs::canonicalize(source).unwrap_or_else(|_| source.to_path_buf());
+        let dir_abs =
+            fs::canonicalize(destination_dir).unwrap_or_else(|_| destination_dir.to_path_buf());
+        if dir_abs.starts_with(&source_abs) {
+            return result(
+                Some(&target),
+                TransferItemStatus::Failed,
+                Some("Cannot copy or move a directory into itself".into()),
+            );
+        }
+    }
+
+    let same_path = fs::canonicalize(source).ok() == fs::canonicalize(&target).ok();
+    if same_path && progress.operation == TransferOperation::Move {
+        // Moving onto itself is a no-op
+        progress.advance(item_bytes, item_files, source);
+        return result(Some(&target), TransferItemStatus::Skipped, None);
+    }
+
+    // Overwrites are staged under a temporary name, so the existing entry
+    // survives a failed or cancelled transfer
+    let mut staging = None;
+    if fs::symlink_metadata(&target).is_ok() {
+        match conflict {
+            ConflictPolicy::Skip => {
+                progress.advance(item_bytes, item_files, source);
+                return result(Some(&target), TransferItemStatus::Skipped, None);
+            }
+            // Copying onto itself always takes a new name
+            ConflictPolicy::Rename => target = unique_path(&target),
+            ConflictPolicy::Overwrite if same_path => target = unique_path(&target),
+            ConflictPolicy::Overwrite => staging = Some(sibling_temp_path(&target, "tmp")),
+        }
+    }
+    let written = staging.clone().unwrap_or_else(|| target.clone());
+
+    // Don't leave a partial copy behind when copying fails or is cancelled
+    let copy = |progress: &mut Progress| {
+        copy_tree(source, &written, progress).inspect_err(|_| {
+            let _ = remove_path(&written);
+        })
+    };
+    let outcome = match progress.operation {
+        TransferOperation::Move => match fs::rename(source, &written) {
+            Ok(()) => {
+                progress.advance(item_bytes, item_files, source);
+                Ok(())
+            }
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                copy(progress).and_then(|_| remove_path(source).map_err(TransferError::Io))
+            }
+            Err(e) => Err(TransferError::Io(e)),
+        },
+        TransferOperation::Copy => copy(progress),
+    };
+
+    let outcome = match (outcome, &staging) {
+        (Ok(()), Some(staged)) => replace_with(staged, &target).map_err(|e| {
+            // Undo the transfer so the source isn't lost with a moved item
+            let _ = match progress.operation {
+                TransferOperation::Move => move_path(staged, source),
+                TransferOperation::Copy => remove_path(staged),
+            };
+            TransferError::Io(io::Error::new(
+                e.kind(),
+                format!("Failed to replace existing entry: {}", e),
+            ))
+        }),
+        (outcome, _) => outcome,
+    };
+
+    match outcome {
+        Ok(()) => result(Some(&target), TransferItemStatus::Done, None),
+        Err(TransferError::Cancelled) => result(Some(&target), TransferItemStatus::Cancelled, None),
+        Err(TransferError::Io(e)) => result(
+            Some(&target),
+            TransferItemStatus::Failed,
+            Some(e.to_string()),
+        ),
+    }
+}
+
+/// Start copying or moving `sources` into `destination_dir` in the background.
+/// Progress and the final per-item results are reported through
+/// `FILE_TRANSFER_EVENT`; the returned job id can be passed to `cancel_transfer`.
+pub fn start_transfer(
+    app: tauri::AppHandle,
+    operation: TransferOperation,
+    sources: Vec<String>,
+    destination_dir: String,
+    conflict: ConflictPolicy,
+) -> Result<String, String> {
+    let destination_dir = PathBuf::from(destination_dir);
+    if !destination_dir.is_dir() {
+        return Err(format!(
+            "Destination is not a directory: {}",
+            destination_dir.display()
+        ));
+    }
+
+    let job_id = nanoid::nanoid!();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    TRANSFER_JOBS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(job_id.clone(), cancelled.clone());
+
+    let id = job_id.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let sources: Vec<PathBuf> = sources.into_iter().map(PathBuf::from).collect();
+        let (total_bytes, total_files) = sources
+            .iter()
+            .map(|source| measure(source))
+            .fold((0, 0), |acc, item| (acc.0 + item.0, acc.1 + item.1));
+
+        let mut progress = Progress {
+            app,
+            job_id: id.clone(),
+            operation,
+            cancelled,
+            total_bytes,
+            total_files,
+            processed_bytes: 0,
+            processed_files: 0,
+            last_emit: Instant::now(),
+        };
+        progress.emit("progress", None, None);
+
+        let mut results = Vec::new();
+        for source in &sources {
+            if progress.check_cancelled().is_err() {
+                results.push(TransferItemResult {
+                    source: source.to_string_lossy().to_string(),
+                    destination: None,
+                    status: TransferItemStatus::Cancelled,
+                    error: None,
+                });
+                continue;
+            }
+            results.push(transfer_item(
+                source,
+                &destination_dir,
+                conflict,
+                &mut progress,
+            ));
+        }
+
+        TRANSFER_JOBS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id);
+        let event = if progress.check_cancelled().is_err() {
+            "cancelled"
+        } else {
+            "completed"
+        };
+        progress.emit(event, None, Some(results));
+    });
+
+    Ok(job_id)
+}
+
+pub fn cancel_transfer(job_id: &str) -> Result<(), String> {
+    let jobs = TRANSFER_JOBS.lock().unwrap_or_else(|e| e.into_inner());
+    let cancelled = jobs
+        .get(job_id)
+        .ok_or_else(|| format!("Transfer job {} not found", job_id))?;
+    cancelled.store(true, Ordering::Relaxed);
+    Ok(())
+}
@@ -0,0 +1,392 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+use crate::files::transfer::{move_path, remove_path};
+
+const TRASH_DIR_NAME: &str = "trash";
+
+/// Where a trashed item physically lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrashLocation {
+    /// `<app data>/trash/<id>/<name>`
+    App,
+    /// The freedesktop.org home trash (`~/.local/share/Trash`), Linux only
+    System,
+}
+
+impl TrashLocation {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TrashLocation::App => "app",
+            TrashLocation::System => "system",
+        }
+    }
+
+    fn from_str(value: &str) -> Self {
+        match value {
+            "system" => TrashLocation::System,
+            _ => TrashLocation::App,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashEntry {
+    pub id: String,
+    pub original_path: String,
+    pub trash_path: String,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    /// "user" or the id of the agent that requested the deletion
+    pub deleted_by: String,
+    pub location: TrashLocation,
+    pub deleted_at: String,
+}
+
+fn app_trash_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(TRASH_DIR_NAME))
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))
+}
+
+fn row_to_entry(row: &sqlx::sqlite::SqliteRow) -> Result<TrashEntry, String> {
+    let read_err = |e: sqlx::Error| format!("Failed to read trash entry: {}", e);
+    let location: String = row.try_get("location").map_err(read_err)?;
+    let size: Option<i64> = row.try_get("size").map_err(read_err)?;
+    Ok(TrashEntry {
+        id: row.try_get("id").map_err(read_err)?,
+        original_path: row.try_get("originalPath").map_err(read_err)?,
+        trash_path: row.try_get("trashPath").map_err(read_err)?,
+        is_dir: row.try_get("isDir").map_err(read_err)?,
+        size: size.map(|s| s as u64),
+        deleted_by: row.try_get("deletedBy").map_err(read_err)?,
+        location: TrashLocation::from_str(&location),
+        deleted_at: row.try_get("deletedAt").map_err(read_err)?,
+    })
+}
+
+async fn get_entry(pool: &Pool<Sqlite>, id: &str) -> Result<TrashEntry, String> {
+    let row = sqlx::query("SELECT * FROM trash WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to query trash: {}", e))?
+        .ok_or_else(|| format!("Trash entry {} not found", id))?;
+    row_to_entry(&row)
+}
+
+async fn delete_entry(pool: &Pool<Sqlite>, id: &str) -> Result<(), String> {
+    sqlx::query("DELETE FROM trash WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete trash entry: {}", e))?;
+    Ok(())
+}
+
+/// Move a file or directory into the trash and record it in the database
+pub async fn move_to_trash(
+    app: &tauri::AppHandle,
+    path: &Path,
+    deleted_by: &str,
+    location: TrashLocation,
+) -> Result<TrashEntry, String> {
+    let metadata =
+        fs::symlink_metadata(path).map_err(|e| format!("Failed to read metadata: {}", e))?;
+    let pool = crate::db_pool(app).await?;
+    let id = nanoid::nanoid!();
+
+    let trash_path = match location {
+        TrashLocation::App => {
+            let name = path
+                .file_name()
+                .ok_or_else(|| "Cannot trash a path without a file name".to_string())?;
+            let entry_dir = app_trash_dir(app)?.join(&id);
+            fs::create_dir_all(&entry_dir)
+                .map_err(|e| format!("Failed to create trash directory: {}", e))?;
+            let trash_path = entry_dir.join(name);
+            if let Err(e) = move_path(path, &trash_path) {
+                let _ = fs::remove_dir_all(&entry_dir);
+                return Err(format!("Failed to move to trash: {}", e));
+            }
+            trash_path
+        }
+        TrashLocation::System => freedesktop::trash(path)?,
+    };
+
+    let entry = TrashEntry {
+        id,
+        original_path: path.to_string_lossy().to_string(),
+        trash_path: trash_path.to_string_lossy().to_string(),
+        is_dir: metadata.is_dir(),
+        size: if metadata.is_dir() {
+            None
+        } else {
+            Some(metadata.len())
+        },
+        deleted_by: deleted_by.to_string(),
+        location,
+        deleted_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let inserted = sqlx::query(
+        "INSERT INTO trash (id, originalPath, trashPath, isDir, size, deletedBy, location, deletedAt)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&entry.id)
+    .bind(&entry.original_path)
+    .bind(&entry.trash_path)
+    .bind(entry.is_dir)
+    .bind(entry.size.map(|s| s as i64))
+    .bind(&entry.deleted_by)
+    .bind(entry.location.as_str())
+    .bind(&entry.deleted_at)
+    .execute(&pool)
+    .await;
+
+    // Without a record the item could never be restored, so put it back
+    if let Err(e) = inserted {
+        let rollback = move_path(&trash_path, path);
+        if rollback.is_ok() {
+            match location {
+                TrashLocation::App => {
+                    if let Some(entry_dir) = trash_path.parent() {
+                        let _ = fs::remove_dir_all(entry_dir);
+                    }
+                }
+                TrashLocation::System => freedesktop::remove_info(&trash_path),
+            }
+        }
+        return Err(match rollback {
+            Ok(()) => format!("Failed to record trash entry: {}", e),
+            Err(move_err) => format!(
+                "Failed to record trash entry: {}; the item remains at {}: {}",
+                e, entry.trash_path, move_err
+            ),
+        });
+    }
+
+    Ok(entry)
+}
+
+/// List trashed items, newest first. Entries whose files were removed
+/// outside the app (e.g. the system trash was emptied) are dropped.
+pub async fn list_trash(app: &tauri::AppHandle) -> Result<Vec<TrashEntry>, String> {
+    let pool = crate::db_pool(app).await?;
+    let rows = sqlx::query("SELECT * FROM trash ORDER BY deletedAt DESC")
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| format!("Failed to query trash: {}", e))?;
+
+    let mut entries = Vec::new();
+    for row in rows {
+        let entry = row_to_entry(&row)?;
+        if fs::symlink_metadata(&entry.trash_path).is_err() {
+            delete_entry(&pool, &entry.id).await?;
+            continue;
+        }
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// Move a trashed item back to its original location
+pub async fn restore_from_trash(app: &tauri::AppHandle, id: &str) -> Result<TrashEntry, String> {
+    let pool = crate::db_pool(app).await?;
+    let entry = get_entry(&pool, id).await?;
+
+    let original_path = Path::new(&entry.original_path);
+    if fs::symlink_metadata(original_path).is_ok() {
+        return Err(format!(
+            "Cannot restore: {} already exists",
+            entry.original_path
+        ));
+    }
+    if let Some(parent) = original_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create parent directory: {}", e))?;
+    }
+
+    let trash_path = Path::new(&entry.trash_path);
+    move_path(trash_path, original_path).map_err(|e| format!("Failed to restore: {}", e))?;
+
+    match entry.location {
+        TrashLocation::App => {
+            if let Some(entry_dir) = trash_path.parent() {
+                let _ = fs::remove_dir_all(entry_dir);
+            }
+        }
+        TrashLocation::System => freedesktop::remove_info(trash_path),
+    }
+
+    delete_entry(&pool, id).await?;
+    Ok(entry)
+}
+
+/// Permanently delete the given trash entries, or everything when `ids` is None.
+/// Returns the number of entries removed.
+pub async fn empty_trash(
+    app: &tauri::AppHandle,
+    ids: Option<Vec<String>>,
+) -> Result<usize, String> {
+    let pool = crate::db_pool(app).await?;
+
+    let entries = match ids {
+        Some(ids) => {
+            let mut entries = Vec::new();
+            for id in ids {
+                entries.push(get_entry(&pool, &id).await?);
+            }
+            entries
+        }
+        None => {
+            let rows = sqlx::query("SELECT * FROM trash")
+                .fetch_all(&pool)
+                .await
+                .map_err(|e| format!("Failed to query trash: {}", e))?;
+            rows.iter()
+                .map(row_to_entry)
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+
+    let count = entries.len();
+    for entry in entries {
+        let trash_path = Path::new(&entry.trash_path);
+        match entry.location {
+            TrashLocation::App => {
+                let target = trash_path.parent().unwrap_or(trash_path);
+                if target.exists() {
+                    remove_path(target)
+                        .map_err(|e| format!("Failed to delete {}: {}", entry.trash_path, e))?;
+                }
+            }
+            TrashLocation::System => {
+                if fs::symlink_metadata(trash_path).is_ok() {
+                    remove_path(trash_path)
+                        .map_err(|e| format!("Failed to delete {}: {}", entry.trash_path, e))?;
+                }
+                freedesktop::remove_info(trash_path);
+            }
+        }
+        delete_entry(&pool, &entry.id).await?;
+    }
+
+    Ok(count)
+}
+
+/// Home trash as described by the freedesktop.org trash specification
+#[cfg(target_os = "linux")]
+mod freedesktop {
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+
+    use crate::files::transfer::move_path;
+
+    fn home_trash_dir() -> Option<PathBuf> {
+        std::env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+            .map(|data_home| data_home.join("Trash"))
+    }
+
+    fn encode_path(path: &Path) -> String {
+        let mut encoded = String::new();
+        for &byte in path.as_os_str().as_bytes() {
+            if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        encoded
+    }
+
+    pub fn trash(path: &Path) -> Result<PathBuf, String> {
+        let trash_dir =
+            home_trash_dir().ok_or_else(|| "Failed to locate the system trash".to_string())?;
+        let files_dir = trash_dir.join("files");
+        let info_dir = trash_dir.join("info");
+        fs::create_dir_all(&files_dir)
+            .and_then(|_| fs::create_dir_all(&info_dir))
+            .map_err(|e| format!("Failed to create system trash: {}", e))?;
+
+        let absolute =
+            std::path::absolute(path).map_err(|e| format!("Failed to resolve path: {}", e))?;
+        let name = absolute
+            .file_name()
+            .ok_or_else(|| "Cannot trash a path without a file name".to_string())?
+            .to_string_lossy()
+            .to_string();
+
+        // Reserve a unique name by creating the .trashinfo file exclusively
+        let mut candidate = name.clone();
+        let mut counter = 1;
+        let info_path = loop {
+            let info_path = info_dir.join(format!("{}.trashinfo", candidate));
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&info_path)
+            {
+                Ok(_) if !files_dir.join(&candidate).exists() => break info_path,
+                Ok(_) => {
+                    let _ = fs::remove_file(&info_path);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(format!("Failed to write trash info: {}", e)),
+            }
+            candidate = format!("{}.{}", name, counter);
+            counter += 1;
+        };
+
+        let info = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_path(&absolute),
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+        );
+        let trash_path = files_dir.join(&candidate);
+        let result = fs::write(&info_path, info).and_then(|_| move_path(&absolute, &trash_path));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&info_path);
+            return Err(format!("Failed to move to system trash: {}", e));
+        }
+
+        Ok(trash_path)
+    }
+
+    /// Remove the .trashinfo file that belongs to a file in `Trash/files`
+    pub fn remove_info(trash_path: &Path) {
+        let (Some(files_dir), Some(name)) = (trash_path.parent(), trash_path.file_name()) else {
+            return;
+        };
+        if let Some(trash_dir) = files_dir.parent() {
+            let info_path = trash_dir
+                .join("info")
+                .join(format!("{}.trashinfo", name.to_string_lossy()));
+            let _ = fs::remove_file(info_path);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod freedesktop {
+    use std::path::{Path, PathBuf};
+
+    pub fn trash(_path: &Path) -> Result<PathBuf, String> {
+        Err("System trash is only supported on Linux".to_string())
+    }
+
+    pub fn remove_info(_trash_path: &Path) {}
+}
@@ -0,0 +1,639 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+use crate::files::{documents, finder};
+
+pub const FILE_CHANGED_EVENT: &str = "file_changed";
+pub const FILE_TREE_CHANGED_EVENT: &str = "file_tree_changed";
+
+/// A batch is flushed once no event arrived for this long...
+const DEBOUNCE: Duration = Duration::from_millis(100);
+/// ...or when it has been collecting for this long, whichever comes first
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamedPath {
+    pub from: String,
+    pub to: String,
+}
+
+/// Structural changes under the watched directory during one debounce window.
+/// Paths are relative to the watched directory.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTreeChangedPayload {
+    pub created: Vec<String>,
+    pub removed: Vec<String>,
+    pub renamed: Vec<RenamedPath>,
+}
+
+/// `.gitignore` aware filter, with one matcher per directory loaded lazily
+struct IgnoreFilter {
+    root: PathBuf,
+    /// `.git/info/exclude` and the global excludes file
+    base: Vec<Gitignore>,
+    matchers: HashMap<PathBuf, Gitignore>,
+}
+
+impl IgnoreFilter {
+    /// Filter for events under `dir`, rooted at the enclosing repository so
+    /// `.gitignore` files above `dir` and `.git/info/exclude` apply too
+    fn for_dir(dir: &Path) -> Self {
+        let root = dir
+            .ancestors()
+            .find(|ancestor| ancestor.join(".git").exists())
+            .unwrap_or(dir);
+        Self::new(root)
+    }
+
+    fn new(root: &Path) -> Self {
+        let mut base = Vec::new();
+        let mut exclude = GitignoreBuilder::new(root);
+        if exclude
+            .add(root.join(".git").join("info").join("exclude"))
+            .is_none()
+        {
+            if let Ok(matcher) = exclude.build() {
+                base.push(matcher);
+            }
+        }
+        // Rooted at the workspace; Gitignore::global() would use the process cwd
+        let (global, _) = GitignoreBuilder::new(root).build_global();
+        base.push(global);
+
+        Self {
+            root: root.to_path_buf(),
+            base,
+            matchers: HashMap::new(),
+        }
+    }
+
+    fn matcher_for(&mut self, dir: &Path) -> &Gitignore {
+        self.matchers.entry(dir.to_path_buf()).or_insert_with(|| {
+            let file = dir.join(".gitignore");
+            if file.is_file() {
+                Gitignore::new(file).0
+            } else {
+                Gitignore::empty()
+            }
+        })
+    }
+
+    /// Forget the cached matcher of a directory whose `.gitignore` changed
+    fn invalidate(&mut self, gitignore: &Path) {
+        if let Some(dir) = gitignore.parent() {
+            self.matchers.remove(dir);
+        }
+    }
+
+    fn is_ignored_as(&mut self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if relative.components().any(|c| c.as_os_str() == ".git") {
+            return true;
+        }
+
+        let mut ignored = false;
+        for matcher in &self.base {
+            let matched = matcher.matched_path_or_any_parents(path, is_dir);
+            if matched.is_ignore() {
+                ignored = true;
+            }
+        }
+        // Deeper .gitignore files override shallower ones
+        let mut dir = self.root.clone();
+        let mut dirs = vec![dir.clone()];
+        if let Some(parent) = relative.parent() {
+            for component in parent.components() {
+                dir.push(component);
+                dirs.push(dir.clone());
+            }
+        }
+        for dir in dirs {
+            let matched = self
+                .matcher_for(&dir)
+                .matched_path_or_any_parents(path, is_dir);
+            if matched.is_ignore() {
+                ignored = true;
+            } else if matched.is_whitelist() {
+                ignored = false;
+            }
+        }
+        ignored
+    }
+
+    fn is_ignored(&mut self, path: &Path) -> bool {
+        match path.symlink_metadata() {
+            Ok(metadata) => self.is_ignored_as(path, metadata.is_dir()),
+            // Gone paths may have been either
+            Err(_) => self.is_ignored_as(path, false) || self.is_ignored_as(path, true),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TreeChange {
+    Created,
+    Removed,
+}
+
+/// Raw events of one debounce window, coalesced per path
+#[derive(Default)]
+struct Batch {
+    tree: BTreeMap<PathBuf, TreeChange>,
+    renames: Vec<(PathBuf, PathBuf)>,
+    modified: BTreeSet<PathBuf>,
+    /// "rename from" halves waiting for their "rename to", by inotify cookie
+    pending_from: HashMap<usize, PathBuf>,
+    /// Cookies already paired from their halves; inotify follows those up
+    /// with a combined event that has to be skipped
+    paired_cookies: HashSet<usize>,
+    /// Rename halves without a cookie (FSEvents, Windows), paired on flush
+    unpaired: Vec<PathBuf>,
+}
+
+impl Batch {
+    fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+            && self.renames.is_empty()
+            && self.modified.is_empty()
+            && self.pending_from.is_empty()
+            && self.unpaired.is_empty()
+    }
+
+    fn created(&mut self, path: PathBuf) {
+        match self.tree.get(&path) {
+            // Deleted and recreated, e.g. an editor saving by replacing the file
+            Some(TreeChange::Removed) => {
+                self.tree.remove(&path);
+                self.modified.insert(path);
+            }
+            _ => {
+                self.tree.insert(path, TreeChange::Created);
+            }
+        }
+    }
+
+    fn removed(&mut self, path: PathBuf) {
+        self.modified.remove(&path);
+        match self.tree.get(&path) {
+            // Created and removed within the window, e.g. a temp file
+            Some(TreeChange::Created) => {
+                self.tree.remove(&path);
+            }
+            _ => {
+                self.tree.insert(path, TreeChange::Removed);
+            }
+        }
+    }
+
+    fn renamed(&mut self, from: PathBuf, to: PathBuf) {
+        if self.modified.remove(&from) {
+            self.modified.insert(to.clone());
+        }
+        if self.tree.get(&from) == Some(&TreeChange::Created) {
+            // A file created in this window and renamed into place, which is
+            // how atomic saves look
+            self.tree.remove(&from);
+            self.created(to.clone());
+            self.modified.insert(to);
+            return;
+        }
+        self.renames.push((from, to));
+    }
+
+    fn add(&mut self, event: notify::Event) {
+        let mut paths = event.paths.into_iter();
+        match event.kind {
+            EventKind::Create(_) => paths.for_each(|p| self.created(p)),
+            EventKind::Remove(_) => paths.for_each(|p| self.removed(p)),
+            EventKind::Modify(ModifyKind::Data(_)) | EventKind::Modify(ModifyKind::Any) => {
+                self.modified.extend(paths)
+            }
+            EventKind::Modify(ModifyKind::Name(mode)) => match mode {
+                RenameMode::Both => {
+                    let cookie = event.attrs.tracker();
+                    if cookie.is_some_and(|cookie| self.paired_cookies.remove(&cookie)) {
+                        return;
+                    }
+                    if let (Some(from), Some(to)) = (paths.next(), paths.next()) {
+                        self.renamed(from, to);
+                    }
+                }
+                RenameMode::From => {
+                    if let Some(from) = paths.next() {
+                        match event.attrs.tracker() {
+                            Some(cookie) => {
+                                self.pending_from.insert(cookie, from);
+                            }
+                            None => self.unpaired.push(from),
+                        }
+                    }
+                }
+                RenameMode::To => {
+                    if let Some(to) = paths.next() {
+                        let cookie = event.attrs.tracker();
+                        let from = cookie.and_then(|cookie| self.pending_from.remove(&cookie));
+                        match from {
+                            Some(from) => {
+                                self.paired_cookies.extend(cookie);
+                                self.renamed(from, to);
+                            }
+                            None => self.created(to),
+                        }
+                    }
+                }
+                _ => self.unpaired.extend(paths),
+            },
+            _ => {}
+        }
+    }
+
+    /// Pair rename halves that came without a cookie: paths that no longer
+    /// exist are sources, existing ones are targets. A lone pair, or pairs
+    /// with the same file name, are treated as renames.
+    fn pair_renames(&mut self) {
+        for (_, from) in std::mem::take(&mut self.pending_from) {
+            self.removed(from);
+        }
+        self.paired_cookies.clear();
+
+        let (mut targets, mut sources): (Vec<PathBuf>, Vec<PathBuf>) =
+            std::mem::take(&mut self.unpaired)
+                .into_iter()
+                .partition(|p| p.symlink_metadata().is_ok());
+        sources.dedup();
+        targets.dedup();
+
+        if sources.len() == 1 && targets.len() == 1 {
+            self.renamed(sources.remove(0), targets.remove(0));
+        } else {
+            sources.retain(|from| {
+                let position = targets
+                    .iter()
+                    .position(|to| to.file_name() == from.file_name());
+                match position {
+                    Some(index) => {
+                        let to = targets.remove(index);
+                        self.renamed(from.clone(), to);
+                        false
+                    }
+                    None => true,
+                }
+            });
+        }
+        sources.into_iter().for_each(|p| self.removed(p));
+        targets.into_iter().for_each(|p| self.created(p));
+    }
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Changes of one flushed batch, with ignored paths removed
+#[derive(Default)]
+struct ChangeSet {
+    created: Vec<PathBuf>,
+    removed: Vec<PathBuf>,
+    renamed: Vec<(PathBuf, PathBuf)>,
+    modified: BTreeSet<PathBuf>,
+}
+
+impl ChangeSet {
+    fn from_batch(mut batch: Batch, filter: &mut Option<IgnoreFilter>) -> Self {
+        batch.pair_renames();
+        let mut ignored = |path: &Path| filter.as_mut().is_some_and(|f| f.is_ignored(path));
+
+        let mut changes = ChangeSet::default();
+        for (path, change) in batch.tree {
+            if ignored(&path) {
+                continue;
+            }
+            match change {
+                TreeChange::Created => changes.created.push(path),
+                TreeChange::Removed => changes.removed.push(path),
+            }
+        }
+        for (from, to) in batch.renames {
+            match (ignored(&from), ignored(&to)) {
+                (true, true) => continue,
+                (true, false) => changes.created.push(to.clone()),
+                (false, true) => changes.removed.push(from),
+                (false, false) => changes.renamed.push((from, to.clone())),
+            }
+            // Whatever now lives at `to` has different content than before
+            changes.modified.insert(to);
+        }
+        for path in batch.modified {
+            if !ignored(&path) {
+                changes.modified.insert(path);
+            }
+        }
+        changes
+    }
+
+    /// Paths whose content may differ from before the batch
+    fn content_paths(&self) -> Vec<&Path> {
+        self.modified
+            .iter()
+            .chain(&self.removed)
+            .chain(self.renamed.iter().map(|(from, _)| from))
+            .map(PathBuf::as_path)
+            .collect()
+    }
+
+    fn tree_paths(&self) -> Vec<PathBuf> {
+        self.created
+            .iter()
+            .chain(&self.removed)
+            .chain(self.renamed.iter().flat_map(|(from, to)| [from, to]))
+            .cloned()
+            .collect()
+    }
+}
+
+struct Subscriber {
+    base_path: PathBuf,
+    /// The watched file or directory
+    target: PathBuf,
+    is_file: bool,
+    window: tauri::Window,
+}
+
+impl Subscriber {
+    fn emit_file_changed(&self, id: &str, path: &Path, change: &str) {
+        let _ = self.window.emit(
+            &format!("{}::{}", FILE_CHANGED_EVENT, id),
+            serde_json::json!({
+                "path": relative(&self.base_path, path),
+                "type": change
+            }),
+        );
+    }
+
+    /// Deliver the part of `changes` that concerns this subscription
+    fn dispatch(&self, id: &str, changes: &ChangeSet) {
+        if self.is_file {
+            let target = self.target.as_path();
+            for (from, to) in &changes.renamed {
+                if from == target {
+                    self.emit_file_changed(id, to, "renamed");
+                }
+            }
+            if changes.removed.iter().any(|p| p == target) {
+                self.emit_file_changed(id, target, "removed");
+            } else if changes.modified.contains(target) && target.is_file() {
+                self.emit_file_changed(id, target, "modified");
+            }
+            return;
+        }
+
+        let under = |path: &Path| path.starts_with(&self.target) && path != self.target;
+        let mut payload = FileTreeChangedPayload::default();
+        for path in changes.created.iter().filter(|p| under(p)) {
+            payload.created.push(relative(&self.base_path, path));
+        }
+        for path in changes.removed.iter().filter(|p| under(p)) {
+            payload.removed.push(relative(&self.base_path, path));
+        }
+        for (from, to) in &changes.renamed {
+            match (under(from), under(to)) {
+                (true, true) => payload.renamed.push(RenamedPath {
+                    from: relative(&self.base_path, from),
+                    to: relative(&self.base_path, to),
+                }),
+                (true, false) => payload.removed.push(relative(&self.base_path, from)),
+                (false, true) => payload.created.push(relative(&self.base_path, to)),
+                (false, false) => {}
+            }
+        }
+        if !payload.created.is_empty() || !payload.removed.is_empty() || !payload.renamed.is_empty()
+        {
+            let _ = self
+                .window
+                .emit(&format!("{}::{}", FILE_TREE_CHANGED_EVENT, id), &payload);
+        }
+
+        for path in changes.modified.iter().filter(|p| under(p)) {
+            if path.is_file() {
+                self.emit_file_changed(id, path, "modified");
+            }
+        }
+    }
+}
+
+type Subscribers = Arc<StdMutex<HashMap<String, Subscriber>>>;
+
+/// Collect raw notify events into batches and dispatch them to subscribers
+/// once they settle. Returns when the watcher, and with it the sending side,
+/// is dropped.
+fn run_debouncer(
+    receiver: mpsc::Receiver<notify::Result<notify::Event>>,
+    dir: PathBuf,
+    recursive: bool,
+    subscribers: Subscribers,
+) {
+    // Single files are delivered even when ignored, e.g. an open .env file
+    let mut filter = recursive.then(|| IgnoreFilter::for_dir(&dir));
+    let mut batch = Batch::default();
+    let mut batch_started: Option<Instant> = None;
+
+    loop {
+        let timeout = match batch_started {
+            Some(started) => DEBOUNCE.min(MAX_BATCH_DELAY.saturating_sub(started.elapsed())),
+            None => Duration::from_secs(3600),
+        };
+
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(event)) => {
+                if let Some(filter) = filter.as_mut() {
+                    // Cheap check first so floods inside .git or node_modules stay cheap
+                    if event.paths.iter().all(|p| filter.is_ignored(p)) {
+                        continue;
+                    }
+                    for path in &event.paths {
+                        if path.file_name().is_some_and(|n| n == ".gitignore") {
+                            filter.invalidate(path);
+                        }
+                    }
+                }
+                batch.add(event);
+                let started = *batch_started.get_or_insert_with(Instant::now);
+                // A steady stream of events never lets recv_timeout time out
+                if started.elapsed() >= MAX_BATCH_DELAY {
+                    flush(&mut batch, &mut filter, recursive, &subscribers);
+                    batch_started = None;
+                }
+            }
+            Ok(Err(e)) => eprintln!("Watch error: {:?}", e),
+            Err(RecvTimeoutError::Timeout) => {
+                flush(&mut batch, &mut filter, recursive, &subscribers);
+                batch_started = None;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+/// Dispatch the collected batch, if any, and start a new one
+fn flush(
+    batch: &mut Batch,
+    filter: &mut Option<IgnoreFilter>,
+    recursive: bool,
+    subscribers: &Subscribers,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let changes = ChangeSet::from_batch(std::mem::take(batch), filter);
+    if recursive {
+        let tree_paths = changes.tree_paths();
+        if !tree_paths.is_empty() {
+            finder::update_index(&tree_paths);
+        }
+    }
+    for (id, subscriber) in subscribers.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        subscriber.dispatch(id, &changes);
+    }
+    documents::disk_changed(&changes.content_paths());
+}
+
+/// One OS watch shared by every subscription that needs it
+struct WatchedPath {
+    _watcher: notify::RecommendedWatcher,
+    subscribers: Subscribers,
+}
+
+/// Directory and whether it is watched recursively
+type WatchKey = (PathBuf, bool);
+
+#[derive(Default)]
+struct WatchRegistry {
+    watched: HashMap<WatchKey, WatchedPath>,
+    subscriptions: HashMap<String, WatchKey>,
+}
+
+static WATCH_REGISTRY: std::sync::LazyLock<StdMutex<WatchRegistry>> =
+    std::sync::LazyLock::new(|| StdMutex::new(WatchRegistry::default()));
+
+fn start_watch(dir: &Path, recursive: bool) -> Result<WatchedPath, String> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = sender.send(res);
+    })
+    .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher
+        .watch(dir, mode)
+        .map_err(|e| format!("Failed to watch directory: {}", e))?;
+
+    let subscribers: Subscribers = Arc::new(StdMutex::new(HashMap::new()));
+    let thread_dir = dir.to_path_buf();
+    let thread_subscribers = subscribers.clone();
+    std::thread::spawn(move || run_debouncer(receiver, thread_dir, recursive, thread_subscribers));
+
+    Ok(WatchedPath {
+        _watcher: watcher,
+        subscribers,
+    })
+}
+
+/// Subscribe to changes of `relative_path` under `base_path` (the base itself
+/// when empty or "."). Directories are watched recursively and get debounced
+/// `file_tree_changed::<id>` and `file_changed::<id>` events; files get
+/// `file_changed::<id>` only. Subscriptions to the same path share one OS watch.
+pub fn subscribe(
+    base_path: &Path,
+    relative_path: &str,
+    window: tauri::Window,
+) -> Result<String, String> {
+    if !base_path.exists() {
+        return Err("Base path does not exist".to_string());
+    }
+    let target = match relative_path {
+        "" | "." => base_path.to_path_buf(),
+        relative_path => base_path.join(relative_path),
+    };
+    let is_file = target.is_file();
+    if !is_file && !target.is_dir() {
+        return Err(format!("Path does not exist: {}", target.display()));
+    }
+
+    // Files are watched through their directory so atomic replaces are seen
+    let key: WatchKey = if is_file {
+        let parent = target.parent().unwrap_or(base_path).to_path_buf();
+        (parent, false)
+    } else {
+        (target.clone(), true)
+    };
+
+    let mut registry = WATCH_REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    if !registry.watched.contains_key(&key) {
+        let watched = start_watch(&key.0, key.1)?;
+        if key.1 {
+            finder::watch_started(&key.0);
+        }
+        registry.watched.insert(key.clone(), watched);
+    }
+
+    let id = nanoid::nanoid!();
+    registry.watched[&key]
+        .subscribers
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(
+            id.clone(),
+            Subscriber {
+                base_path: base_path.to_path_buf(),
+                target,
+                is_file,
+                window,
+            },
+        );
+    registry.subscriptions.insert(id.clone(), key);
+    Ok(id)
+}
+
+/// Drop a subscription; the OS watch goes away with its last subscriber
+pub fn unsubscribe(id: &str) -> Result<(), String> {
+    let mut registry = WATCH_REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let key = registry
+        .subscriptions
+        .remove(id)
+        .ok_or_else(|| format!("Watch subscription {} not found", id))?;
+
+    let now_empty = registry.watched.get(&key).is_some_and(|watched| {
+        let mut subscribers = watched
+            .subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        subscribers.remove(id);
+        subscribers.is_empty()
+    });
+    if now_empty {
+        registry.watched.remove(&key);
+        if key.1 {
+            finder::watch_stopped(&key.0);
+        }
+    }
+    Ok(())
+}
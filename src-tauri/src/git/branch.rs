@@ -0,0 +1,238 @@
+use git2::build::CheckoutBuilder;
+use git2::{Branch, BranchType, CheckoutNotificationType, ObjectType, Repository, RepositoryState};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::git::commit::{self, CommitAuthor};
+use crate::git::merge::{self, MergeResult};
+use crate::git::{head_commit, open_repo, path_string};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchInfo {
+    /// Short name, e.g. "main" or "origin/main" for remote branches
+    pub name: String,
+    pub remote: bool,
+    /// Checked out in this working tree
+    pub current: bool,
+    pub commit: String,
+    pub summary: String,
+    /// Commit time in milliseconds since the Unix epoch
+    pub time: i64,
+    /// Upstream of a local branch, e.g. "origin/main"
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagInfo {
+    pub name: String,
+    /// Commit the tag points to
+    pub commit: String,
+    /// Message of an annotated tag, `None` for lightweight tags
+    pub message: Option<String>,
+    pub tagger: Option<String>,
+    /// Tag time for annotated tags, commit time otherwise, in milliseconds
+    pub time: i64,
+}
+
+fn branch_info(repo: &Repository, branch: &Branch, kind: BranchType) -> Option<BranchInfo> {
+    let name = branch.name().ok().flatten()?.to_string();
+    let commit = branch.get().peel_to_commit().ok()?;
+    let upstream = branch.upstream().ok();
+    let (ahead, behind) = upstream
+        .as_ref()
+        .and_then(|upstream| upstream.get().target())
+        .and_then(|target| repo.graph_ahead_behind(commit.id(), target).ok())
+        .unwrap_or((0, 0));
+
+    Some(BranchInfo {
+        name,
+        remote: kind == BranchType::Remote,
+        current: branch.is_head(),
+        commit: commit.id().to_string(),
+        summary: commit.summary().unwrap_or_default().to_string(),
+        time: commit.time().seconds() * 1000,
+        upstream: upstream.and_then(|upstream| upstream.name().ok().flatten().map(str::to_string)),
+        ahead,
+        behind,
+    })
+}
+
+/// Local branches, and remote-tracking ones when `include_remote` is set,
+/// sorted by name. Remote HEAD aliases are left out.
+pub fn list_branches(base_path: &Path, include_remote: bool) -> Result<Vec<BranchInfo>, String> {
+    let repo = open_repo(base_path)?;
+    let filter = if include_remote {
+        None
+    } else {
+        Some(BranchType::Local)
+    };
+    let mut branches = Vec::new();
+    for entry in repo
+        .branches(filter)
+        .map_err(|e| format!("Failed to list branches: {}", e))?
+    {
+        let (branch, kind) = entry.map_err(|e| format!("Failed to list branches: {}", e))?;
+        if branch.get().symbolic_target().is_some() {
+            continue;
+        }
+        if let Some(info) = branch_info(&repo, &branch, kind) {
+            branches.push(info);
+        }
+    }
+    branches.sort_by(|a, b| (a.remote, &a.name).cmp(&(b.remote, &b.name)));
+    Ok(branches)
+}
+
+/// Create branch `name` at `start_point` (HEAD by default), checking it out
+/// when `checkout` is set
+pub fn create_branch(
+    base_path: &Path,
+    name: &str,
+    start_point: Option<&str>,
+    checkout: bool,
+) -> Result<BranchInfo, String> {
+    if !Branch::name_is_valid(name).unwrap_or(false) {
+        return Err(format!("Invalid branch name: {}", name));
+    }
+    let repo = open_repo(base_path)?;
+    let target = match start_point {
+        Some(revision) => repo
+            .revparse_single(revision)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| format!("Failed to resolve {}: {}", revision, e))?,
+        None => head_commit(&repo)?
+            .ok_or_else(|| "Commit at least once before creating a branch".to_string())?,
+    };
+    let mut branch = repo
+        .branch(name, &target, false)
+        .map_err(|e| match e.code() {
+            git2::ErrorCode::Exists => format!("Branch {} already exists", name),
+            _ => format!("Failed to create branch {}: {}", name, e),
+        })?;
+
+    if checkout {
+        // Don't leave a branch behind that the caller was told wasn't created
+        if let Err(e) = self::checkout(base_path, name) {
+            return Err(match branch.delete() {
+                Ok(()) => e,
+                Err(delete) => format!("{}; branch {} was left behind: {}", e, name, delete),
+            });
+        }
+    }
+    branch_info(&repo, &branch, BranchType::Local)
+        .ok_or_else(|| format!("Failed to read branch {}", name))
+}
+
+/// Switch the working tree to local branch `target`, or detach HEAD at any
+/// other revision. Local changes are carried over like `git checkout` does;
+/// when any of them would be overwritten nothing changes and the error lists
+/// the files.
+pub fn checkout(base_path: &Path, target: &str) -> Result<(), String> {
+    let repo = open_repo(base_path)?;
+    if repo.state() != RepositoryState::Clean {
+        return Err("Another operation is in progress; conclude or abort it first".to_string());
+    }
+
+    let branch = repo.find_branch(target, BranchType::Local).ok();
+    let object = match &branch {
+        Some(branch) => branch.get().peel(ObjectType::Commit),
+        None => repo
+            .revparse_single(target)
+            .and_then(|object| object.peel(ObjectType::Commit)),
+    }
+    .map_err(|e| format!("Failed to resolve {}: {}", target, e))?;
+
+    let mut conflicts = Vec::new();
+    let result = {
+        let mut builder = CheckoutBuilder::new();
+        builder
+            .safe()
+            .notify_on(CheckoutNotificationType::CONFLICT)
+            .notify(|_, path, _, _, _| {
+                conflicts.push(path_string(path));
+                true
+            });
+        repo.checkout_tree(&object, Some(&mut builder))
+    };
+    if let Err(e) = result {
+        return Err(if conflicts.is_empty() {
+            format!("Failed to check out {}: {}", target, e)
+        } else {
+            format!(
+                "Local changes to {} would be overwritten; commit or stash them first",
+                conflicts.join(", ")
+            )
+        });
+    }
+
+    match branch.as_ref().and_then(|branch| branch.get().name()) {
+        Some(reference) => repo.set_head(reference),
+        None => repo.set_head_detached(object.id()),
+    }
+    .map_err(|e| format!("Failed to update HEAD: {}", e))
+}
+
+/// Merge a branch or any other revision into HEAD: a fast-forward when
+/// possible, otherwise a merge commit. Conflicts are reported and left in the
+/// working tree to resolve and commit.
+pub fn merge(base_path: &Path, source: &str, author: &CommitAuthor) -> Result<MergeResult, String> {
+    let repo = open_repo(base_path)?;
+    let signature = commit::signature(&repo, author)?;
+    let (their, message) = match repo.resolve_reference_from_short_name(source) {
+        Ok(reference) if reference.is_branch() || reference.is_remote() => (
+            repo.reference_to_annotated_commit(&reference),
+            format!("Merge branch '{}'", source),
+        ),
+        _ => {
+            let commit = repo
+                .revparse_single(source)
+                .and_then(|object| object.peel_to_commit())
+                .map_err(|e| format!("Failed to resolve {}: {}", source, e))?;
+            (
+                repo.find_annotated_commit(commit.id()),
+                format!("Merge commit '{}'", source),
+            )
+        }
+    };
+    let their = their.map_err(|e| format!("Failed to resolve {}: {}", source, e))?;
+    merge::merge_into_head(&repo, &their, &message, &signature)
+}
+
+/// Tags sorted by name
+pub fn list_tags(base_path: &Path) -> Result<Vec<TagInfo>, String> {
+    let repo = open_repo(base_path)?;
+    let names = repo
+        .tag_names(None)
+        .map_err(|e| format!("Failed to list tags: {}", e))?;
+
+    let mut tags = Vec::new();
+    for name in names.iter().flatten() {
+        let Ok(object) = repo.revparse_single(&format!("refs/tags/{}", name)) else {
+            continue;
+        };
+        // Tags of trees or blobs have no commit to show
+        let Ok(commit) = object.peel_to_commit() else {
+            continue;
+        };
+        let annotation = object.as_tag();
+        tags.push(TagInfo {
+            name: name.to_string(),
+            commit: commit.id().to_string(),
+            message: annotation.and_then(|tag| tag.message().map(str::to_string)),
+            tagger: annotation
+                .and_then(|tag| tag.tagger())
+                .and_then(|tagger| tagger.name().map(str::to_string)),
+            time: annotation
+                .and_then(|tag| tag.tagger())
+                .map(|tagger| tagger.when().seconds())
+                .unwrap_or_else(|| commit.time().seconds())
+                * 1000,
+        });
+    }
+    tags.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(tags)
+}
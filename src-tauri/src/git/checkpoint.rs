@@ -0,0 +1,748 @@
+use git2::{
+    FileMode, IndexAddOption, ObjectType, Oid, Repository, Signature, Status, StatusOptions,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::Manager;
+
+use crate::git::{head_commit, sanitize_name};
+
+const CHECKPOINTS_DIR_NAME: &str = "checkpoints";
+const CHECKPOINT_REF_PREFIX: &str = "refs/raven/checkpoints";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+/// Shadow file contents of a chat, stored once each under their SHA-256
+const BLOBS_DIR_NAME: &str = "blobs";
+/// Oldest checkpoints of a chat are dropped beyond this
+const MAX_CHECKPOINTS_PER_CHAT: usize = 100;
+/// Larger untracked files are left out of checkpoints and never restored
+const MAX_SNAPSHOT_FILE_SIZE: u64 = 32 * 1024 * 1024;
+/// A checkpoint before an agent turn is skipped when it would have to store
+/// more new content than this...
+const MAX_SNAPSHOT_BYTES: u64 = 256 * 1024 * 1024;
+/// ...or cover more files than this
+const MAX_SNAPSHOT_FILES: usize = 20_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckpointKind {
+    /// A commit under `refs/raven/checkpoints/<chat>/`, outside of any branch
+    Git,
+    /// A copy of the directory in the app data directory
+    Shadow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    /// Starts with the creation time, so ids sort chronologically
+    pub id: String,
+    pub label: String,
+    /// Milliseconds since the Unix epoch
+    pub created_at: u64,
+    pub kind: CheckpointKind,
+    /// Snapshot commit of a git checkpoint
+    pub commit: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreResult {
+    /// Files written back with their checkpoint content
+    pub restored: Vec<String>,
+    /// Files created after the checkpoint, now deleted
+    pub removed: Vec<String>,
+    /// Checkpoint of the state just before restoring, to undo the restore
+    pub backup: Checkpoint,
+}
+
+/// A file of a shadow checkpoint; its content is the blob named `hash`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShadowFile {
+    hash: String,
+    size: u64,
+    /// Milliseconds since the Unix epoch, so the next checkpoint can reuse
+    /// the hash of a file that didn't change
+    mtime: u64,
+    executable: bool,
+}
+
+/// Shadow checkpoint metadata
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    checkpoint: Checkpoint,
+    cwd: String,
+    /// Files by path relative to `cwd`
+    files: BTreeMap<String, ShadowFile>,
+    /// Files left out for being too large, relative to `cwd`
+    skipped: Vec<String>,
+}
+
+fn over_budget(bytes: u64, files: usize) -> Option<String> {
+    if files > MAX_SNAPSHOT_FILES {
+        Some(format!(
+            "Skipped: {} files exceed the checkpoint limit of {}",
+            files, MAX_SNAPSHOT_FILES
+        ))
+    } else if bytes > MAX_SNAPSHOT_BYTES {
+        Some(format!(
+            "Skipped: {} MB of changes exceed the checkpoint limit of {} MB",
+            bytes / (1024 * 1024),
+            MAX_SNAPSHOT_BYTES / (1024 * 1024)
+        ))
+    } else {
+        None
+    }
+}
+
+pub fn checkpoints_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(CHECKPOINTS_DIR_NAME))
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))
+}
+
+fn new_checkpoint(label: &str, kind: CheckpointKind) -> Checkpoint {
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    Checkpoint {
+        id: format!(
+            "{:013}-{}",
+            created_at,
+            nanoid::nanoid!(8, &nanoid::alphabet::SAFE)
+        ),
+        label: label.to_string(),
+        created_at,
+        kind,
+        commit: None,
+    }
+}
+
+/// Remove `path` and then its parents while they are empty, stopping at `root`
+fn remove_file_and_empty_parents(root: &Path, path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let mut dir = path.parent();
+    while let Some(current) = dir {
+        if current == root || !current.starts_with(root) || fs::remove_dir(current).is_err() {
+            break;
+        }
+        dir = current.parent();
+    }
+    Ok(())
+}
+
+mod git_store {
+    use super::*;
+
+    fn ref_prefix(chat_id: &str) -> String {
+        format!("{}/{}/", CHECKPOINT_REF_PREFIX, sanitize_name(chat_id))
+    }
+
+    fn workdir(repo: &Repository) -> Result<&Path, String> {
+        repo.workdir()
+            .ok_or_else(|| "Repository has no working directory".to_string())
+    }
+
+    /// Tree of the working tree as it is now, untracked files included and
+    /// ignored or oversized untracked ones left out. Built from an in-memory
+    /// copy of the index that is never written back, so the real index is
+    /// untouched.
+    fn snapshot_tree(repo: &Repository) -> Result<Oid, String> {
+        let workdir = workdir(repo)?;
+        let mut index = repo
+            .index()
+            .map_err(|e| format!("Failed to get index: {}", e))?;
+        // Returning 1 skips the path
+        let mut skip_large = |path: &Path, _: &[u8]| -> i32 {
+            let size = fs::symlink_metadata(workdir.join(path)).map_or(0, |m| m.len());
+            i32::from(size > MAX_SNAPSHOT_FILE_SIZE)
+        };
+        index
+            .add_all(["*"], IndexAddOption::DEFAULT, Some(&mut skip_large))
+            .and_then(|_| index.update_all(["*"], None))
+            .and_then(|_| index.write_tree())
+            .map_err(|e| format!("Failed to snapshot working tree: {}", e))
+    }
+
+    /// Bytes and number of files a snapshot has to hash into the object
+    /// database: modified tracked files and untracked ones under the size cap
+    fn pending_changes(repo: &Repository) -> Result<(u64, usize), String> {
+        let workdir = workdir(repo)?;
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false)
+            .exclude_submodules(true);
+        let statuses = repo
+            .statuses(Some(&mut options))
+            .map_err(|e| format!("Failed to get status: {}", e))?;
+
+        let (mut bytes, mut files) = (0, 0);
+        for entry in statuses.iter() {
+            let status = entry.status();
+            if !status.intersects(Status::WT_NEW | Status::WT_MODIFIED | Status::WT_TYPECHANGE) {
+                continue;
+            }
+            let Some(path) = entry.path() else {
+                continue;
+            };
+            let size = fs::symlink_metadata(workdir.join(path)).map_or(0, |m| m.len());
+            if status.contains(Status::WT_NEW) && size > MAX_SNAPSHOT_FILE_SIZE {
+                continue;
+            }
+            bytes += size;
+            files += 1;
+        }
+        Ok((bytes, files))
+    }
+
+    pub fn create(
+        repo: &Repository,
+        chat_id: &str,
+        label: &str,
+        bounded: bool,
+    ) -> Result<Checkpoint, String> {
+        if bounded {
+            let (bytes, files) = pending_changes(repo)?;
+            if let Some(reason) = over_budget(bytes, files) {
+                return Err(reason);
+            }
+        }
+        let tree_id = snapshot_tree(repo)?;
+        let tree = repo
+            .find_tree(tree_id)
+            .map_err(|e| format!("Failed to find tree: {}", e))?;
+        let parent = head_commit(repo)?;
+        let signature = Signature::now("Raven", "checkpoints@raven.local")
+            .map_err(|e| format!("Failed to create signature: {}", e))?;
+
+        let mut checkpoint = new_checkpoint(label, CheckpointKind::Git);
+        let commit_id = repo
+            .commit(
+                None,
+                &signature,
+                &signature,
+                label,
+                &tree,
+                &parent.iter().collect::<Vec<_>>(),
+            )
+            .map_err(|e| format!("Failed to create checkpoint commit: {}", e))?;
+        repo.reference(
+            &format!("{}{}", ref_prefix(chat_id), checkpoint.id),
+            commit_id,
+            false,
+            "checkpoint",
+        )
+        .map_err(|e| format!("Failed to create checkpoint ref: {}", e))?;
+
+        checkpoint.commit = Some(commit_id.to_string());
+        Ok(checkpoint)
+    }
+
+    pub fn list(repo: &Repository, chat_id: &str) -> Result<Vec<Checkpoint>, String> {
+        let prefix = ref_prefix(chat_id);
+        let references = repo
+            .references_glob(&format!("{}*", prefix))
+            .map_err(|e| format!("Failed to list checkpoints: {}", e))?;
+
+        let mut checkpoints = Vec::new();
+        for reference in references.flatten() {
+            let (Some(name), Ok(commit)) = (reference.name(), reference.peel_to_commit()) else {
+                continue;
+            };
+            let id = name.trim_start_matches(&prefix).to_string();
+            let created_at = id
+                .split('-')
+                .next()
+                .and_then(|millis| millis.parse().ok())
+                .unwrap_or(0);
+            checkpoints.push(Checkpoint {
+                id,
+                label: commit.message().unwrap_or_default().to_string(),
+                created_at,
+                kind: CheckpointKind::Git,
+                commit: Some(commit.id().to_string()),
+            });
+        }
+        checkpoints.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(checkpoints)
+    }
+
+    pub fn delete(repo: &Repository, chat_id: &str, id: &str) -> Result<(), String> {
+        let name = format!("{}{}", ref_prefix(chat_id), id);
+        match repo.find_reference(&name) {
+            Ok(mut reference) => reference
+                .delete()
+                .map_err(|e| format!("Failed to delete checkpoint: {}", e)),
+            Err(_) => Ok(()),
+        }
+    }
+
+    fn write_entry(repo: &Repository, path: &Path, blob: Oid, mode: i32) -> Result<(), String> {
+        let blob = repo
+            .find_blob(blob)
+            .map_err(|e| format!("Failed to read checkpoint file: {}", e))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+        let _ = fs::remove_file(path);
+
+        #[cfg(unix)]
+        if mode == i32::from(FileMode::Link) {
+            let target = String::from_utf8_lossy(blob.content()).to_string();
+            return std::os::unix::fs::symlink(target, path)
+                .map_err(|e| format!("Failed to restore symlink: {}", e));
+        }
+        fs::write(path, blob.content()).map_err(|e| format!("Failed to restore file: {}", e))?;
+
+        #[cfg(unix)]
+        if mode == i32::from(FileMode::BlobExecutable) {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o755));
+        }
+        Ok(())
+    }
+
+    /// Make every file that changed since the checkpoint match it again
+    pub fn restore(
+        repo: &Repository,
+        checkpoint: &Checkpoint,
+    ) -> Result<(Vec<String>, Vec<String>), String> {
+        let workdir = workdir(repo)?;
+        let commit_id = checkpoint
+            .commit
+            .as_deref()
+            .and_then(|id| Oid::from_str(id).ok())
+            .ok_or_else(|| "Checkpoint has no commit".to_string())?;
+        let checkpoint_tree = repo
+            .find_commit(commit_id)
+            .and_then(|commit| commit.tree())
+            .map_err(|e| format!("Failed to read checkpoint: {}", e))?;
+        let current_tree = repo
+            .find_tree(snapshot_tree(repo)?)
+            .map_err(|e| format!("Failed to find tree: {}", e))?;
+
+        let diff = repo
+            .diff_tree_to_tree(Some(&checkpoint_tree), Some(&current_tree), None)
+            .map_err(|e| format!("Failed to compare with checkpoint: {}", e))?;
+
+        let (mut restored, mut removed) = (Vec::new(), Vec::new());
+        for delta in diff.deltas() {
+            let old = delta.old_file();
+            let new = delta.new_file();
+            // Files created since the checkpoint go away
+            if !old.exists() {
+                if let Some(path) = new.path() {
+                    remove_file_and_empty_parents(workdir, &workdir.join(path))
+                        .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+                    removed.push(path.to_string_lossy().to_string());
+                }
+                continue;
+            }
+            let Some(path) = old.path() else {
+                continue;
+            };
+            let is_blob = checkpoint_tree
+                .get_path(path)
+                .is_ok_and(|entry| entry.kind() == Some(ObjectType::Blob));
+            if !is_blob {
+                // Submodules are left alone
+                continue;
+            }
+            write_entry(repo, &workdir.join(path), old.id(), i32::from(old.mode()))?;
+            restored.push(path.to_string_lossy().to_string());
+        }
+        Ok((restored, removed))
+    }
+}
+
+mod shadow_store {
+    use super::*;
+
+    fn chat_dir(root: &Path, chat_id: &str) -> PathBuf {
+        root.join(sanitize_name(chat_id))
+    }
+
+    /// Directory of checkpoint `id`. Ids come from the frontend, so anything
+    /// that isn't a plain name is rejected rather than joined onto the path.
+    fn checkpoint_dir(root: &Path, chat_id: &str, id: &str) -> Result<PathBuf, String> {
+        if id.is_empty() || sanitize_name(id) != id {
+            return Err(format!("Invalid checkpoint id: {}", id));
+        }
+        Ok(chat_dir(root, chat_id).join(id))
+    }
+
+    fn mtime_millis(metadata: &fs::Metadata) -> u64 {
+        metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_millis() as u64)
+    }
+
+    #[cfg(unix)]
+    fn is_executable(metadata: &fs::Metadata) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+
+    #[cfg(not(unix))]
+    fn is_executable(_metadata: &fs::Metadata) -> bool {
+        false
+    }
+
+    /// Files of `cwd` that are not ignored, relative to it
+    fn walk(cwd: &Path) -> BTreeMap<PathBuf, fs::Metadata> {
+        let mut files = BTreeMap::new();
+        let walker = ignore::WalkBuilder::new(cwd)
+            .hidden(false)
+            .require_git(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+        for entry in walker.flatten() {
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let (Ok(relative), Ok(metadata)) = (entry.path().strip_prefix(cwd), entry.metadata())
+            else {
+                continue;
+            };
+            files.insert(relative.to_path_buf(), metadata);
+        }
+        files
+    }
+
+    fn hash_file(path: &Path) -> io::Result<String> {
+        let mut hasher = Sha256::new();
+        io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Copy `source` into the blob store, hashing it on the way. Content that
+    /// is already stored is not kept twice.
+    fn store_blob(blobs_dir: &Path, source: &Path) -> io::Result<String> {
+        let temp_path = blobs_dir.join(format!(".{}.tmp", nanoid::nanoid!(8)));
+        let result = (|| {
+            let mut reader = fs::File::open(source)?;
+            let mut writer = fs::File::create(&temp_path)?;
+            let mut hasher = Sha256::new();
+            let mut buffer = vec![0; 64 * 1024];
+            loop {
+                let read = reader.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+                writer.write_all(&buffer[..read])?;
+            }
+            let hash = format!("{:x}", hasher.finalize());
+            let blob = blobs_dir.join(&hash);
+            if blob.exists() {
+                fs::remove_file(&temp_path)?;
+            } else {
+                fs::rename(&temp_path, &blob)?;
+            }
+            Ok(hash)
+        })();
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    fn read_manifest(dir: &Path) -> Option<Manifest> {
+        let json = fs::read(dir.join(MANIFEST_FILE_NAME)).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+
+    /// Manifests of a chat's checkpoints, oldest first
+    fn manifests(root: &Path, chat_id: &str) -> Vec<Manifest> {
+        let Ok(entries) = fs::read_dir(chat_dir(root, chat_id)) else {
+            return Vec::new();
+        };
+        let mut manifests: Vec<Manifest> = entries
+            .flatten()
+            .filter_map(|entry| read_manifest(&entry.path()))
+            .collect();
+        manifests.sort_by(|a, b| a.checkpoint.id.cmp(&b.checkpoint.id));
+        manifests
+    }
+
+    pub fn create(
+        root: &Path,
+        cwd: &Path,
+        chat_id: &str,
+        label: &str,
+        bounded: bool,
+    ) -> Result<Checkpoint, String> {
+        let checkpoint = new_checkpoint(label, CheckpointKind::Shadow);
+        let chat_dir = chat_dir(root, chat_id);
+        let blobs_dir = chat_dir.join(BLOBS_DIR_NAME);
+
+        // Unchanged files keep the hash recorded by the previous checkpoint
+        let previous = manifests(root, chat_id)
+            .pop()
+            .map(|manifest| manifest.files)
+            .unwrap_or_default();
+        let reusable = |relative: &str, metadata: &fs::Metadata| {
+            previous.get(relative).filter(|file| {
+                file.size == metadata.len()
+                    && file.mtime == mtime_millis(metadata)
+                    && blobs_dir.join(&file.hash).exists()
+            })
+        };
+
+        let (mut included, mut skipped) = (Vec::new(), Vec::new());
+        for (relative, metadata) in walk(cwd) {
+            let relative = relative.to_string_lossy().to_string();
+            if metadata.len() > MAX_SNAPSHOT_FILE_SIZE {
+                skipped.push(relative);
+            } else {
+                included.push((relative, metadata));
+            }
+        }
+        if bounded {
+            let new_bytes = included
+                .iter()
+                .filter(|(relative, metadata)| reusable(relative, metadata).is_none())
+                .map(|(_, metadata)| metadata.len())
+                .sum();
+            if let Some(reason) = over_budget(new_bytes, included.len()) {
+                return Err(reason);
+            }
+        }
+
+        fs::create_dir_all(&blobs_dir)
+            .map_err(|e| format!("Failed to create checkpoint directory: {}", e))?;
+        let mut files = BTreeMap::new();
+        for (relative, metadata) in included {
+            let hash = match reusable(&relative, &metadata) {
+                Some(file) => file.hash.clone(),
+                None => store_blob(&blobs_dir, &cwd.join(&relative))
+                    .map_err(|e| format!("Failed to copy {}: {}", relative, e))?,
+            };
+            files.insert(
+                relative,
+                ShadowFile {
+                    hash,
+                    size: metadata.len(),
+                    mtime: mtime_millis(&metadata),
+                    executable: is_executable(&metadata),
+                },
+            );
+        }
+
+        let dir = chat_dir.join(&checkpoint.id);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create checkpoint directory: {}", e))?;
+        let manifest = Manifest {
+            checkpoint: checkpoint.clone(),
+            cwd: cwd.to_string_lossy().to_string(),
+            files,
+            skipped,
+        };
+        let json = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| format!("Failed to serialize checkpoint: {}", e))?;
+        if let Err(e) = fs::write(dir.join(MANIFEST_FILE_NAME), json) {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(format!("Failed to write checkpoint: {}", e));
+        }
+        Ok(checkpoint)
+    }
+
+    pub fn list(root: &Path, chat_id: &str) -> Vec<Checkpoint> {
+        manifests(root, chat_id)
+            .into_iter()
+            .map(|manifest| manifest.checkpoint)
+            .collect()
+    }
+
+    /// Delete blobs no remaining checkpoint of the chat refers to
+    fn collect_garbage(root: &Path, chat_id: &str) -> io::Result<()> {
+        let referenced: HashSet<String> = manifests(root, chat_id)
+            .into_iter()
+            .flat_map(|manifest| manifest.files.into_values().map(|file| file.hash))
+            .collect();
+        let Ok(entries) = fs::read_dir(chat_dir(root, chat_id).join(BLOBS_DIR_NAME)) else {
+            return Ok(());
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Temp files belong to a checkpoint being created
+            if !name.starts_with('.') && !referenced.contains(&name) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn delete(root: &Path, chat_id: &str, id: &str) -> Result<(), String> {
+        let dir = checkpoint_dir(root, chat_id, id)?;
+        match fs::remove_dir_all(&dir) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                return Err(format!("Failed to delete checkpoint: {}", e));
+            }
+            _ => {}
+        }
+        collect_garbage(root, chat_id)
+            .map_err(|e| format!("Failed to clean up checkpoint files: {}", e))
+    }
+
+    pub fn restore(
+        root: &Path,
+        cwd: &Path,
+        chat_id: &str,
+        id: &str,
+    ) -> Result<(Vec<String>, Vec<String>), String> {
+        let manifest = read_manifest(&checkpoint_dir(root, chat_id, id)?)
+            .ok_or_else(|| format!("Checkpoint {} not found", id))?;
+        if Path::new(&manifest.cwd) != cwd {
+            return Err(format!(
+                "Checkpoint {} was taken in {}, not {}",
+                id,
+                manifest.cwd,
+                cwd.display()
+            ));
+        }
+        let blobs_dir = chat_dir(root, chat_id).join(BLOBS_DIR_NAME);
+
+        let (mut restored, mut removed) = (Vec::new(), Vec::new());
+        for (relative, metadata) in walk(cwd) {
+            // Too large for the backup taken before restoring, so deleting
+            // it could not be undone
+            if metadata.len() > MAX_SNAPSHOT_FILE_SIZE {
+                continue;
+            }
+            let key = relative.to_string_lossy().to_string();
+            if !manifest.files.contains_key(&key) && !manifest.skipped.contains(&key) {
+                remove_file_and_empty_parents(cwd, &cwd.join(&relative))
+                    .map_err(|e| format!("Failed to remove {}: {}", relative.display(), e))?;
+                removed.push(key);
+            }
+        }
+        for (relative, file) in &manifest.files {
+            let target = cwd.join(relative);
+            let unchanged = fs::metadata(&target).is_ok_and(|m| m.len() == file.size)
+                && hash_file(&target).is_ok_and(|hash| hash == file.hash);
+            if unchanged {
+                continue;
+            }
+            target
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::copy(blobs_dir.join(&file.hash), &target))
+                .map_err(|e| format!("Failed to restore {}: {}", relative, e))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mode = if file.executable { 0o755 } else { 0o644 };
+                let _ = fs::set_permissions(&target, fs::Permissions::from_mode(mode));
+            }
+            restored.push(relative.clone());
+        }
+        Ok((restored, removed))
+    }
+}
+
+/// Git repositories get git checkpoints, anything else shadow copies
+fn open_repo(cwd: &Path) -> Option<Repository> {
+    Repository::open(cwd).ok().filter(|repo| !repo.is_bare())
+}
+
+pub fn list_checkpoints(
+    shadow_root: &Path,
+    cwd: &Path,
+    chat_id: &str,
+) -> Result<Vec<Checkpoint>, String> {
+    match open_repo(cwd) {
+        Some(repo) => git_store::list(&repo, chat_id),
+        None => Ok(shadow_store::list(shadow_root, chat_id)),
+    }
+}
+
+pub fn delete_checkpoint(
+    shadow_root: &Path,
+    cwd: &Path,
+    chat_id: &str,
+    id: &str,
+) -> Result<(), String> {
+    match open_repo(cwd) {
+        Some(repo) => git_store::delete(&repo, chat_id, id),
+        None => shadow_store::delete(shadow_root, chat_id, id),
+    }
+}
+
+/// With `bounded` set, fails with a "Skipped:" error instead of storing more
+/// than `MAX_SNAPSHOT_BYTES` of new content or `MAX_SNAPSHOT_FILES` files
+fn snapshot(
+    shadow_root: &Path,
+    cwd: &Path,
+    chat_id: &str,
+    label: &str,
+    bounded: bool,
+) -> Result<Checkpoint, String> {
+    match open_repo(cwd) {
+        Some(repo) => git_store::create(&repo, chat_id, label, bounded),
+        None => shadow_store::create(shadow_root, cwd, chat_id, label, bounded),
+    }
+}
+
+/// Snapshot `cwd` before an agent turn. The oldest checkpoints of the chat
+/// are dropped beyond `MAX_CHECKPOINTS_PER_CHAT`. Directories with too many
+/// files or changes are not snapshotted; that fails with a "Skipped:" error.
+pub fn create_checkpoint(
+    shadow_root: &Path,
+    cwd: &Path,
+    chat_id: &str,
+    label: &str,
+) -> Result<Checkpoint, String> {
+    let checkpoint = snapshot(shadow_root, cwd, chat_id, label, true)?;
+    let checkpoints = list_checkpoints(shadow_root, cwd, chat_id)?;
+    let excess = checkpoints.len().saturating_sub(MAX_CHECKPOINTS_PER_CHAT);
+    for old in &checkpoints[..excess] {
+        delete_checkpoint(shadow_root, cwd, chat_id, &old.id)?;
+    }
+    Ok(checkpoint)
+}
+
+/// Revert every file that changed since checkpoint `id` to its content at
+/// that point, deleting files created since unless they are too large to
+/// snapshot. Ignored files, the index and
+/// HEAD are left alone. The current state is checkpointed first.
+pub fn restore_checkpoint(
+    shadow_root: &Path,
+    cwd: &Path,
+    chat_id: &str,
+    id: &str,
+) -> Result<RestoreResult, String> {
+    let checkpoint = list_checkpoints(shadow_root, cwd, chat_id)?
+        .into_iter()
+        .find(|checkpoint| checkpoint.id == id)
+        .ok_or_else(|| format!("Checkpoint {} not found", id))?;
+
+    // Not pruned, which could drop the checkpoint being restored, and not
+    // bounded, since restoring without a way back is worse than a slow copy
+    let label = format!("Before restoring \"{}\"", checkpoint.label);
+    let backup = snapshot(shadow_root, cwd, chat_id, &label, false)?;
+    let (restored, removed) = match open_repo(cwd) {
+        Some(repo) => git_store::restore(&repo, &checkpoint)?,
+        None => shadow_store::restore(shadow_root, cwd, chat_id, id)?,
+    };
+    Ok(RestoreResult {
+        restored,
+        removed,
+        backup,
+    })
+}
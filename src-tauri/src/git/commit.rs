@@ -0,0 +1,169 @@
+use git2::{Repository, RepositoryState, Signature, StashApplyOptions, StashFlags};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::path::Path;
+
+use crate::git::{head_commit, open_repo};
+
+/// Commit author configured in the app settings (`git::default`). Missing
+/// fields fall back to the repository's git config.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitAuthor {
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitResult {
+    pub id: String,
+    pub summary: String,
+}
+
+/// Read the commit author from the settings table shared with the frontend
+pub async fn author_from_settings(app: &tauri::AppHandle) -> Result<CommitAuthor, String> {
+    let pool = crate::db_pool(app).await?;
+    let row = sqlx::query("SELECT value FROM settings WHERE id = 'git::default'")
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| format!("Failed to read git settings: {}", e))?;
+    let Some(row) = row else {
+        return Ok(CommitAuthor::default());
+    };
+    let value: String = row
+        .try_get("value")
+        .map_err(|e| format!("Failed to read git settings: {}", e))?;
+    serde_json::from_str(&value).map_err(|e| format!("Invalid git settings: {}", e))
+}
+
+pub(crate) fn signature(
+    repo: &Repository,
+    author: &CommitAuthor,
+) -> Result<Signature<'static>, String> {
+    let config = repo
+        .config()
+        .map_err(|e| format!("Failed to read git config: {}", e))?;
+    let setting = |value: &Option<String>, key: &str| {
+        value
+            .clone()
+            .filter(|v| !v.trim().is_empty())
+            .or_else(|| config.get_string(key).ok())
+    };
+    let (Some(name), Some(email)) = (
+        setting(&author.author_name, "user.name"),
+        setting(&author.author_email, "user.email"),
+    ) else {
+        return Err("No commit author configured; set a name and email in settings".to_string());
+    };
+    Signature::now(&name, &email).map_err(|e| format!("Invalid commit author: {}", e))
+}
+
+/// Commit the index. Concludes a merge in progress, with MERGE_HEAD as second
+/// parent.
+pub fn commit(
+    base_path: &Path,
+    message: &str,
+    author: &CommitAuthor,
+) -> Result<CommitResult, String> {
+    if message.trim().is_empty() {
+        return Err("Commit message is empty".to_string());
+    }
+    let mut repo = open_repo(base_path)?;
+    let merging = repo.state() == RepositoryState::Merge;
+    let mut merge_heads = Vec::new();
+    if merging {
+        repo.mergehead_foreach(|oid| {
+            merge_heads.push(*oid);
+            true
+        })
+        .map_err(|e| format!("Failed to read MERGE_HEAD: {}", e))?;
+    }
+
+    let mut index = repo
+        .index()
+        .map_err(|e| format!("Failed to get index: {}", e))?;
+    if index.has_conflicts() {
+        return Err("Resolve all conflicts before committing".to_string());
+    }
+    let tree_id = index
+        .write_tree()
+        .map_err(|e| format!("Failed to write tree: {}", e))?;
+    let tree = repo
+        .find_tree(tree_id)
+        .map_err(|e| format!("Failed to find tree: {}", e))?;
+
+    let mut parents = Vec::new();
+    if let Some(head) = head_commit(&repo)? {
+        parents.push(head);
+    }
+    if !merging && parents.first().map(|head| head.tree_id()) == Some(tree_id) {
+        return Err("Nothing to commit".to_string());
+    }
+    for oid in merge_heads {
+        parents.push(
+            repo.find_commit(oid)
+                .map_err(|e| format!("Failed to find merge head: {}", e))?,
+        );
+    }
+
+    let signature = signature(&repo, author)?;
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+    let id = repo
+        .commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parent_refs,
+        )
+        .map_err(|e| format!("Failed to commit: {}", e))?;
+    if merging {
+        repo.cleanup_state()
+            .map_err(|e| format!("Failed to clean up merge state: {}", e))?;
+    }
+
+    Ok(CommitResult {
+        id: id.to_string(),
+        summary: message.lines().next().unwrap_or_default().to_string(),
+    })
+}
+
+/// Stash local changes, like `git stash push`, and return the stash commit id
+pub fn stash_push(
+    base_path: &Path,
+    message: Option<&str>,
+    include_untracked: bool,
+    author: &CommitAuthor,
+) -> Result<String, String> {
+    let mut repo = open_repo(base_path)?;
+    let signature = signature(&repo, author)?;
+    let flags = if include_untracked {
+        StashFlags::INCLUDE_UNTRACKED
+    } else {
+        StashFlags::DEFAULT
+    };
+    repo.stash_save2(&signature, message, Some(flags))
+        .map(|oid| oid.to_string())
+        .map_err(|e| match e.code() {
+            git2::ErrorCode::NotFound => "No local changes to stash".to_string(),
+            _ => format!("Failed to stash changes: {}", e),
+        })
+}
+
+/// Apply and drop a stash entry, like `git stash pop`. The entry is kept when
+/// applying it conflicts with local changes.
+pub fn stash_pop(base_path: &Path, index: usize) -> Result<(), String> {
+    let mut repo = open_repo(base_path)?;
+    let mut options = StashApplyOptions::new();
+    options.reinstantiate_index();
+    repo.stash_pop(index, Some(&mut options))
+        .map_err(|e| match e.code() {
+            git2::ErrorCode::NotFound => format!("No stash entry at index {}", index),
+            git2::ErrorCode::Conflict => {
+                "Stash conflicts with local changes; commit or stash them first".to_string()
+            }
+            _ => format!("Failed to pop stash: {}", e),
+        })
+}
@@ -0,0 +1,348 @@
+use encoding_rs::Encoding;
+use git2::{Delta, Diff, DiffFindOptions, DiffOptions, Patch};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::files::encoding;
+use crate::git::{head_tree, open_repo, path_string};
+
+const DEFAULT_CONTEXT_LINES: u32 = 3;
+const DEFAULT_MAX_FILE_BYTES: usize = 512 * 1024;
+const DEFAULT_MAX_TOTAL_BYTES: usize = 8 * 1024 * 1024;
+
+/// What a diff compares
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffMode {
+    /// HEAD against the index, like `git diff --cached`
+    Staged,
+    /// The index against the working tree, like `git diff`
+    Unstaged,
+    /// HEAD against the working tree, like `git diff HEAD`
+    #[default]
+    Both,
+    /// A given commit against the working tree, like `git diff <commit>`
+    Commit,
+}
+
+/// How a diff is computed and how much of it is returned
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiffSettings {
+    pub context_lines: u32,
+    /// Ignore all whitespace, like `git diff -w`
+    pub ignore_whitespace: bool,
+    /// Ignore changes in the amount of whitespace, like `git diff -b`
+    pub ignore_whitespace_change: bool,
+    pub ignore_blank_lines: bool,
+    pub detect_renames: bool,
+    pub detect_copies: bool,
+    /// Files with a larger patch are listed without hunks
+    pub max_file_bytes: usize,
+    /// Files past this much patch in total are listed without hunks
+    pub max_total_bytes: usize,
+}
+
+impl Default for DiffSettings {
+    fn default() -> Self {
+        Self {
+            context_lines: DEFAULT_CONTEXT_LINES,
+            ignore_whitespace: false,
+            ignore_whitespace_change: false,
+            ignore_blank_lines: false,
+            detect_renames: true,
+            detect_copies: false,
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            max_total_bytes: DEFAULT_MAX_TOTAL_BYTES,
+        }
+    }
+}
+
+impl DiffSettings {
+    pub(crate) fn apply(&self, options: &mut DiffOptions) {
+        options
+            .context_lines(self.context_lines)
+            .ignore_whitespace(self.ignore_whitespace)
+            .ignore_whitespace_change(self.ignore_whitespace_change)
+            .ignore_blank_lines(self.ignore_blank_lines);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Copied,
+    TypeChange,
+    Untracked,
+    Conflicted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineKind {
+    Context,
+    Addition,
+    Deletion,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub kind: LineKind,
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    /// The "@@ -a,b +c,d @@ ..." line
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffFile {
+    pub path: String,
+    /// Previous path of a renamed or copied file
+    pub old_path: Option<String>,
+    pub change: FileChange,
+    pub binary: bool,
+    /// Size in bytes before the change, `None` when the file didn't exist
+    pub old_size: Option<u64>,
+    /// Size in bytes after the change, `None` when the file was deleted
+    pub new_size: Option<u64>,
+    pub additions: usize,
+    pub deletions: usize,
+    /// Hunks left out for exceeding the size caps; counts are still complete
+    pub truncated: bool,
+    pub hunks: Vec<DiffHunk>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiff {
+    pub files: Vec<DiffFile>,
+    pub additions: usize,
+    pub deletions: usize,
+    /// Some files were truncated
+    pub truncated: bool,
+    /// The whole diff as a unified patch, without the hunks of truncated files
+    pub patch: String,
+}
+
+fn file_change(status: Delta) -> FileChange {
+    match status {
+        Delta::Added => FileChange::Added,
+        Delta::Deleted => FileChange::Deleted,
+        Delta::Renamed => FileChange::Renamed,
+        Delta::Copied => FileChange::Copied,
+        Delta::Typechange => FileChange::TypeChange,
+        Delta::Untracked => FileChange::Untracked,
+        Delta::Conflicted => FileChange::Conflicted,
+        _ => FileChange::Modified,
+    }
+}
+
+/// Text of a diff line or patch in the encoding detected for its file, so
+/// content that isn't UTF-8 is decoded rather than dropped
+fn decode_text(bytes: &[u8], encoding: &'static Encoding) -> String {
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// Headers of a file patch, up to its first hunk
+fn patch_header(patch: &mut Patch) -> String {
+    let mut header = Vec::new();
+    // Stopping at the first hunk makes print return an error, which is expected
+    let _ = patch.print(&mut |_, hunk, line| {
+        if hunk.is_some() {
+            return false;
+        }
+        header.extend_from_slice(line.content());
+        true
+    });
+    String::from_utf8_lossy(&header).into_owned()
+}
+
+/// Break down file `index` of `diff`, appending its patch to `patch_text`.
+/// Hunks are left out when the file's patch exceeds `settings.max_file_bytes`
+/// or the remaining `budget`.
+fn diff_file(
+    diff: &Diff,
+    index: usize,
+    settings: &DiffSettings,
+    budget: &mut usize,
+    patch_text: &mut String,
+) -> Result<DiffFile, String> {
+    let mut patch =
+        Patch::from_diff(diff, index).map_err(|e| format!("Failed to create patch: {}", e))?;
+    let delta = match &patch {
+        Some(patch) => patch.delta(),
+        None => diff
+            .get_delta(index)
+            .ok_or_else(|| "Failed to get diff delta".to_string())?,
+    };
+
+    let change = file_change(delta.status());
+    let path = path_string(delta.new_file().path().or(delta.old_file().path()));
+    let old_path = matches!(change, FileChange::Renamed | FileChange::Copied)
+        .then(|| path_string(delta.old_file().path()));
+    let size = |file: git2::DiffFile| file.exists().then(|| file.size());
+
+    let mut file = DiffFile {
+        path,
+        old_path,
+        change,
+        binary: delta.flags().is_binary(),
+        old_size: size(delta.old_file()),
+        new_size: size(delta.new_file()),
+        additions: 0,
+        deletions: 0,
+        truncated: false,
+        hunks: Vec::new(),
+    };
+    let Some(patch) = patch.as_mut() else {
+        return Ok(file);
+    };
+
+    let (_, additions, deletions) = patch
+        .line_stats()
+        .map_err(|e| format!("Failed to count diff lines: {}", e))?;
+    file.additions = additions;
+    file.deletions = deletions;
+
+    // Sized up front so oversized patches are never printed in full
+    let patch_size = patch.size(true, true, true);
+    if patch_size > settings.max_file_bytes || patch_size > *budget {
+        file.truncated = true;
+        patch_text.push_str(&patch_header(patch));
+        patch_text.push_str(&format!(
+            "# Diff of {} truncated ({} bytes)\n",
+            file.path, patch_size
+        ));
+        return Ok(file);
+    }
+    let text = patch
+        .to_buf()
+        .map_err(|e| format!("Failed to print diff: {}", e))?;
+    // Files differ in encoding; detect each one's once for all its lines
+    let (text_encoding, _) = encoding::detect_encoding(&text);
+    *budget = budget.saturating_sub(text.len());
+    patch_text.push_str(&decode_text(&text, text_encoding));
+
+    for hunk_index in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch
+            .hunk(hunk_index)
+            .map_err(|e| format!("Failed to read hunk: {}", e))?;
+        let mut lines = Vec::with_capacity(line_count);
+        for line_index in 0..line_count {
+            let line = patch
+                .line_in_hunk(hunk_index, line_index)
+                .map_err(|e| format!("Failed to read diff line: {}", e))?;
+            let kind = match line.origin() {
+                '+' => LineKind::Addition,
+                '-' => LineKind::Deletion,
+                ' ' => LineKind::Context,
+                // End-of-file newline markers
+                _ => continue,
+            };
+            lines.push(DiffLine {
+                kind,
+                old_line: line.old_lineno(),
+                new_line: line.new_lineno(),
+                content: decode_text(line.content(), text_encoding),
+            });
+        }
+        file.hunks.push(DiffHunk {
+            header: decode_text(hunk.header(), text_encoding)
+                .trim_end()
+                .to_string(),
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            lines,
+        });
+    }
+    Ok(file)
+}
+
+/// Diff the repository at `base_path` in the given mode, limited to `paths`
+/// when not empty. `commit` is any revision and is required by `DiffMode::Commit`.
+/// Untracked files show up as added in every mode that includes the worktree.
+pub fn diff(
+    base_path: &Path,
+    mode: DiffMode,
+    commit: Option<&str>,
+    paths: &[String],
+    settings: &DiffSettings,
+) -> Result<GitDiff, String> {
+    let repo = open_repo(base_path)?;
+
+    let mut options = DiffOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    settings.apply(&mut options);
+    for path in paths {
+        options.pathspec(path);
+    }
+
+    let mut diff = match mode {
+        DiffMode::Staged => {
+            let head = head_tree(&repo)?;
+            repo.diff_tree_to_index(head.as_ref(), None, Some(&mut options))
+        }
+        DiffMode::Unstaged => repo.diff_index_to_workdir(None, Some(&mut options)),
+        DiffMode::Both => {
+            let head = head_tree(&repo)?;
+            repo.diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut options))
+        }
+        DiffMode::Commit => {
+            let commit = commit.ok_or_else(|| "A commit is required for this diff".to_string())?;
+            let tree = repo
+                .revparse_single(commit)
+                .and_then(|object| object.peel_to_tree())
+                .map_err(|e| format!("Failed to resolve {}: {}", commit, e))?;
+            repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut options))
+        }
+    }
+    .map_err(|e| format!("Failed to create diff: {}", e))?;
+
+    structured_diff(&mut diff, settings)
+}
+
+/// Detect renames and copies in `diff` as `settings` ask and break it down
+/// into files, hunks and lines
+pub(crate) fn structured_diff(diff: &mut Diff, settings: &DiffSettings) -> Result<GitDiff, String> {
+    let mut find = DiffFindOptions::new();
+    find.renames(settings.detect_renames)
+        .copies(settings.detect_copies)
+        .ignore_whitespace(settings.ignore_whitespace);
+    diff.find_similar(Some(&mut find))
+        .map_err(|e| format!("Failed to detect renames: {}", e))?;
+
+    let mut budget = settings.max_total_bytes;
+    let mut patch = String::new();
+    let files = (0..diff.deltas().len())
+        .map(|index| diff_file(diff, index, settings, &mut budget, &mut patch))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(GitDiff {
+        additions: files.iter().map(|f| f.additions).sum(),
+        deletions: files.iter().map(|f| f.deletions).sum(),
+        truncated: files.iter().any(|f| f.truncated),
+        patch,
+        files,
+    })
+}
@@ -0,0 +1,351 @@
+use git2::{BlameOptions, Commit, DiffOptions, Repository, Sort};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::files::{chunked, encoding};
+use crate::git::diff::{structured_diff, DiffSettings, GitDiff};
+use crate::git::{head_commit, open_repo, path_string};
+
+const DEFAULT_LOG_LIMIT: usize = 50;
+const MAX_LOG_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Person {
+    pub name: String,
+    pub email: String,
+    /// Milliseconds since the Unix epoch
+    pub time: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitInfo {
+    pub id: String,
+    pub short_id: String,
+    pub summary: String,
+    pub message: String,
+    pub author: Person,
+    pub committer: Person,
+    pub parents: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogOptions {
+    /// Where to start walking, HEAD by default
+    pub revision: Option<String>,
+    /// Only commits that changed this file or directory
+    pub path: Option<String>,
+    /// Only commits whose author name or email contains this, ignoring case
+    pub author: Option<String>,
+    /// Matching commits to skip, for pagination
+    #[serde(default)]
+    pub skip: usize,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogPage {
+    pub commits: Vec<CommitInfo>,
+    /// More commits match after this page; fetch them with `skip` advanced
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitDetail {
+    pub commit: CommitInfo,
+    /// Changes against the first parent, or everything for a root commit
+    pub diff: GitDiff,
+}
+
+/// Consecutive lines last changed by the same commit
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlameHunk {
+    /// `None` for lines not committed yet
+    pub commit: Option<String>,
+    /// 1-based first line in the blamed version of the file
+    pub start_line: usize,
+    pub lines: usize,
+    /// Path and first line in `commit`, which differ after renames and moves
+    pub orig_path: String,
+    pub orig_start_line: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlameResult {
+    pub path: String,
+    pub hunks: Vec<BlameHunk>,
+    /// Every commit referenced by `hunks`
+    pub commits: BTreeMap<String, CommitInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionFile {
+    pub path: String,
+    pub revision: String,
+    /// Decoded text, `None` for binary files
+    pub content: Option<String>,
+    pub binary: bool,
+    pub size: usize,
+}
+
+fn person(signature: &git2::Signature) -> Person {
+    Person {
+        name: signature.name().unwrap_or_default().to_string(),
+        email: signature.email().unwrap_or_default().to_string(),
+        time: signature.when().seconds() * 1000,
+    }
+}
+
+fn commit_info(commit: &Commit) -> CommitInfo {
+    let id = commit.id().to_string();
+    CommitInfo {
+        short_id: commit
+            .as_object()
+            .short_id()
+            .ok()
+            .and_then(|buf| buf.as_str().map(str::to_string))
+            .unwrap_or_else(|| id[..7].to_string()),
+        id,
+        summary: commit.summary().unwrap_or_default().to_string(),
+        message: commit.message().unwrap_or_default().to_string(),
+        author: person(&commit.author()),
+        committer: person(&commit.committer()),
+        parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+    }
+}
+
+fn find_commit<'r>(repo: &'r Repository, revision: &str) -> Result<Commit<'r>, String> {
+    repo.revparse_single(revision)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| format!("Failed to resolve {}: {}", revision, e))
+}
+
+/// Whether `commit` changed `path`. Like `git log -- <path>`, a merge counts
+/// only when it differs from every parent.
+fn touches_path(repo: &Repository, commit: &Commit, path: &str) -> Result<bool, String> {
+    let tree = commit
+        .tree()
+        .map_err(|e| format!("Failed to get tree: {}", e))?;
+    let changed_from = |parent_tree: Option<&git2::Tree>| {
+        let mut options = DiffOptions::new();
+        options.pathspec(path);
+        repo.diff_tree_to_tree(parent_tree, Some(&tree), Some(&mut options))
+            .map(|diff| diff.deltas().len() > 0)
+            .map_err(|e| format!("Failed to create diff: {}", e))
+    };
+
+    if commit.parent_count() == 0 {
+        return changed_from(None);
+    }
+    for parent in commit.parents() {
+        let parent_tree = parent
+            .tree()
+            .map_err(|e| format!("Failed to get tree: {}", e))?;
+        if !changed_from(Some(&parent_tree))? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Commits reachable from a revision, newest first. Empty for repositories
+/// without commits.
+pub fn log(base_path: &Path, options: &LogOptions) -> Result<LogPage, String> {
+    let repo = open_repo(base_path)?;
+    let start = match &options.revision {
+        Some(revision) => find_commit(&repo, revision)?.id(),
+        None => match head_commit(&repo)? {
+            Some(head) => head.id(),
+            None => {
+                return Ok(LogPage {
+                    commits: Vec::new(),
+                    has_more: false,
+                })
+            }
+        },
+    };
+
+    let mut revwalk = repo
+        .revwalk()
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+    revwalk
+        .set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+        .and_then(|_| revwalk.push(start))
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+
+    let limit = options
+        .limit
+        .unwrap_or(DEFAULT_LOG_LIMIT)
+        .clamp(1, MAX_LOG_LIMIT);
+    let author = options.author.as_ref().map(|a| a.to_lowercase());
+    let mut skipped = 0;
+    let mut commits = Vec::new();
+
+    for oid in revwalk {
+        let oid = oid.map_err(|e| format!("Failed to walk history: {}", e))?;
+        let commit = repo
+            .find_commit(oid)
+            .map_err(|e| format!("Failed to find commit: {}", e))?;
+
+        if let Some(author) = &author {
+            let signature = commit.author();
+            let name = signature.name().unwrap_or_default().to_lowercase();
+            let email = signature.email().unwrap_or_default().to_lowercase();
+            if !name.contains(author) && !email.contains(author) {
+                continue;
+            }
+        }
+        if let Some(path) = &options.path {
+            if !touches_path(&repo, &commit, path)? {
+                continue;
+            }
+        }
+
+        if skipped < options.skip {
+            skipped += 1;
+            continue;
+        }
+        if commits.len() == limit {
+            return Ok(LogPage {
+                commits,
+                has_more: true,
+            });
+        }
+        commits.push(commit_info(&commit));
+    }
+
+    Ok(LogPage {
+        commits,
+        has_more: false,
+    })
+}
+
+pub fn show_commit(
+    base_path: &Path,
+    revision: &str,
+    settings: &DiffSettings,
+) -> Result<CommitDetail, String> {
+    let repo = open_repo(base_path)?;
+    let commit = find_commit(&repo, revision)?;
+    let tree = commit
+        .tree()
+        .map_err(|e| format!("Failed to get tree: {}", e))?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(
+            parent
+                .tree()
+                .map_err(|e| format!("Failed to get tree: {}", e))?,
+        ),
+        Err(_) => None,
+    };
+
+    let mut options = DiffOptions::new();
+    settings.apply(&mut options);
+    let mut diff = repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut options))
+        .map_err(|e| format!("Failed to create diff: {}", e))?;
+    Ok(CommitDetail {
+        commit: commit_info(&commit),
+        diff: structured_diff(&mut diff, settings)?,
+    })
+}
+
+/// Blame `path` at `revision`, or as it is in the working tree when no
+/// revision is given, with uncommitted lines attributed to no commit
+pub fn blame(base_path: &Path, path: &str, revision: Option<&str>) -> Result<BlameResult, String> {
+    let repo = open_repo(base_path)?;
+    let mut options = BlameOptions::new();
+    if let Some(revision) = revision {
+        options.newest_commit(find_commit(&repo, revision)?.id());
+    }
+
+    let committed = repo
+        .blame_file(Path::new(path), Some(&mut options))
+        .map_err(|e| format!("Failed to blame {}: {}", path, e))?;
+    let working;
+    let blame = match revision {
+        Some(_) => &committed,
+        None => {
+            let workdir = repo
+                .workdir()
+                .ok_or_else(|| "Repository has no working directory".to_string())?;
+            let content =
+                fs::read(workdir.join(path)).map_err(|e| format!("Failed to read file: {}", e))?;
+            working = committed
+                .blame_buffer(&content)
+                .map_err(|e| format!("Failed to blame {}: {}", path, e))?;
+            &working
+        }
+    };
+
+    let mut hunks = Vec::new();
+    let mut commits = BTreeMap::new();
+    for hunk in blame.iter() {
+        let id = hunk.final_commit_id();
+        let commit = (!id.is_zero()).then(|| id.to_string());
+        if let Some(commit_id) = &commit {
+            if !commits.contains_key(commit_id) {
+                let found = repo
+                    .find_commit(id)
+                    .map_err(|e| format!("Failed to find commit: {}", e))?;
+                commits.insert(commit_id.clone(), commit_info(&found));
+            }
+        }
+        hunks.push(BlameHunk {
+            commit,
+            start_line: hunk.final_start_line(),
+            lines: hunk.lines_in_hunk(),
+            orig_path: path_string(hunk.path()),
+            orig_start_line: hunk.orig_start_line(),
+        });
+    }
+
+    Ok(BlameResult {
+        path: path.to_string(),
+        hunks,
+        commits,
+    })
+}
+
+/// Content of `path` at `revision`. An empty revision reads the staged version
+/// from the index.
+pub fn file_at_revision(
+    base_path: &Path,
+    path: &str,
+    revision: &str,
+) -> Result<RevisionFile, String> {
+    let repo = open_repo(base_path)?;
+    let blob = if revision.is_empty() {
+        // libgit2 does not parse the `:<path>` shorthand for the index
+        let index = repo
+            .index()
+            .map_err(|e| format!("Failed to get index: {}", e))?;
+        let entry = index
+            .get_path(Path::new(path), 0)
+            .ok_or_else(|| format!("{} is not in the index", path))?;
+        repo.find_blob(entry.id)
+    } else {
+        repo.revparse_single(&format!("{}:{}", revision, path))
+            .and_then(|object| object.peel_to_blob())
+    }
+    .map_err(|e| format!("Failed to read {} at {}: {}", path, revision, e))?;
+
+    let bytes = blob.content();
+    let binary = chunked::looks_binary(&bytes[..bytes.len().min(8192)]);
+    Ok(RevisionFile {
+        path: path.to_string(),
+        revision: revision.to_string(),
+        content: (!binary).then(|| encoding::decode(bytes).0),
+        binary,
+        size: bytes.len(),
+    })
+}
@@ -0,0 +1,161 @@
+use git2::build::CheckoutBuilder;
+use git2::{AnnotatedCommit, Commit, Repository, RepositoryState, Signature};
+use serde::Serialize;
+
+use crate::git::head_commit;
+use crate::git::status::{conflicts, ConflictKind};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    pub path: String,
+    pub kind: ConflictKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeResult {
+    /// HEAD already contained the merged commit; nothing changed
+    pub up_to_date: bool,
+    pub fast_forward: bool,
+    /// New HEAD commit, `None` while conflicts are left to resolve
+    pub commit: Option<String>,
+    /// Conflicted paths left in the index and working tree. Resolve them,
+    /// stage the result and commit to conclude the merge.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Check out `commit` and move HEAD (the current branch, or HEAD itself when
+/// detached) to it. Fails without changes when local modifications would be
+/// overwritten.
+pub(crate) fn advance_head(repo: &Repository, commit: &Commit, reflog: &str) -> Result<(), String> {
+    let mut checkout = CheckoutBuilder::new();
+    checkout.safe();
+    repo.checkout_tree(commit.as_object(), Some(&mut checkout))
+        .map_err(|e| match e.code() {
+            git2::ErrorCode::Conflict => {
+                "Local changes would be overwritten; commit or stash them first".to_string()
+            }
+            _ => format!("Failed to check out {}: {}", commit.id(), e),
+        })?;
+
+    let result = match repo.head() {
+        Ok(mut head) if head.is_branch() => head.set_target(commit.id(), reflog).map(|_| ()),
+        Ok(_) => repo.set_head_detached(commit.id()),
+        // Unborn branch: create it where HEAD already points
+        Err(_) => {
+            let target = repo
+                .find_reference("HEAD")
+                .ok()
+                .and_then(|head| head.symbolic_target().map(str::to_string))
+                .unwrap_or_else(|| "refs/heads/main".to_string());
+            repo.reference(&target, commit.id(), true, reflog)
+                .map(|_| ())
+        }
+    };
+    result.map_err(|e| format!("Failed to update HEAD: {}", e))
+}
+
+/// Merge `their` into HEAD like `git merge`: fast-forward when possible,
+/// otherwise a merge commit. Conflicts are left in the working tree with the
+/// repository in merge state.
+pub(crate) fn merge_into_head(
+    repo: &Repository,
+    their: &AnnotatedCommit,
+    message: &str,
+    signature: &Signature,
+) -> Result<MergeResult, String> {
+    if repo.state() != RepositoryState::Clean {
+        return Err("Another operation is in progress; conclude or abort it first".to_string());
+    }
+    let (analysis, preference) = repo
+        .merge_analysis(&[their])
+        .map_err(|e| format!("Failed to analyze merge: {}", e))?;
+    let their_commit = repo
+        .find_commit(their.id())
+        .map_err(|e| format!("Failed to find commit: {}", e))?;
+
+    if analysis.is_up_to_date() {
+        return Ok(MergeResult {
+            up_to_date: true,
+            fast_forward: false,
+            commit: head_commit(repo)?.map(|head| head.id().to_string()),
+            conflicts: Vec::new(),
+        });
+    }
+    if analysis.is_unborn() || (analysis.is_fast_forward() && !preference.is_no_fast_forward()) {
+        advance_head(repo, &their_commit, &format!("merge: {}", message))?;
+        return Ok(MergeResult {
+            up_to_date: false,
+            fast_forward: true,
+            commit: Some(their_commit.id().to_string()),
+            conflicts: Vec::new(),
+        });
+    }
+
+    // The merge commit is written from the index, which must not carry
+    // staged changes of its own
+    let head = head_commit(repo)?.ok_or_else(|| "HEAD has no commit".to_string())?;
+    let head_tree = head
+        .tree()
+        .map_err(|e| format!("Failed to read HEAD tree: {}", e))?;
+    let staged = repo
+        .diff_tree_to_index(Some(&head_tree), None, None)
+        .map_err(|e| format!("Failed to diff index: {}", e))?;
+    if staged.deltas().len() > 0 {
+        return Err(
+            "Staged changes would be committed by the merge; commit or unstage them first"
+                .to_string(),
+        );
+    }
+
+    repo.merge(&[their], None, None)
+        .map_err(|e| match e.code() {
+            git2::ErrorCode::Conflict => {
+                "Local changes would be overwritten by the merge; commit or stash them first"
+                    .to_string()
+            }
+            _ => format!("Failed to merge: {}", e),
+        })?;
+
+    let mut index = repo
+        .index()
+        .map_err(|e| format!("Failed to get index: {}", e))?;
+    if index.has_conflicts() {
+        let mut conflicts: Vec<MergeConflict> = conflicts(repo)?
+            .into_iter()
+            .map(|(path, kind)| MergeConflict { path, kind })
+            .collect();
+        conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+        return Ok(MergeResult {
+            up_to_date: false,
+            fast_forward: false,
+            commit: None,
+            conflicts,
+        });
+    }
+
+    let tree = index
+        .write_tree()
+        .and_then(|id| repo.find_tree(id))
+        .map_err(|e| format!("Failed to write tree: {}", e))?;
+    let id = repo
+        .commit(
+            Some("HEAD"),
+            signature,
+            signature,
+            message,
+            &tree,
+            &[&head, &their_commit],
+        )
+        .map_err(|e| format!("Failed to commit merge: {}", e))?;
+    repo.cleanup_state()
+        .map_err(|e| format!("Failed to clean up merge state: {}", e))?;
+
+    Ok(MergeResult {
+        up_to_date: false,
+        fast_forward: false,
+        commit: Some(id.to_string()),
+        conflicts: Vec::new(),
+    })
+}
@@ -0,0 +1,59 @@
+pub mod branch;
+pub mod checkpoint;
+pub mod commit;
+pub mod diff;
+pub mod history;
+pub mod merge;
+pub mod stage;
+pub mod status;
+pub mod worktree;
+
+use git2::{Commit, Repository, Tree};
+use std::path::Path;
+
+pub(crate) fn open_repo(path: &Path) -> Result<Repository, String> {
+    Repository::open(path).map_err(|e| format!("Failed to open repository: {}", e))
+}
+
+/// Commit HEAD points to, or `None` while HEAD is unborn (a fresh repository
+/// without commits)
+pub(crate) fn head_commit(repo: &Repository) -> Result<Option<Commit<'_>>, String> {
+    match repo.head() {
+        Ok(head) => head
+            .peel_to_commit()
+            .map(Some)
+            .map_err(|e| format!("Failed to get HEAD commit: {}", e)),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => Ok(None),
+        Err(e) => Err(format!("Failed to get HEAD: {}", e)),
+    }
+}
+
+/// Tree of the commit HEAD points to, or `None` while HEAD is unborn
+pub(crate) fn head_tree(repo: &Repository) -> Result<Option<Tree<'_>>, String> {
+    match head_commit(repo)? {
+        Some(commit) => commit
+            .tree()
+            .map(Some)
+            .map_err(|e| format!("Failed to get HEAD tree: {}", e)),
+        None => Ok(None),
+    }
+}
+
+/// Path of a diff or status entry relative to the working directory
+pub(crate) fn path_string(path: Option<&Path>) -> String {
+    path.map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Chat and task ids end up in ref names and directory names
+pub(crate) fn sanitize_name(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,194 @@
+use git2::build::CheckoutBuilder;
+use git2::{ApplyLocation, ApplyOptions, Diff, DiffHunk, DiffOptions, IndexAddOption, Repository};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::path::Path;
+
+use crate::git::{head_commit, head_tree, open_repo, path_string};
+
+/// A hunk as reported by `git_diff`: staged hunks for unstaging, unstaged
+/// hunks for staging and discarding
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkSelector {
+    pub path: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    /// Context lines of the diff the hunk was taken from, 3 by default
+    #[serde(default)]
+    pub context_lines: Option<u32>,
+}
+
+impl HunkSelector {
+    /// Whether `hunk` is this hunk, or this hunk with old and new swapped
+    fn matches(&self, hunk: &DiffHunk, reversed: bool) -> bool {
+        let (old_start, old_lines, new_start, new_lines) = if reversed {
+            (
+                self.new_start,
+                self.new_lines,
+                self.old_start,
+                self.old_lines,
+            )
+        } else {
+            (
+                self.old_start,
+                self.old_lines,
+                self.new_start,
+                self.new_lines,
+            )
+        };
+        hunk.old_start() == old_start
+            && hunk.old_lines() == old_lines
+            && hunk.new_start() == new_start
+            && hunk.new_lines() == new_lines
+    }
+}
+
+fn diff_options(hunks: &[HunkSelector], reverse: bool) -> DiffOptions {
+    let mut options = DiffOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true)
+        .disable_pathspec_match(true)
+        .reverse(reverse);
+    // Hunk boundaries depend on the context, so diff with the one they came from
+    if let Some(lines) = hunks.iter().find_map(|hunk| hunk.context_lines) {
+        options.context_lines(lines);
+    }
+    for hunk in hunks {
+        options.pathspec(&hunk.path);
+    }
+    options
+}
+
+/// Apply only the selected hunks of `diff` to `location`. Fails without
+/// changing anything if a selected hunk is not part of the diff anymore.
+fn apply_hunks(
+    repo: &Repository,
+    diff: &Diff,
+    hunks: &[HunkSelector],
+    location: ApplyLocation,
+    reversed: bool,
+) -> Result<(), String> {
+    let current_path = RefCell::new(String::new());
+    let matched = RefCell::new(vec![false; hunks.len()]);
+
+    // First pass only checks that every hunk is still there
+    for check in [true, false] {
+        let mut options = ApplyOptions::new();
+        options.check(check);
+        options.delta_callback(|delta| {
+            let path = delta
+                .map(|d| path_string(d.new_file().path().or(d.old_file().path())))
+                .unwrap_or_default();
+            let wanted = hunks.iter().any(|h| h.path == path);
+            *current_path.borrow_mut() = path;
+            wanted
+        });
+        options.hunk_callback(|hunk| {
+            let Some(hunk) = hunk else {
+                return false;
+            };
+            let path = current_path.borrow();
+            match hunks
+                .iter()
+                .position(|h| h.path == *path && h.matches(&hunk, reversed))
+            {
+                Some(index) => {
+                    matched.borrow_mut()[index] = true;
+                    true
+                }
+                None => false,
+            }
+        });
+        repo.apply(diff, location, Some(&mut options))
+            .map_err(|e| format!("Failed to apply hunks: {}", e))?;
+
+        if check {
+            if let Some(index) = matched.borrow().iter().position(|m| !m) {
+                let hunk = &hunks[index];
+                return Err(format!(
+                    "Hunk @@ -{},{} +{},{} @@ in {} no longer matches; refresh the diff",
+                    hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines, hunk.path
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Stage files, including deletions, like `git add -A -- <paths>`
+pub fn stage_paths(base_path: &Path, paths: &[String]) -> Result<(), String> {
+    let repo = open_repo(base_path)?;
+    let mut index = repo
+        .index()
+        .map_err(|e| format!("Failed to get index: {}", e))?;
+    index
+        .add_all(paths, IndexAddOption::DEFAULT, None)
+        .and_then(|_| index.update_all(paths, None))
+        .and_then(|_| index.write())
+        .map_err(|e| format!("Failed to stage files: {}", e))
+}
+
+/// Unstage files, like `git reset -- <paths>`
+pub fn unstage_paths(base_path: &Path, paths: &[String]) -> Result<(), String> {
+    let repo = open_repo(base_path)?;
+    let head = head_commit(&repo)?;
+    match head {
+        Some(head) => repo
+            .reset_default(Some(head.as_object()), paths)
+            .map_err(|e| format!("Failed to unstage files: {}", e)),
+        None => {
+            // Nothing to reset to yet: unstaging removes the files from the index
+            let mut index = repo
+                .index()
+                .map_err(|e| format!("Failed to get index: {}", e))?;
+            index
+                .remove_all(paths, None)
+                .and_then(|_| index.write())
+                .map_err(|e| format!("Failed to unstage files: {}", e))
+        }
+    }
+}
+
+pub fn stage_hunks(base_path: &Path, hunks: &[HunkSelector]) -> Result<(), String> {
+    let repo = open_repo(base_path)?;
+    let diff = repo
+        .diff_index_to_workdir(None, Some(&mut diff_options(hunks, false)))
+        .map_err(|e| format!("Failed to create diff: {}", e))?;
+    apply_hunks(&repo, &diff, hunks, ApplyLocation::Index, false)
+}
+
+pub fn unstage_hunks(base_path: &Path, hunks: &[HunkSelector]) -> Result<(), String> {
+    let repo = open_repo(base_path)?;
+    let head = head_tree(&repo)?;
+    let diff = repo
+        .diff_tree_to_index(head.as_ref(), None, Some(&mut diff_options(hunks, true)))
+        .map_err(|e| format!("Failed to create diff: {}", e))?;
+    apply_hunks(&repo, &diff, hunks, ApplyLocation::Index, true)
+}
+
+/// Revert unstaged hunks in the working tree to their staged content
+pub fn discard_hunks(base_path: &Path, hunks: &[HunkSelector]) -> Result<(), String> {
+    let repo = open_repo(base_path)?;
+    let diff = repo
+        .diff_index_to_workdir(None, Some(&mut diff_options(hunks, true)))
+        .map_err(|e| format!("Failed to create diff: {}", e))?;
+    apply_hunks(&repo, &diff, hunks, ApplyLocation::WorkDir, true)
+}
+
+/// Revert files in the working tree to their staged content, deleting them
+/// when untracked
+pub fn discard_paths(base_path: &Path, paths: &[String]) -> Result<(), String> {
+    let repo = open_repo(base_path)?;
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force().remove_untracked(true);
+    for path in paths {
+        checkout.path(path);
+    }
+    repo.checkout_index(None, Some(&mut checkout))
+        .map_err(|e| format!("Failed to discard changes: {}", e))
+}
@@ -0,0 +1,259 @@
+use git2::{Repository, RepositoryState, Status, StatusOptions};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::git::{open_repo, path_string};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileState {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    TypeChange,
+    Untracked,
+    Ignored,
+}
+
+/// Which sides of a merge touched a conflicted path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    BothModified,
+    BothAdded,
+    BothDeleted,
+    AddedByUs,
+    AddedByThem,
+    DeletedByUs,
+    DeletedByThem,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusEntry {
+    pub path: String,
+    /// Previous path when the file was renamed in the index or the worktree
+    pub old_path: Option<String>,
+    /// Change staged in the index, relative to HEAD
+    pub index: Option<FileState>,
+    /// Change in the working tree, relative to the index
+    pub worktree: Option<FileState>,
+    pub conflict: Option<ConflictKind>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitStatus {
+    /// Current branch, also set while HEAD is unborn; `None` when detached
+    pub branch: Option<String>,
+    /// Commit HEAD points to, `None` while HEAD is unborn
+    pub head: Option<String>,
+    pub detached: bool,
+    pub unborn: bool,
+    /// Upstream of the current branch, e.g. "origin/main"
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+    /// Operation in progress: "clean", "merge", "rebase", "cherry_pick", ...
+    pub state: &'static str,
+    pub files: Vec<StatusEntry>,
+}
+
+fn index_state(status: Status) -> Option<FileState> {
+    if status.is_index_new() {
+        Some(FileState::Added)
+    } else if status.is_index_renamed() {
+        Some(FileState::Renamed)
+    } else if status.is_index_deleted() {
+        Some(FileState::Deleted)
+    } else if status.is_index_typechange() {
+        Some(FileState::TypeChange)
+    } else if status.is_index_modified() {
+        Some(FileState::Modified)
+    } else {
+        None
+    }
+}
+
+fn worktree_state(status: Status) -> Option<FileState> {
+    if status.is_wt_new() {
+        Some(FileState::Untracked)
+    } else if status.is_wt_renamed() {
+        Some(FileState::Renamed)
+    } else if status.is_wt_deleted() {
+        Some(FileState::Deleted)
+    } else if status.is_wt_typechange() {
+        Some(FileState::TypeChange)
+    } else if status.is_wt_modified() {
+        Some(FileState::Modified)
+    } else if status.is_ignored() {
+        Some(FileState::Ignored)
+    } else {
+        None
+    }
+}
+
+fn state_name(state: RepositoryState) -> &'static str {
+    match state {
+        RepositoryState::Clean => "clean",
+        RepositoryState::Merge => "merge",
+        RepositoryState::Revert | RepositoryState::RevertSequence => "revert",
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => "cherry_pick",
+        RepositoryState::Bisect => "bisect",
+        RepositoryState::Rebase
+        | RepositoryState::RebaseInteractive
+        | RepositoryState::RebaseMerge => "rebase",
+        RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => "apply_mailbox",
+    }
+}
+
+/// Conflicted paths in the index and which sides touched them
+pub(crate) fn conflicts(repo: &Repository) -> Result<HashMap<String, ConflictKind>, String> {
+    let index = repo
+        .index()
+        .map_err(|e| format!("Failed to get index: {}", e))?;
+    let mut conflicts = HashMap::new();
+    if !index.has_conflicts() {
+        return Ok(conflicts);
+    }
+
+    for conflict in index
+        .conflicts()
+        .map_err(|e| format!("Failed to read conflicts: {}", e))?
+    {
+        let conflict = conflict.map_err(|e| format!("Failed to read conflicts: {}", e))?;
+        let kind = match (
+            conflict.ancestor.is_some(),
+            conflict.our.is_some(),
+            conflict.their.is_some(),
+        ) {
+            (true, true, true) => ConflictKind::BothModified,
+            (false, true, true) => ConflictKind::BothAdded,
+            (true, false, false) => ConflictKind::BothDeleted,
+            (false, true, false) => ConflictKind::AddedByUs,
+            (false, false, true) => ConflictKind::AddedByThem,
+            (true, false, true) => ConflictKind::DeletedByUs,
+            (true, true, false) => ConflictKind::DeletedByThem,
+            (false, false, false) => continue,
+        };
+        let entry = conflict
+            .our
+            .as_ref()
+            .or(conflict.their.as_ref())
+            .or(conflict.ancestor.as_ref());
+        if let Some(entry) = entry {
+            conflicts.insert(String::from_utf8_lossy(&entry.path).to_string(), kind);
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Upstream of the branch HEAD points to and how far the branch is ahead of
+/// and behind it
+fn upstream(repo: &Repository) -> Option<(String, usize, usize)> {
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    let upstream_ref = repo.branch_upstream_name(head.name()?).ok()?;
+    let upstream_ref = upstream_ref.as_str()?;
+    let upstream_oid = repo.refname_to_id(upstream_ref).ok()?;
+    let (ahead, behind) = repo.graph_ahead_behind(head.target()?, upstream_oid).ok()?;
+    let name = upstream_ref
+        .strip_prefix("refs/remotes/")
+        .unwrap_or(upstream_ref)
+        .to_string();
+    Some((name, ahead, behind))
+}
+
+pub fn status(base_path: &Path, include_ignored: bool) -> Result<GitStatus, String> {
+    let repo = open_repo(base_path)?;
+
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(include_ignored)
+        .renames_head_to_index(true)
+        .renames_index_to_workdir(true);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|e| format!("Failed to get status: {}", e))?;
+    let conflicts = conflicts(&repo)?;
+
+    let mut files = Vec::new();
+    for entry in statuses.iter() {
+        let status = entry.status();
+        let path = entry.path().unwrap_or_default().to_string();
+
+        // The old path is on the side that detected the rename
+        let rename = entry
+            .head_to_index()
+            .filter(|_| status.is_index_renamed())
+            .or_else(|| entry.index_to_workdir().filter(|_| status.is_wt_renamed()));
+        let (path, old_path) = match rename {
+            Some(delta) => (
+                path_string(delta.new_file().path()),
+                Some(path_string(delta.old_file().path())),
+            ),
+            None => (path, None),
+        };
+
+        if status.is_conflicted() {
+            files.push(StatusEntry {
+                conflict: conflicts.get(&path).copied(),
+                path,
+                old_path: None,
+                index: None,
+                worktree: None,
+            });
+            continue;
+        }
+        files.push(StatusEntry {
+            path,
+            old_path,
+            index: index_state(status),
+            worktree: worktree_state(status),
+            conflict: None,
+        });
+    }
+
+    let (head, unborn) = match repo.head() {
+        Ok(head) => (head.target().map(|oid| oid.to_string()), false),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => (None, true),
+        Err(e) => return Err(format!("Failed to read HEAD: {}", e)),
+    };
+    let detached = repo.head_detached().unwrap_or(false);
+    let branch = if detached {
+        None
+    } else {
+        // Read HEAD itself so the branch name is known before the first commit
+        repo.find_reference("HEAD")
+            .ok()
+            .and_then(|head| head.symbolic_target().map(str::to_string))
+            .map(|target| {
+                target
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(&target)
+                    .to_string()
+            })
+    };
+    let (upstream, ahead, behind) = match upstream(&repo) {
+        Some((name, ahead, behind)) => (Some(name), ahead, behind),
+        None => (None, 0, 0),
+    };
+
+    Ok(GitStatus {
+        branch,
+        head,
+        detached,
+        unborn,
+        upstream,
+        ahead,
+        behind,
+        state: state_name(repo.state()),
+        files,
+    })
+}
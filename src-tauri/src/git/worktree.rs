@@ -0,0 +1,371 @@
+use git2::{
+    BranchType, IndexAddOption, ObjectType, Oid, Repository, ResetType, Signature, Sort,
+    StatusOptions, WorktreeAddOptions, WorktreePruneOptions,
+};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+use crate::git::commit::{self, CommitAuthor};
+use crate::git::merge::{self, MergeResult};
+use crate::git::{head_commit, sanitize_name};
+
+const WORKTREES_DIR_NAME: &str = "worktrees";
+const TASK_BRANCH_PREFIX: &str = "raven/task/";
+const DEFAULT_COMMIT_MESSAGE: &str = "Agent task changes";
+
+/// Git worktree an agent task runs in, checked out on its own branch so
+/// parallel tasks on one repository don't overwrite each other's edits
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskWorktree {
+    pub task_id: String,
+    /// Root of the worktree
+    pub path: String,
+    /// Directory to run the agent in: the worktree counterpart of the
+    /// directory the task was created for
+    pub cwd: String,
+    pub branch: String,
+    /// Working directory of the repository the worktree belongs to
+    pub repo_path: String,
+    /// Commits on the task branch that the repository's HEAD doesn't have
+    pub ahead: usize,
+    /// Commits on the repository's HEAD since the task branched off
+    pub behind: usize,
+    /// Uncommitted changes in the worktree
+    pub dirty: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CherryPickResult {
+    /// New commits on the repository's HEAD, oldest first
+    pub commits: Vec<String>,
+}
+
+pub fn worktrees_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(WORKTREES_DIR_NAME))
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))
+}
+
+fn branch_name(task_id: &str) -> String {
+    format!("{}{}", TASK_BRANCH_PREFIX, sanitize_name(task_id))
+}
+
+/// Open the worktree of `task_id` and the repository it belongs to
+fn open_task(root: &Path, task_id: &str) -> Result<(Repository, Repository), String> {
+    let path = root.join(sanitize_name(task_id));
+    let worktree =
+        Repository::open(&path).map_err(|_| format!("Task {} has no worktree", task_id))?;
+    if !worktree.is_worktree() {
+        return Err(format!("Task {} has no worktree", task_id));
+    }
+    // The git dir of a linked worktree is `<common git dir>/worktrees/<name>`
+    let common_dir = worktree
+        .path()
+        .parent()
+        .and_then(Path::parent)
+        .ok_or_else(|| format!("Task {} has no worktree", task_id))?;
+    let repo =
+        Repository::open(common_dir).map_err(|e| format!("Failed to open repository: {}", e))?;
+    Ok((repo, worktree))
+}
+
+fn task_info(
+    repo: &Repository,
+    worktree: &Repository,
+    task_id: &str,
+    relative: &Path,
+) -> Result<TaskWorktree, String> {
+    let workdir = |repo: &Repository| {
+        repo.workdir()
+            .map(|dir| dir.to_string_lossy().trim_end_matches('/').to_string())
+            .unwrap_or_default()
+    };
+    let tip = head_commit(worktree)?.map(|commit| commit.id());
+    let head = head_commit(repo)?.map(|commit| commit.id());
+    let (ahead, behind) = match (tip, head) {
+        (Some(tip), Some(head)) => repo.graph_ahead_behind(tip, head).unwrap_or((0, 0)),
+        _ => (0, 0),
+    };
+
+    let mut options = StatusOptions::new();
+    options.include_untracked(true);
+    let dirty = worktree
+        .statuses(Some(&mut options))
+        .map(|statuses| !statuses.is_empty())
+        .map_err(|e| format!("Failed to get status: {}", e))?;
+
+    let path = workdir(worktree);
+    Ok(TaskWorktree {
+        task_id: task_id.to_string(),
+        cwd: Path::new(&path)
+            .join(relative)
+            .to_string_lossy()
+            .to_string(),
+        path,
+        branch: branch_name(task_id),
+        repo_path: workdir(repo),
+        ahead,
+        behind,
+        dirty,
+    })
+}
+
+/// Create a worktree for `task_id` on a new branch from the HEAD of the
+/// repository containing `directory`. Returns the existing one when the task
+/// already has a worktree.
+pub fn create(root: &Path, directory: &Path, task_id: &str) -> Result<TaskWorktree, String> {
+    let repo =
+        Repository::discover(directory).map_err(|e| format!("Failed to open repository: {}", e))?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| "Repository has no working directory".to_string())?;
+    let relative = directory
+        .canonicalize()
+        .ok()
+        .zip(workdir.canonicalize().ok())
+        .and_then(|(dir, workdir)| dir.strip_prefix(workdir).ok().map(Path::to_path_buf))
+        .unwrap_or_default();
+
+    if let Ok((repo, worktree)) = open_task(root, task_id) {
+        return task_info(&repo, &worktree, task_id, &relative);
+    }
+
+    let name = sanitize_name(task_id);
+    let branch = branch_name(task_id);
+    let head = head_commit(&repo)?
+        .ok_or_else(|| "Commit at least once before running a task in a worktree".to_string())?;
+    let branch_ref = match repo.find_branch(&branch, BranchType::Local) {
+        Ok(existing) => existing,
+        Err(_) => repo
+            .branch(&branch, &head, false)
+            .map_err(|e| format!("Failed to create branch {}: {}", branch, e))?,
+    };
+
+    // A registration left behind by a worktree deleted by hand blocks the name
+    if let Ok(stale) = repo.find_worktree(&name) {
+        if stale.validate().is_err() {
+            stale
+                .prune(None)
+                .map_err(|e| format!("Failed to prune stale worktree: {}", e))?;
+        }
+    }
+
+    fs::create_dir_all(root).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let mut options = WorktreeAddOptions::new();
+    options.reference(Some(branch_ref.get()));
+    repo.worktree(&name, &root.join(&name), Some(&options))
+        .map_err(|e| format!("Failed to create worktree: {}", e))?;
+
+    let (repo, worktree) = open_task(root, task_id)?;
+    task_info(&repo, &worktree, task_id, &relative)
+}
+
+pub fn get(root: &Path, task_id: &str) -> Result<Option<TaskWorktree>, String> {
+    match open_task(root, task_id) {
+        Ok((repo, worktree)) => task_info(&repo, &worktree, task_id, Path::new("")).map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Commit everything the agent left uncommitted in the worktree to the task
+/// branch, ignored files excepted
+fn commit_pending(
+    worktree: &Repository,
+    signature: &Signature,
+    message: &str,
+) -> Result<Option<Oid>, String> {
+    let mut index = worktree
+        .index()
+        .map_err(|e| format!("Failed to get index: {}", e))?;
+    let tree_id = index
+        .add_all(["*"], IndexAddOption::DEFAULT, None)
+        .and_then(|_| index.update_all(["*"], None))
+        .and_then(|_| index.write())
+        .and_then(|_| index.write_tree())
+        .map_err(|e| format!("Failed to stage task changes: {}", e))?;
+
+    let head = head_commit(worktree)?.ok_or_else(|| "Task branch has no commit".to_string())?;
+    if head.tree_id() == tree_id {
+        return Ok(None);
+    }
+    let tree = worktree
+        .find_tree(tree_id)
+        .map_err(|e| format!("Failed to find tree: {}", e))?;
+    worktree
+        .commit(Some("HEAD"), signature, signature, message, &tree, &[&head])
+        .map(Some)
+        .map_err(|e| format!("Failed to commit task changes: {}", e))
+}
+
+/// Commit pending changes of the task and merge its branch into the
+/// repository's current branch
+pub fn merge(
+    root: &Path,
+    task_id: &str,
+    message: Option<&str>,
+    author: &CommitAuthor,
+) -> Result<MergeResult, String> {
+    let (repo, worktree) = open_task(root, task_id)?;
+    let signature = commit::signature(&repo, author)?;
+    commit_pending(
+        &worktree,
+        &signature,
+        message.unwrap_or(DEFAULT_COMMIT_MESSAGE),
+    )?;
+
+    let branch = branch_name(task_id);
+    let tip = repo
+        .find_branch(&branch, BranchType::Local)
+        .map_err(|e| format!("Failed to find branch {}: {}", branch, e))?;
+    let their = repo
+        .reference_to_annotated_commit(tip.get())
+        .map_err(|e| format!("Failed to read branch {}: {}", branch, e))?;
+    merge::merge_into_head(
+        &repo,
+        &their,
+        &format!("Merge branch '{}'", branch),
+        &signature,
+    )
+}
+
+/// Commit pending changes of the task and replay the task's commits on top of
+/// the repository's HEAD. The task branch and worktree then move to the new
+/// HEAD, so the picked commits no longer count as unmerged. Nothing changes
+/// when any of them conflicts.
+pub fn cherry_pick(
+    root: &Path,
+    task_id: &str,
+    message: Option<&str>,
+    author: &CommitAuthor,
+) -> Result<CherryPickResult, String> {
+    let (repo, worktree) = open_task(root, task_id)?;
+    let signature = commit::signature(&repo, author)?;
+    commit_pending(
+        &worktree,
+        &signature,
+        message.unwrap_or(DEFAULT_COMMIT_MESSAGE),
+    )?;
+
+    let tip = head_commit(&worktree)?.ok_or_else(|| "Task branch has no commit".to_string())?;
+    let mut current = head_commit(&repo)?
+        .ok_or_else(|| "Repository HEAD has no commit to cherry-pick onto".to_string())?;
+    let mut revwalk = repo
+        .revwalk()
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+    revwalk
+        .set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)
+        .and_then(|_| revwalk.push(tip.id()))
+        .and_then(|_| revwalk.hide(current.id()))
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        let oid = oid.map_err(|e| format!("Failed to walk history: {}", e))?;
+        let picked = repo
+            .find_commit(oid)
+            .map_err(|e| format!("Failed to find commit: {}", e))?;
+        // Merges from HEAD into the task branch bring nothing new
+        if picked.parent_count() > 1 {
+            continue;
+        }
+
+        let mut index = repo
+            .cherrypick_commit(&picked, &current, 0, None)
+            .map_err(|e| format!("Failed to cherry-pick {}: {}", oid, e))?;
+        if index.has_conflicts() {
+            let paths: Vec<String> = index
+                .conflicts()
+                .map_err(|e| format!("Failed to read conflicts: {}", e))?
+                .filter_map(|conflict| conflict.ok())
+                .filter_map(|conflict| conflict.our.or(conflict.their).or(conflict.ancestor))
+                .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+                .collect();
+            return Err(format!(
+                "Cherry-picking {} conflicts in {}; merge the task instead to resolve them",
+                &oid.to_string()[..7],
+                paths.join(", ")
+            ));
+        }
+        let tree = index
+            .write_tree_to(&repo)
+            .and_then(|id| repo.find_tree(id))
+            .map_err(|e| format!("Failed to write tree: {}", e))?;
+        let id = repo
+            .commit(
+                None,
+                &picked.author(),
+                &signature,
+                picked.message().unwrap_or_default(),
+                &tree,
+                &[&current],
+            )
+            .map_err(|e| format!("Failed to commit: {}", e))?;
+        current = repo
+            .find_commit(id)
+            .map_err(|e| format!("Failed to find commit: {}", e))?;
+        commits.push(id.to_string());
+    }
+
+    if !commits.is_empty() {
+        let reflog = format!("cherry-pick: {}", branch_name(task_id));
+        merge::advance_head(&repo, &current, &reflog)?;
+    }
+    // Everything on the task branch is on HEAD now; only their ids differ
+    let new_tip = worktree
+        .find_object(current.id(), Some(ObjectType::Commit))
+        .map_err(|e| format!("Failed to find commit: {}", e))?;
+    worktree
+        .reset(&new_tip, ResetType::Hard, None)
+        .map_err(|e| format!("Failed to reset task branch: {}", e))?;
+    Ok(CherryPickResult { commits })
+}
+
+/// Delete the worktree of `task_id` and its branch. Unless `force` is set,
+/// fails with an "Unmerged:" error while the task has commits the
+/// repository's HEAD doesn't have or uncommitted changes. Succeeds when the
+/// task has no worktree.
+pub fn discard(root: &Path, task_id: &str, force: bool) -> Result<(), String> {
+    let Ok((repo, worktree)) = open_task(root, task_id) else {
+        return Ok(());
+    };
+    if !force {
+        let info = task_info(&repo, &worktree, task_id, Path::new(""))?;
+        let mut unmerged = Vec::new();
+        if info.ahead > 0 {
+            unmerged.push(format!("{} unmerged commits", info.ahead));
+        }
+        if info.dirty {
+            unmerged.push("uncommitted changes".to_string());
+        }
+        if !unmerged.is_empty() {
+            return Err(format!(
+                "Unmerged: branch {} has {}",
+                info.branch,
+                unmerged.join(" and ")
+            ));
+        }
+    }
+    drop(worktree);
+
+    let worktree = repo
+        .find_worktree(&sanitize_name(task_id))
+        .map_err(|e| format!("Failed to find worktree: {}", e))?;
+    let mut options = WorktreePruneOptions::new();
+    options.valid(true).locked(true).working_tree(true);
+    worktree
+        .prune(Some(&mut options))
+        .map_err(|e| format!("Failed to remove worktree: {}", e))?;
+
+    let branch = branch_name(task_id);
+    if let Ok(mut branch_ref) = repo.find_branch(&branch, BranchType::Local) {
+        branch_ref
+            .delete()
+            .map_err(|e| format!("Failed to delete branch {}: {}", branch, e))?;
+    }
+    Ok(())
+}
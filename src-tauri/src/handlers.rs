@@ -1,4 +1,3 @@
-use notify::{EventKind, RecursiveMode, Watcher};
 use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -16,9 +15,33 @@ use tokio::io::{AsyncReadExt, BufReader as AsyncBufReader};
 use tokio::process::Command as TokioCommand;
 use tokio::sync::Mutex;
 
+use crate::files::atomic::{self, FileStamp, WritePrecondition};
+use crate::files::chunked::{self, FileSniff};
+use crate::files::documents;
+use crate::files::encoding::{self, TextEncoding, TextFile};
+use crate::files::finder::{self, FileSuggestion};
+use crate::files::patch::{self, ApplyPatchRequest, ApplyPatchResult};
+use crate::files::search::{self, FileReplaceResult, SearchOptions};
+use crate::files::transfer::{self, ConflictPolicy, TransferOperation};
+use crate::files::trash::{self, TrashEntry, TrashLocation};
+use crate::files::watcher;
+use crate::git::{
+    self,
+    branch::{BranchInfo, TagInfo},
+    checkpoint::{self, Checkpoint, RestoreResult},
+    commit::CommitResult,
+    diff::{DiffMode, DiffSettings, GitDiff},
+    history::{BlameResult, CommitDetail, LogOptions, LogPage, RevisionFile},
+    merge::MergeResult,
+    stage::HunkSelector,
+    status::GitStatus,
+    worktree::{self, CherryPickResult, TaskWorktree},
+};
+
 // MCP module imports
 use crate::mcp::{
-    McpManager, McpPromptGetRequest, McpResourceReadRequest, McpServerConfig, McpToolCallRequest,
+    McpLogFilter, McpManager, McpPromptGetRequest, McpResourceReadRequest,
+    McpResourceTemplateReadRequest, McpServerConfig, McpToolCallRequest,
 };
 
 // Struct to hold the shell process with its event receiver
@@ -859,24 +882,82 @@ pub async fn read_directory(path: &str) -> Result<FileNode, String> {
     }
 }
 
+/// Reads a text file in any detected encoding. Use `read_text_file` to also get
+/// the encoding, line endings and stamp.
 #[tauri::command]
 pub async fn read_file(path: &str) -> Result<String, String> {
-    fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(encoding::decode(&bytes).0)
 }
 
+/// Writes atomically, keeping the existing file's encoding and line endings.
+/// When `expected_mtime` or `expected_hash` is given and the file changed on
+/// disk since then, fails with an error starting with "Conflict:".
 #[tauri::command]
-pub async fn write_file(path: &str, content: &str) -> Result<(), String> {
-    let path_buf = Path::new(path);
+pub async fn write_file(
+    path: &str,
+    content: &str,
+    expected_mtime: Option<u64>,
+    expected_hash: Option<String>,
+) -> Result<FileStamp, String> {
+    let precondition = WritePrecondition {
+        expected_mtime,
+        expected_hash,
+    };
+    encoding::write_text_file(Path::new(path), content, None, &precondition)
+}
 
-    // Check if the parent directory exists, create it if it doesn't
-    if let Some(parent) = path_buf.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-        }
-    }
+#[tauri::command]
+pub async fn read_text_file(path: &str) -> Result<TextFile, String> {
+    encoding::read_text_file(Path::new(path))
+}
 
-    fs::write(path, content).map_err(|e| format!("Failed to write file: {}", e))
+/// Like `write_file`, but writes with the given encoding metadata (as returned
+/// by `read_text_file`) instead of detecting it from the file on disk. Fails
+/// with an "Unrepresentable:" error when the content doesn't fit that encoding.
+#[tauri::command]
+pub async fn write_text_file(
+    path: &str,
+    content: &str,
+    encoding: Option<TextEncoding>,
+    expected_mtime: Option<u64>,
+    expected_hash: Option<String>,
+) -> Result<FileStamp, String> {
+    let precondition = WritePrecondition {
+        expected_mtime,
+        expected_hash,
+    };
+    encoding::write_text_file(Path::new(path), content, encoding, &precondition)
+}
+
+/// Read a file for editing and track it, so changes made on disk by anything
+/// else are reported as `document_changed` or, when the buffer has unsaved
+/// edits, as `document_conflict` with a three-way diff
+#[tauri::command]
+pub async fn open_document(path: &str, window: tauri::Window) -> Result<TextFile, String> {
+    documents::open(Path::new(path), window)
+}
+
+/// Report the unsaved content of an open document, or `None` once it is saved
+#[tauri::command]
+pub async fn update_document_buffer(path: &str, content: Option<String>) -> Result<(), String> {
+    documents::set_buffer(Path::new(path), content)
+}
+
+/// Save an open document. Fails with a "Conflict:" error if the file changed
+/// on disk since it was opened or last saved, unless `force` is set.
+#[tauri::command]
+pub async fn save_document(
+    path: &str,
+    content: &str,
+    force: Option<bool>,
+) -> Result<FileStamp, String> {
+    documents::save(Path::new(path), content, force.unwrap_or(false))
+}
+
+#[tauri::command]
+pub async fn close_document(path: &str) -> Result<(), String> {
+    documents::close(Path::new(path))
 }
 
 #[tauri::command]
@@ -885,37 +966,64 @@ pub async fn read_binary_file(path: &str) -> Result<Vec<u8>, String> {
 }
 
 #[tauri::command]
-pub async fn write_binary_file(path: &str, content: Vec<u8>) -> Result<(), String> {
-    let path_buf = Path::new(path);
-
-    // Check if the parent directory exists, create it if it doesn't
-    if let Some(parent) = path_buf.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-        }
-    }
+pub async fn write_binary_file(
+    path: &str,
+    content: Vec<u8>,
+    expected_mtime: Option<u64>,
+    expected_hash: Option<String>,
+) -> Result<FileStamp, String> {
+    let precondition = WritePrecondition {
+        expected_mtime,
+        expected_hash,
+    };
+    atomic::write_atomic(Path::new(path), &content, &precondition)
+}
 
-    fs::write(path, content).map_err(|e| format!("Failed to write file: {}", e))
+/// Returns the mtime and content hash to pass back as a write precondition
+#[tauri::command]
+pub async fn get_file_stamp(path: &str) -> Result<FileStamp, String> {
+    atomic::file_stamp(Path::new(path))
 }
 
 #[tauri::command]
 pub async fn read_file_by_range(path: &str, start: usize, end: usize) -> Result<String, String> {
-    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    chunked::read_lines(Path::new(path), start, end)
+}
 
-    let lines: Vec<&str> = content.lines().collect();
+#[tauri::command]
+pub async fn read_file_bytes(path: &str, offset: u64, length: u64) -> Result<Vec<u8>, String> {
+    chunked::read_bytes(Path::new(path), offset, length)
+}
 
-    // Ensure start and end are within bounds
-    let start = start.min(lines.len());
-    let end = end.min(lines.len());
+#[tauri::command]
+pub async fn get_file_line_count(path: &str) -> Result<usize, String> {
+    chunked::line_count(Path::new(path))
+}
 
-    if start >= end {
-        return Ok(String::new());
-    }
+#[tauri::command]
+pub async fn sniff_file(path: &str) -> Result<FileSniff, String> {
+    chunked::sniff_file(Path::new(path))
+}
+
+/// Let the `raven-file` protocol serve files under a workspace root
+#[tauri::command]
+pub async fn allow_file_protocol_root(path: &str) -> Result<(), String> {
+    chunked::allow_protocol_root(Path::new(path))
+}
 
-    // Extract the line range (start is inclusive, end is exclusive)
-    let range_lines = &lines[start..end];
-    Ok(range_lines.join("\n"))
+#[tauri::command]
+pub async fn revoke_file_protocol_root(path: &str) -> Result<(), String> {
+    chunked::revoke_protocol_root(Path::new(path));
+    Ok(())
+}
+
+/// Apply a unified diff and/or search/replace edits. Nothing is written unless
+/// every hunk applies; per-hunk results are returned either way.
+#[tauri::command]
+pub async fn apply_patch(request: ApplyPatchRequest) -> Result<ApplyPatchResult, String> {
+    tauri::async_runtime::spawn_blocking(move || patch::apply_patch(request))
+        .await
+        .map_err(|e| format!("Failed to apply patch: {}", e))?
 }
 
 #[tauri::command]
@@ -933,17 +1041,53 @@ pub async fn rename_file(old_path: &str, new_path: &str) -> Result<(), String> {
     fs::rename(old_path, new_path).map_err(|e| format!("Failed to rename: {}", e))
 }
 
+/// Moves the path to the trash unless `permanent` is set. `deleted_by` records
+/// who asked for the deletion ("user" or an agent id).
 #[tauri::command]
-pub async fn delete_file(path: &str) -> Result<(), String> {
+pub async fn delete_file(
+    path: &str,
+    deleted_by: Option<String>,
+    permanent: Option<bool>,
+    system_trash: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<Option<TrashEntry>, String> {
     let path_buf = Path::new(path);
 
-    if path_buf.is_dir() {
-        fs::remove_dir_all(path_buf).map_err(|e| format!("Failed to delete directory: {}", e))?;
-    } else {
-        fs::remove_file(path_buf).map_err(|e| format!("Failed to delete file: {}", e))?;
+    if permanent.unwrap_or(false) {
+        if path_buf.is_dir() {
+            fs::remove_dir_all(path_buf)
+                .map_err(|e| format!("Failed to delete directory: {}", e))?;
+        } else {
+            fs::remove_file(path_buf).map_err(|e| format!("Failed to delete file: {}", e))?;
+        }
+        return Ok(None);
     }
 
-    Ok(())
+    let location = if system_trash.unwrap_or(false) {
+        TrashLocation::System
+    } else {
+        TrashLocation::App
+    };
+    let deleted_by = deleted_by.unwrap_or_else(|| "user".to_string());
+
+    trash::move_to_trash(&app, path_buf, &deleted_by, location)
+        .await
+        .map(Some)
+}
+
+#[tauri::command]
+pub async fn list_trash(app: tauri::AppHandle) -> Result<Vec<TrashEntry>, String> {
+    trash::list_trash(&app).await
+}
+
+#[tauri::command]
+pub async fn restore_from_trash(id: String, app: tauri::AppHandle) -> Result<TrashEntry, String> {
+    trash::restore_from_trash(&app, &id).await
+}
+
+#[tauri::command]
+pub async fn empty_trash(ids: Option<Vec<String>>, app: tauri::AppHandle) -> Result<usize, String> {
+    trash::empty_trash(&app, ids).await
 }
 
 #[tauri::command]
@@ -954,7 +1098,48 @@ pub async fn move_file(from_path: &str, to_path: &str) -> Result<(), String> {
             .map_err(|e| format!("Failed to create destination directory: {}", e))?;
     }
 
-    fs::rename(from_path, to_path).map_err(|e| format!("Failed to move file: {}", e))
+    transfer::move_path(Path::new(from_path), Path::new(to_path))
+        .map_err(|e| format!("Failed to move file: {}", e))
+}
+
+/// Copy files and directory trees into `destination` in the background.
+/// Returns a job id; progress is reported through `file_transfer_progress` events.
+#[tauri::command]
+pub async fn copy_paths(
+    sources: Vec<String>,
+    destination: String,
+    conflict: Option<ConflictPolicy>,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    transfer::start_transfer(
+        app,
+        TransferOperation::Copy,
+        sources,
+        destination,
+        conflict.unwrap_or_default(),
+    )
+}
+
+/// Move files and directory trees into `destination`, copying across filesystems
+#[tauri::command]
+pub async fn move_paths(
+    sources: Vec<String>,
+    destination: String,
+    conflict: Option<ConflictPolicy>,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    transfer::start_transfer(
+        app,
+        TransferOperation::Move,
+        sources,
+        destination,
+        conflict.unwrap_or_default(),
+    )
+}
+
+#[tauri::command]
+pub async fn cancel_file_transfer(job_id: String) -> Result<(), String> {
+    transfer::cancel_transfer(&job_id)
 }
 
 struct TerminalSession {
@@ -1574,242 +1759,139 @@ pub async fn acp_terminal_release(
     Ok(())
 }
 
-// File Watcher structures
-struct FileWatcherSession {
-    _watcher: notify::RecommendedWatcher,
-    #[allow(dead_code)]
-    base_path: PathBuf,
-}
+/// Subscription ids of a session, keyed by (base path, relative path)
+type WatchSubscriptions = HashMap<(String, String), String>;
 
-static FILE_WATCHERS: std::sync::LazyLock<Arc<Mutex<HashMap<String, FileWatcherSession>>>> =
+static FILE_WATCHERS: std::sync::LazyLock<Arc<Mutex<HashMap<String, WatchSubscriptions>>>> =
     std::sync::LazyLock::new(|| Arc::new(Mutex::new(HashMap::new())));
 
 #[derive(Deserialize)]
 pub struct WatchFileArgs {
     session_id: String,
-    #[allow(dead_code)]
     relative_path: String,
     base_path: String,
 }
 
+/// Subscribe a session to changes of `relative_path` under `base_path` and
+/// return the subscription id. Events are emitted as `file_changed::<id>`
+/// and, for directories, `file_tree_changed::<id>`. Watching the same path
+/// twice in one session returns the existing subscription.
 #[tauri::command]
-pub async fn watch_file(args: WatchFileArgs, window: tauri::Window) -> Result<(), String> {
+pub async fn watch_file(args: WatchFileArgs, window: tauri::Window) -> Result<String, String> {
     let WatchFileArgs {
         session_id,
-        relative_path: _,
+        relative_path,
         base_path,
     } = args;
 
-    let base_path_buf = PathBuf::from(&base_path);
-
-    // Validate the base path exists
-    if !base_path_buf.exists() {
-        return Err("Base path does not exist".to_string());
-    }
-
     let mut watchers = FILE_WATCHERS.lock().await;
+    let session = watchers.entry(session_id).or_default();
+    let key = (base_path, relative_path);
 
-    // Only create a new watcher if one doesn't exist for this session
-    if !watchers.contains_key(&session_id) {
-        let window_clone = window.clone();
-        let base_path_clone = base_path_buf.clone();
-
-        // Create the watcher with the new notify API
-        let mut watcher =
-            notify::recommended_watcher(move |res: Result<notify::Event, notify::Error>| {
-                match res {
-                    Ok(event) => {
-                        match &event.kind {
-                            // Only handle actual data/content changes, not metadata
-                            EventKind::Modify(notify::event::ModifyKind::Data(_)) => {
-                                // Handle file content modifications
-                                for path in &event.paths {
-                                    if path.is_file() {
-                                        let relative = path
-                                            .strip_prefix(&base_path_clone)
-                                            .ok()
-                                            .and_then(|p: &Path| p.to_str())
-                                            .unwrap_or("");
-
-                                        let _ = window_clone.emit(
-                                            "file_changed",
-                                            serde_json::json!({
-                                                "path": relative,
-                                                "type": "modified"
-                                            }),
-                                        );
-                                    }
-                                }
-                            }
-                            EventKind::Create(_) => {
-                                // Handle file/folder creation
-                                for path in &event.paths {
-                                    let relative = path
-                                        .strip_prefix(&base_path_clone)
-                                        .ok()
-                                        .and_then(|p: &Path| p.to_str())
-                                        .unwrap_or("");
-
-                                    let _ = window_clone.emit(
-                                        "file_tree_changed",
-                                        serde_json::json!({
-                                            "path": relative,
-                                            "type": "created"
-                                        }),
-                                    );
-                                }
-                            }
-                            EventKind::Remove(_) => {
-                                // Handle file/folder removal
-                                for path in &event.paths {
-                                    let relative = path
-                                        .strip_prefix(&base_path_clone)
-                                        .ok()
-                                        .and_then(|p: &Path| p.to_str())
-                                        .unwrap_or("");
-
-                                    let _ = window_clone.emit(
-                                        "file_tree_changed",
-                                        serde_json::json!({
-                                            "path": relative,
-                                            "type": "removed"
-                                        }),
-                                    );
-                                }
-                            }
-                            // Handle rename/move events as file tree changes
-                            EventKind::Modify(notify::event::ModifyKind::Name(_)) => {
-                                for path in &event.paths {
-                                    let relative = path
-                                        .strip_prefix(&base_path_clone)
-                                        .ok()
-                                        .and_then(|p: &Path| p.to_str())
-                                        .unwrap_or("");
-
-                                    let _ = window_clone.emit(
-                                        "file_tree_changed",
-                                        serde_json::json!({
-                                            "path": relative,
-                                            "type": "renamed"
-                                        }),
-                                    );
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Watch error: {:?}", e);
-                    }
-                }
-            })
-            .expect("Failed to create file watcher");
-
-        // Watch the base directory recursively
-        watcher
-            .watch(&base_path_buf, RecursiveMode::Recursive)
-            .map_err(|e| format!("Failed to watch directory: {}", e))?;
-
-        watchers.insert(
-            session_id.clone(),
-            FileWatcherSession {
-                _watcher: watcher,
-                base_path: base_path_buf,
-            },
-        );
+    if let Some(id) = session.get(&key) {
+        return Ok(id.clone());
     }
 
-    Ok(())
+    let id = watcher::subscribe(Path::new(&key.0), &key.1, window)?;
+    session.insert(key, id.clone());
+    Ok(id)
 }
 
 #[tauri::command]
 pub async fn unwatch_file(
-    _session_id: String,
-    _relative_path: String,
-    _base_path: String,
+    session_id: String,
+    relative_path: String,
+    base_path: String,
 ) -> Result<(), String> {
-    // No-op since we now watch the entire base directory
-    // Individual files don't need to be unwatched anymore
-    Ok(())
+    let mut watchers = FILE_WATCHERS.lock().await;
+    let id = watchers
+        .get_mut(&session_id)
+        .and_then(|session| session.remove(&(base_path, relative_path)));
+
+    match id {
+        Some(id) => watcher::unsubscribe(&id),
+        None => Ok(()),
+    }
 }
 
 #[tauri::command]
 pub async fn stop_watching(session_id: String) -> Result<(), String> {
     let mut watchers = FILE_WATCHERS.lock().await;
-    watchers.remove(&session_id);
-    Ok(())
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct FileSuggestion {
-    name: String,
-    path: String,
-    is_dir: bool,
+    let Some(session) = watchers.remove(&session_id) else {
+        return Ok(());
+    };
+    // Every subscription is released even if some fail, or they would leak
+    let errors: Vec<String> = session
+        .into_values()
+        .filter_map(|id| watcher::unsubscribe(&id).err())
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Failed to stop watching: {}", errors.join("; ")))
+    }
 }
 
+/// Fuzzy or glob search over the cached file index of `directory`
 #[tauri::command]
 pub async fn glob_files(
-    directory: &str,
-    pattern: Option<&str>,
+    directory: String,
+    pattern: Option<String>,
+    limit: Option<usize>,
 ) -> Result<Vec<FileSuggestion>, String> {
-    use ignore::WalkBuilder;
-    use std::path::Path;
-
-    let dir_path = Path::new(directory);
+    let dir_path = PathBuf::from(&directory);
     if !dir_path.exists() {
         return Err(format!("Directory does not exist: {}", directory));
     }
 
-    let query = pattern.unwrap_or("").to_lowercase();
-
-    let mut results = Vec::new();
-
-    let walker = WalkBuilder::new(directory)
-        .hidden(true)
-        .git_ignore(true)
-        .git_global(true)
-        .git_exclude(true)
-        .build();
-
-    for entry in walker {
-        let entry = match entry {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
-
-        let path = entry.path();
-
-        // Skip the root directory itself
-        if path == dir_path {
-            continue;
-        }
-
-        let file_name = match path.file_name() {
-            Some(name) => name.to_string_lossy().to_string(),
-            None => continue,
-        };
-
-        let relative_path = path
-            .strip_prefix(directory)
-            .unwrap_or(path)
-            .to_string_lossy()
-            .to_string();
-
-        // Match query as substring in relative path (case-insensitive)
-        if !query.is_empty() && !relative_path.to_lowercase().contains(&query) {
-            continue;
-        }
+    tauri::async_runtime::spawn_blocking(move || {
+        finder::find_files(
+            &dir_path,
+            pattern.as_deref().unwrap_or(""),
+            limit.unwrap_or(finder::DEFAULT_LIMIT),
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to search files: {}", e))?
+}
 
-        let is_dir = path.is_dir();
+/// Search file contents under `directory`. Returns a search id; matches are
+/// streamed through `search_result` events and finish with `search_complete`.
+#[tauri::command]
+pub async fn search_in_files(
+    directory: String,
+    options: SearchOptions,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    search::start_search(app, PathBuf::from(directory), options)
+}
 
-        results.push(FileSuggestion {
-            name: file_name,
-            is_dir,
-            path: relative_path,
-        });
-    }
+#[tauri::command]
+pub async fn cancel_search(search_id: String) -> Result<(), String> {
+    search::cancel_search(&search_id)
+}
 
-    Ok(results)
+/// Replace search matches across files. With `preview` set, returns the
+/// changed lines without writing; `paths` limits the replacement to those files.
+#[tauri::command]
+pub async fn replace_in_files(
+    directory: String,
+    options: SearchOptions,
+    replacement: String,
+    paths: Option<Vec<String>>,
+    preview: Option<bool>,
+) -> Result<Vec<FileReplaceResult>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        search::replace_in_files(
+            Path::new(&directory),
+            &options,
+            &replacement,
+            paths,
+            preview.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to replace in files: {}", e))?
 }
 
 #[tauri::command]
@@ -1943,58 +2025,416 @@ pub async fn is_git_repo(path: &str) -> Result<bool, String> {
         .map_err(|e| e.to_string())
 }
 
+/// Patch of the changes staged in the index
 #[tauri::command]
 pub async fn get_git_diff_all(base_path: &str) -> Result<String, String> {
-    let repo = git2::Repository::open(base_path)
-        .map_err(|e| format!("Failed to open repository: {}", e))?;
-
-    let head = repo
-        .head()
-        .map_err(|e| format!("Failed to get HEAD: {}", e))?;
-
-    let head_commit = head
-        .peel_to_commit()
-        .map_err(|e| format!("Failed to peel to commit: {}", e))?;
-
-    let head_tree = head_commit
-        .tree()
-        .map_err(|e| format!("Failed to get tree: {}", e))?;
-
-    let index = repo
-        .index()
-        .map_err(|e| format!("Failed to get index: {}", e))?;
-
-    let mut opts = git2::DiffOptions::new();
-    let diff = repo
-        .diff_tree_to_index(Some(&head_tree), Some(&index), Some(&mut opts))
-        .map_err(|e| format!("Failed to create diff: {}", e))?;
-
-    let mut diff_text = String::new();
-    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
-        match line.origin() {
-            'F' | 'H' => {
-                // File headers (diff --git) and hunk headers (@@)
-                // These don't have the origin prefix, just push the content
-                diff_text.push_str(std::str::from_utf8(line.content()).unwrap_or(""));
-            }
-            '+' | '-' | ' ' => {
-                // Content lines - include the origin prefix
-                diff_text.push(line.origin());
-                diff_text.push_str(std::str::from_utf8(line.content()).unwrap_or(""));
-            }
-            _ => {}
-        }
-        true
+    git::diff::diff(
+        Path::new(base_path),
+        DiffMode::Staged,
+        None,
+        &[],
+        &DiffSettings::default(),
+    )
+    .map(|diff| diff.patch)
+}
+
+/// Per-file status of the index and working tree, plus branch, upstream and
+/// any merge or rebase in progress. Works on repositories without commits.
+#[tauri::command]
+pub async fn git_status(
+    base_path: String,
+    include_ignored: Option<bool>,
+) -> Result<GitStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::status::status(Path::new(&base_path), include_ignored.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| format!("Failed to get status: {}", e))?
+}
+
+/// Structured diff of staged, unstaged or all changes, or of the working tree
+/// against `commit`, optionally limited to `paths`. Files past the size caps
+/// in `options` come without hunks.
+#[tauri::command]
+pub async fn git_diff(
+    base_path: String,
+    mode: Option<DiffMode>,
+    commit: Option<String>,
+    paths: Option<Vec<String>>,
+    options: Option<DiffSettings>,
+) -> Result<GitDiff, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::diff::diff(
+            Path::new(&base_path),
+            mode.unwrap_or_default(),
+            commit.as_deref(),
+            &paths.unwrap_or_default(),
+            &options.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to create diff: {}", e))?
+}
+
+#[tauri::command]
+pub async fn git_stage(base_path: String, paths: Vec<String>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::stage::stage_paths(Path::new(&base_path), &paths)
+    })
+    .await
+    .map_err(|e| format!("Failed to stage files: {}", e))?
+}
+
+#[tauri::command]
+pub async fn git_unstage(base_path: String, paths: Vec<String>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::stage::unstage_paths(Path::new(&base_path), &paths)
+    })
+    .await
+    .map_err(|e| format!("Failed to unstage files: {}", e))?
+}
+
+/// Stage hunks taken from an unstaged `git_diff`
+#[tauri::command]
+pub async fn git_stage_hunks(base_path: String, hunks: Vec<HunkSelector>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::stage::stage_hunks(Path::new(&base_path), &hunks)
+    })
+    .await
+    .map_err(|e| format!("Failed to stage hunks: {}", e))?
+}
+
+/// Unstage hunks taken from a staged `git_diff`
+#[tauri::command]
+pub async fn git_unstage_hunks(base_path: String, hunks: Vec<HunkSelector>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::stage::unstage_hunks(Path::new(&base_path), &hunks)
+    })
+    .await
+    .map_err(|e| format!("Failed to unstage hunks: {}", e))?
+}
+
+/// Drop hunks taken from an unstaged `git_diff` from the working tree
+#[tauri::command]
+pub async fn git_discard_hunks(base_path: String, hunks: Vec<HunkSelector>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::stage::discard_hunks(Path::new(&base_path), &hunks)
+    })
+    .await
+    .map_err(|e| format!("Failed to discard hunks: {}", e))?
+}
+
+/// Drop unstaged changes to files; untracked files are deleted
+#[tauri::command]
+pub async fn git_discard_paths(base_path: String, paths: Vec<String>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::stage::discard_paths(Path::new(&base_path), &paths)
+    })
+    .await
+    .map_err(|e| format!("Failed to discard changes: {}", e))?
+}
+
+/// Commit the staged changes, authored by the name and email from the git
+/// settings, falling back to the repository's git config
+#[tauri::command]
+pub async fn git_commit(
+    base_path: String,
+    message: String,
+    app: tauri::AppHandle,
+) -> Result<CommitResult, String> {
+    let author = git::commit::author_from_settings(&app).await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        git::commit::commit(Path::new(&base_path), &message, &author)
+    })
+    .await
+    .map_err(|e| format!("Failed to commit: {}", e))?
+}
+
+#[tauri::command]
+pub async fn git_stash_push(
+    base_path: String,
+    message: Option<String>,
+    include_untracked: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    let author = git::commit::author_from_settings(&app).await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        git::commit::stash_push(
+            Path::new(&base_path),
+            message.as_deref(),
+            include_untracked.unwrap_or(true),
+            &author,
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to stash: {}", e))?
+}
+
+#[tauri::command]
+pub async fn git_stash_pop(base_path: String, index: Option<usize>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::commit::stash_pop(Path::new(&base_path), index.unwrap_or(0))
+    })
+    .await
+    .map_err(|e| format!("Failed to pop stash: {}", e))?
+}
+
+#[tauri::command]
+pub async fn git_list_branches(
+    base_path: &str,
+    include_remote: Option<bool>,
+) -> Result<Vec<BranchInfo>, String> {
+    git::branch::list_branches(Path::new(base_path), include_remote.unwrap_or(false))
+}
+
+/// Create a branch at `start_point`, HEAD by default, and optionally check it
+/// out
+#[tauri::command]
+pub async fn git_create_branch(
+    base_path: &str,
+    name: &str,
+    start_point: Option<String>,
+    checkout: Option<bool>,
+) -> Result<BranchInfo, String> {
+    git::branch::create_branch(
+        Path::new(base_path),
+        name,
+        start_point.as_deref(),
+        checkout.unwrap_or(false),
+    )
+}
+
+/// Check out a local branch, or detach HEAD at any other revision. Refuses
+/// when local changes would be overwritten.
+#[tauri::command]
+pub async fn git_checkout(base_path: String, target: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::branch::checkout(Path::new(&base_path), &target)
+    })
+    .await
+    .map_err(|e| format!("Failed to check out: {}", e))?
+}
+
+/// Merge a branch or revision into HEAD, reporting conflicts left to resolve
+#[tauri::command]
+pub async fn git_merge(
+    base_path: String,
+    source: String,
+    app: tauri::AppHandle,
+) -> Result<MergeResult, String> {
+    let author = git::commit::author_from_settings(&app).await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        git::branch::merge(Path::new(&base_path), &source, &author)
     })
-    .map_err(|e| format!("Failed to print diff: {}", e))?;
+    .await
+    .map_err(|e| format!("Failed to merge: {}", e))?
+}
+
+#[tauri::command]
+pub async fn git_list_tags(base_path: &str) -> Result<Vec<TagInfo>, String> {
+    git::branch::list_tags(Path::new(base_path))
+}
+
+/// Page of commits reachable from HEAD or `options.revision`, optionally
+/// limited to a path or author
+#[tauri::command]
+pub async fn git_log(base_path: String, options: Option<LogOptions>) -> Result<LogPage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::history::log(Path::new(&base_path), &options.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| format!("Failed to read history: {}", e))?
+}
 
-    Ok(diff_text)
+#[tauri::command]
+pub async fn git_show_commit(
+    base_path: String,
+    revision: String,
+    options: Option<DiffSettings>,
+) -> Result<CommitDetail, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::history::show_commit(
+            Path::new(&base_path),
+            &revision,
+            &options.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to read commit: {}", e))?
+}
+
+/// Commit that last changed each line of `path`, at `revision` or in the
+/// working tree
+#[tauri::command]
+pub async fn git_blame(
+    base_path: String,
+    path: String,
+    revision: Option<String>,
+) -> Result<BlameResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::history::blame(Path::new(&base_path), &path, revision.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Failed to blame file: {}", e))?
+}
+
+/// Content of `path` at `revision`; an empty revision reads the index
+#[tauri::command]
+pub async fn git_file_at_revision(
+    base_path: &str,
+    path: &str,
+    revision: &str,
+) -> Result<RevisionFile, String> {
+    git::history::file_at_revision(Path::new(base_path), path, revision)
+}
+
+/// Snapshot `cwd` before an agent turn of `chat_id`: a hidden git ref in
+/// repositories, a shadow copy in the app data directory otherwise
+#[tauri::command]
+pub async fn create_checkpoint(
+    chat_id: String,
+    cwd: String,
+    label: String,
+    app: tauri::AppHandle,
+) -> Result<Checkpoint, String> {
+    let shadow_root = checkpoint::checkpoints_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        checkpoint::create_checkpoint(&shadow_root, Path::new(&cwd), &chat_id, &label)
+    })
+    .await
+    .map_err(|e| format!("Failed to create checkpoint: {}", e))?
+}
+
+/// Checkpoints of a chat, oldest first
+#[tauri::command]
+pub async fn list_checkpoints(
+    chat_id: String,
+    cwd: String,
+    app: tauri::AppHandle,
+) -> Result<Vec<Checkpoint>, String> {
+    let shadow_root = checkpoint::checkpoints_dir(&app)?;
+    checkpoint::list_checkpoints(&shadow_root, Path::new(&cwd), &chat_id)
+}
+
+/// Revert all files changed since a checkpoint without touching the index
+#[tauri::command]
+pub async fn restore_checkpoint(
+    chat_id: String,
+    cwd: String,
+    checkpoint_id: String,
+    app: tauri::AppHandle,
+) -> Result<RestoreResult, String> {
+    let shadow_root = checkpoint::checkpoints_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        checkpoint::restore_checkpoint(&shadow_root, Path::new(&cwd), &chat_id, &checkpoint_id)
+    })
+    .await
+    .map_err(|e| format!("Failed to restore checkpoint: {}", e))?
+}
+
+#[tauri::command]
+pub async fn delete_checkpoint(
+    chat_id: String,
+    cwd: String,
+    checkpoint_id: String,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let shadow_root = checkpoint::checkpoints_dir(&app)?;
+    checkpoint::delete_checkpoint(&shadow_root, Path::new(&cwd), &chat_id, &checkpoint_id)
+}
+
+/// Worktree on a branch of its own for agent task `task_id`, created from the
+/// HEAD of the repository containing `directory` on first use
+#[tauri::command]
+pub async fn create_task_worktree(
+    task_id: String,
+    directory: String,
+    app: tauri::AppHandle,
+) -> Result<TaskWorktree, String> {
+    let root = worktree::worktrees_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        worktree::create(&root, Path::new(&directory), &task_id)
+    })
+    .await
+    .map_err(|e| format!("Failed to create worktree: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_task_worktree(
+    task_id: String,
+    app: tauri::AppHandle,
+) -> Result<Option<TaskWorktree>, String> {
+    let root = worktree::worktrees_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || worktree::get(&root, &task_id))
+        .await
+        .map_err(|e| format!("Failed to get worktree: {}", e))?
+}
+
+/// Commit what the task left uncommitted and merge its branch into the
+/// repository's current branch
+#[tauri::command]
+pub async fn merge_task_worktree(
+    task_id: String,
+    message: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<MergeResult, String> {
+    let root = worktree::worktrees_dir(&app)?;
+    let author = git::commit::author_from_settings(&app).await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        worktree::merge(&root, &task_id, message.as_deref(), &author)
+    })
+    .await
+    .map_err(|e| format!("Failed to merge task: {}", e))?
+}
+
+/// Commit what the task left uncommitted and replay its commits on the
+/// repository's current branch
+#[tauri::command]
+pub async fn cherry_pick_task_worktree(
+    task_id: String,
+    message: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<CherryPickResult, String> {
+    let root = worktree::worktrees_dir(&app)?;
+    let author = git::commit::author_from_settings(&app).await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        worktree::cherry_pick(&root, &task_id, message.as_deref(), &author)
+    })
+    .await
+    .map_err(|e| format!("Failed to cherry-pick task: {}", e))?
+}
+
+/// Remove the task's worktree and branch. Fails with an "Unmerged:" error
+/// when that would drop changes not merged back, unless `force` is set.
+#[tauri::command]
+pub async fn discard_task_worktree(
+    task_id: String,
+    force: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let root = worktree::worktrees_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        worktree::discard(&root, &task_id, force.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| format!("Failed to discard worktree: {}", e))?
 }
 
 // MCP Server Management
 static MCP_MANAGER: std::sync::LazyLock<Arc<Mutex<Option<McpManager>>>> =
     std::sync::LazyLock::new(|| Arc::new(Mutex::new(None)));
 
+/// The manager shares its state across clones, so commands clone it out of
+/// the lock instead of holding the lock while servers respond
+async fn mcp_manager() -> Result<McpManager, serde_json::Value> {
+    MCP_MANAGER.lock().await.clone().ok_or_else(|| {
+        serde_json::json!({
+            "code": -32000,
+            "message": "MCP manager not initialized. Call mcp_start_servers first."
+        })
+    })
+}
+
 #[tauri::command]
 pub async fn mcp_start_servers(
     configs: Vec<(String, McpServerConfig)>,
@@ -2003,17 +2443,23 @@ pub async fn mcp_start_servers(
     println!("Starting {} MCP servers: {:#?}", configs.len(), configs);
 
     // Initialize manager if needed
-    {
-        let mut manager = MCP_MANAGER.lock().await;
-        if manager.is_none() {
-            *manager = Some(crate::mcp::McpManager::new(app.clone()));
-        }
-    }
+    let manager = MCP_MANAGER
+        .lock()
+        .await
+        .get_or_insert_with(|| crate::mcp::McpManager::new(app.clone()))
+        .clone();
+
+    // Log persistence is a setting; pick up changes on every (re)start
+    let log_dir = crate::mcp::log_dir_from_settings(&app)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            None
+        });
 
-    let manager = MCP_MANAGER.lock().await;
-    let manager_ref = manager.as_ref().expect("Manager should be initialized");
+    manager.set_log_directory(log_dir);
 
-    manager_ref
+    manager
         .start_servers(configs)
         .await
         .map(|results| {
@@ -2034,15 +2480,9 @@ pub async fn mcp_start_servers(
 
 #[tauri::command]
 pub async fn mcp_list_tools() -> Result<serde_json::Value, serde_json::Value> {
-    let manager = MCP_MANAGER.lock().await;
-    let manager_ref = manager.as_ref().ok_or_else(|| {
-        serde_json::json!({
-            "code": -32000,
-            "message": "MCP manager not initialized. Call mcp_start_servers first."
-        })
-    })?;
+    let manager = mcp_manager().await?;
 
-    manager_ref
+    manager
         .list_tools()
         .await
         .map(|tools| {
@@ -2070,15 +2510,9 @@ pub async fn mcp_call_tool(
         request.tool_name, request.server_id
     );
 
-    let manager = MCP_MANAGER.lock().await;
-    let manager_ref = manager.as_ref().ok_or_else(|| {
-        serde_json::json!({
-            "code": -32000,
-            "message": "MCP manager not initialized. Call mcp_start_servers first."
-        })
-    })?;
+    let manager = mcp_manager().await?;
 
-    manager_ref
+    manager
         .call_tool(request)
         .await
         .map(|response| {
@@ -2101,15 +2535,9 @@ pub async fn mcp_call_tool(
 pub async fn mcp_stop_server(server_id: String) -> Result<(), serde_json::Value> {
     println!("Stopping MCP server: {}", server_id);
 
-    let manager = MCP_MANAGER.lock().await;
-    let manager_ref = manager.as_ref().ok_or_else(|| {
-        serde_json::json!({
-            "code": -32000,
-            "message": "MCP manager not initialized. Call mcp_start_servers first."
-        })
-    })?;
+    let manager = mcp_manager().await?;
 
-    manager_ref.stop_server(&server_id).await.map_err(|e| {
+    manager.stop_server(&server_id).await.map_err(|e| {
         serde_json::json!({
             "code": -32000,
             "message": format!("Failed to stop server: {}", e)
@@ -2117,17 +2545,25 @@ pub async fn mcp_stop_server(server_id: String) -> Result<(), serde_json::Value>
     })
 }
 
+/// Re-fetch a server's tools, resources and prompts; the result arrives as
+/// an `mcp-catalog-changed` event
 #[tauri::command]
-pub async fn mcp_list_servers() -> Result<serde_json::Value, serde_json::Value> {
-    let manager = MCP_MANAGER.lock().await;
-    let manager_ref = manager.as_ref().ok_or_else(|| {
+pub async fn mcp_refresh_server(server_id: String) -> Result<(), serde_json::Value> {
+    let manager = mcp_manager().await?;
+
+    manager.refresh_server(&server_id).await.map_err(|e| {
         serde_json::json!({
             "code": -32000,
-            "message": "MCP manager not initialized. Call mcp_start_servers first."
+            "message": format!("Failed to refresh server: {}", e)
         })
-    })?;
+    })
+}
+
+#[tauri::command]
+pub async fn mcp_list_servers() -> Result<serde_json::Value, serde_json::Value> {
+    let manager = mcp_manager().await?;
 
-    let servers = manager_ref.list_servers().await;
+    let servers = manager.list_servers().await;
     serde_json::to_value(servers).map_err(|e| {
         serde_json::json!({
             "code": -32000,
@@ -2136,24 +2572,42 @@ pub async fn mcp_list_servers() -> Result<serde_json::Value, serde_json::Value>
     })
 }
 
+/// Apply changed log persistence settings to running servers; stopped ones
+/// pick them up when started
 #[tauri::command]
-pub async fn mcp_get_server_logs(
-    server_id: String,
+pub async fn mcp_reload_log_settings(
+    app: tauri::AppHandle,
 ) -> Result<serde_json::Value, serde_json::Value> {
-    let manager = MCP_MANAGER.lock().await;
-    let manager_ref = manager.as_ref().ok_or_else(|| {
+    let log_dir = crate::mcp::log_dir_from_settings(&app).await.map_err(|e| {
         serde_json::json!({
             "code": -32000,
-            "message": "MCP manager not initialized. Call mcp_start_servers first."
+            "message": e
         })
     })?;
 
-    let logs = manager_ref.get_server_logs(&server_id).await.map_err(|e| {
-        serde_json::json!({
-            "code": -32000,
-            "message": format!("Failed to get server logs: {}", e)
-        })
-    })?;
+    let manager = MCP_MANAGER.lock().await.clone();
+    if let Some(manager) = manager {
+        manager.set_log_directory(log_dir);
+    }
+    Ok(serde_json::Value::Null)
+}
+
+#[tauri::command]
+pub async fn mcp_get_server_logs(
+    server_id: String,
+    filter: Option<McpLogFilter>,
+) -> Result<serde_json::Value, serde_json::Value> {
+    let manager = mcp_manager().await?;
+
+    let logs = manager
+        .get_server_logs(&server_id, filter.unwrap_or_default())
+        .await
+        .map_err(|e| {
+            serde_json::json!({
+                "code": -32000,
+                "message": format!("Failed to get server logs: {}", e)
+            })
+        })?;
 
     serde_json::to_value(logs).map_err(|e| {
         serde_json::json!({
@@ -2165,15 +2619,9 @@ pub async fn mcp_get_server_logs(
 
 #[tauri::command]
 pub async fn mcp_list_resources() -> Result<serde_json::Value, serde_json::Value> {
-    let manager = MCP_MANAGER.lock().await;
-    let manager_ref = manager.as_ref().ok_or_else(|| {
-        serde_json::json!({
-            "code": -32000,
-            "message": "MCP manager not initialized. Call mcp_start_servers first."
-        })
-    })?;
+    let manager = mcp_manager().await?;
 
-    manager_ref
+    manager
         .list_resources()
         .await
         .map(|resources| {
@@ -2193,16 +2641,33 @@ pub async fn mcp_list_resources() -> Result<serde_json::Value, serde_json::Value
 }
 
 #[tauri::command]
-pub async fn mcp_list_prompts() -> Result<serde_json::Value, serde_json::Value> {
-    let manager = MCP_MANAGER.lock().await;
-    let manager_ref = manager.as_ref().ok_or_else(|| {
-        serde_json::json!({
-            "code": -32000,
-            "message": "MCP manager not initialized. Call mcp_start_servers first."
+pub async fn mcp_list_resource_templates() -> Result<serde_json::Value, serde_json::Value> {
+    let manager = mcp_manager().await?;
+
+    manager
+        .list_resource_templates()
+        .await
+        .map(|templates| {
+            serde_json::to_value(templates).map_err(|e| {
+                serde_json::json!({
+                    "code": -32000,
+                    "message": format!("Failed to serialize resource templates: {}", e)
+                })
+            })
         })
-    })?;
+        .map_err(|e| {
+            serde_json::json!({
+                "code": -32000,
+                "message": format!("Failed to list resource templates: {}", e)
+            })
+        })?
+}
 
-    manager_ref
+#[tauri::command]
+pub async fn mcp_list_prompts() -> Result<serde_json::Value, serde_json::Value> {
+    let manager = mcp_manager().await?;
+
+    manager
         .list_prompts()
         .await
         .map(|prompts| {
@@ -2230,15 +2695,9 @@ pub async fn mcp_read_resource(
         request.uri, request.server_id
     );
 
-    let manager = MCP_MANAGER.lock().await;
-    let manager_ref = manager.as_ref().ok_or_else(|| {
-        serde_json::json!({
-            "code": -32000,
-            "message": "MCP manager not initialized. Call mcp_start_servers first."
-        })
-    })?;
+    let manager = mcp_manager().await?;
 
-    manager_ref
+    manager
         .read_resource(request)
         .await
         .map(|response| {
@@ -2257,6 +2716,74 @@ pub async fn mcp_read_resource(
         })?
 }
 
+/// Expand a resource template with `request.arguments` and read the result
+#[tauri::command]
+pub async fn mcp_read_resource_template(
+    request: McpResourceTemplateReadRequest,
+) -> Result<serde_json::Value, serde_json::Value> {
+    println!(
+        "Reading MCP resource template: {} from server {}",
+        request.uri_template, request.server_id
+    );
+
+    let manager = mcp_manager().await?;
+
+    manager
+        .read_resource_template(request)
+        .await
+        .map(|response| {
+            serde_json::to_value(response).map_err(|e| {
+                serde_json::json!({
+                    "code": -32000,
+                    "message": format!("Failed to serialize response: {}", e)
+                })
+            })
+        })
+        .map_err(|e| {
+            serde_json::json!({
+                "code": -32000,
+                "message": format!("Resource read failed: {}", e)
+            })
+        })?
+}
+
+/// Subscribe to a resource; updates arrive as `mcp-resource-updated` events
+#[tauri::command]
+pub async fn mcp_subscribe_resource(
+    server_id: String,
+    uri: String,
+) -> Result<(), serde_json::Value> {
+    let manager = mcp_manager().await?;
+
+    manager
+        .subscribe_resource(&server_id, &uri)
+        .await
+        .map_err(|e| {
+            serde_json::json!({
+                "code": -32000,
+                "message": format!("Failed to subscribe to resource: {}", e)
+            })
+        })
+}
+
+#[tauri::command]
+pub async fn mcp_unsubscribe_resource(
+    server_id: String,
+    uri: String,
+) -> Result<(), serde_json::Value> {
+    let manager = mcp_manager().await?;
+
+    manager
+        .unsubscribe_resource(&server_id, &uri)
+        .await
+        .map_err(|e| {
+            serde_json::json!({
+                "code": -32000,
+                "message": format!("Failed to unsubscribe from resource: {}", e)
+            })
+        })
+}
+
 #[tauri::command]
 pub async fn mcp_get_prompt(
     request: McpPromptGetRequest,
@@ -2266,15 +2793,9 @@ pub async fn mcp_get_prompt(
         request.name, request.server_id
     );
 
-    let manager = MCP_MANAGER.lock().await;
-    let manager_ref = manager.as_ref().ok_or_else(|| {
-        serde_json::json!({
-            "code": -32000,
-            "message": "MCP manager not initialized. Call mcp_start_servers first."
-        })
-    })?;
+    let manager = mcp_manager().await?;
 
-    manager_ref
+    manager
         .get_prompt(request)
         .await
         .map(|response| {
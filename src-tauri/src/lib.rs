@@ -1,8 +1,27 @@
+use sqlx::{Pool, Sqlite};
 use tauri::{Emitter, Manager, RunEvent};
-use tauri_plugin_sql::{Migration, MigrationKind};
+use tauri_plugin_sql::{DbInstances, DbPool, Migration, MigrationKind};
+mod files;
+mod git;
 mod handlers;
 mod mcp;
 
+/// Database shared with the frontend through tauri-plugin-sql
+pub(crate) const DB_URL: &str = "sqlite:data.db";
+
+/// Get the sqlite pool opened by the frontend through tauri-plugin-sql
+pub(crate) async fn db_pool(app: &tauri::AppHandle) -> Result<Pool<Sqlite>, String> {
+    let instances = app
+        .try_state::<DbInstances>()
+        .ok_or_else(|| "SQL plugin is not initialized".to_string())?;
+    let instances = instances.0.read().await;
+    let db = instances
+        .get(DB_URL)
+        .ok_or_else(|| format!("Database {} is not loaded", DB_URL))?;
+    let DbPool::Sqlite(pool) = db;
+    Ok(pool.clone())
+}
+
 fn get_migrations() -> Vec<Migration> {
     vec![
         Migration {
@@ -29,6 +48,18 @@ fn get_migrations() -> Vec<Migration> {
             sql: include_str!("../migrations/004_image.sql"),
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 5,
+            description: "create_trash_table",
+            sql: include_str!("../migrations/005_trash.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 6,
+            description: "add_agent_worktree",
+            sql: include_str!("../migrations/006_agent_worktree.sql"),
+            kind: MigrationKind::Up,
+        },
     ]
 }
 
@@ -38,7 +69,7 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(
             tauri_plugin_sql::Builder::new()
-                .add_migrations("sqlite:data.db", get_migrations())
+                .add_migrations(DB_URL, get_migrations())
                 .build(),
         )
         .plugin(tauri_plugin_dialog::init())
@@ -46,6 +77,15 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .register_asynchronous_uri_scheme_protocol(
+            files::chunked::FILE_PROTOCOL,
+            |ctx, request, responder| {
+                let app = ctx.app_handle().clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    responder.respond(files::chunked::handle_file_protocol(&app, &request));
+                });
+            },
+        )
         .setup(|app| {
             app.manage(handlers::PendingAppUpdate::default());
             #[cfg(debug_assertions)] // only include this code on debug builds
@@ -72,6 +112,11 @@ pub fn run() {
             handlers::acp_stop_listening,
             handlers::acp_dispose,
             handlers::read_file_by_range,
+            handlers::read_file_bytes,
+            handlers::get_file_line_count,
+            handlers::sniff_file,
+            handlers::allow_file_protocol_root,
+            handlers::revoke_file_protocol_root,
             handlers::terminal_create_session,
             handlers::terminal_send_input,
             handlers::terminal_resize,
@@ -87,17 +132,63 @@ pub fn run() {
             handlers::read_directory,
             handlers::read_file,
             handlers::write_file,
+            handlers::read_text_file,
+            handlers::write_text_file,
+            handlers::open_document,
+            handlers::update_document_buffer,
+            handlers::save_document,
+            handlers::close_document,
             handlers::read_binary_file,
             handlers::write_binary_file,
+            handlers::get_file_stamp,
+            handlers::apply_patch,
             handlers::move_file,
+            handlers::copy_paths,
+            handlers::move_paths,
+            handlers::cancel_file_transfer,
             handlers::rename_file,
             handlers::delete_file,
+            handlers::list_trash,
+            handlers::restore_from_trash,
+            handlers::empty_trash,
             handlers::watch_file,
             handlers::unwatch_file,
             handlers::stop_watching,
             handlers::glob_files,
+            handlers::search_in_files,
+            handlers::cancel_search,
+            handlers::replace_in_files,
             handlers::is_git_repo,
             handlers::get_git_diff_all,
+            handlers::git_status,
+            handlers::git_diff,
+            handlers::git_stage,
+            handlers::git_unstage,
+            handlers::git_stage_hunks,
+            handlers::git_unstage_hunks,
+            handlers::git_discard_hunks,
+            handlers::git_discard_paths,
+            handlers::git_commit,
+            handlers::git_stash_push,
+            handlers::git_stash_pop,
+            handlers::git_list_branches,
+            handlers::git_create_branch,
+            handlers::git_checkout,
+            handlers::git_merge,
+            handlers::git_list_tags,
+            handlers::git_log,
+            handlers::git_show_commit,
+            handlers::git_blame,
+            handlers::git_file_at_revision,
+            handlers::create_checkpoint,
+            handlers::list_checkpoints,
+            handlers::restore_checkpoint,
+            handlers::delete_checkpoint,
+            handlers::create_task_worktree,
+            handlers::get_task_worktree,
+            handlers::merge_task_worktree,
+            handlers::cherry_pick_task_worktree,
+            handlers::discard_task_worktree,
             // Package management
             handlers::upgrade_package,
             handlers::check_for_updates,
@@ -106,13 +197,19 @@ pub fn run() {
             // MCP commands
             handlers::mcp_start_servers,
             handlers::mcp_stop_server,
+            handlers::mcp_refresh_server,
             handlers::mcp_list_servers,
             handlers::mcp_get_server_logs,
+            handlers::mcp_reload_log_settings,
             handlers::mcp_list_tools,
             handlers::mcp_call_tool,
             handlers::mcp_list_resources,
+            handlers::mcp_list_resource_templates,
             handlers::mcp_list_prompts,
             handlers::mcp_read_resource,
+            handlers::mcp_read_resource_template,
+            handlers::mcp_subscribe_resource,
+            handlers::mcp_unsubscribe_resource,
             handlers::mcp_get_prompt,
         ])
         .build(tauri::generate_context!())
@@ -0,0 +1,66 @@
+use rmcp::model::{ClientInfo, Implementation, ResourceUpdatedNotificationParam};
+use rmcp::service::NotificationContext;
+use rmcp::{ClientHandler, RoleClient};
+
+use crate::mcp::manager::McpManager;
+use crate::mcp::types::McpCatalog;
+
+/// Client side of a server session: identifies the app during initialization
+/// and keeps the manager's cached catalogs in sync when the server reports
+/// that its tools, resources or prompts changed. Updates of subscribed
+/// resources are forwarded to the frontend.
+#[derive(Clone)]
+pub struct McpClient {
+    server_id: String,
+    manager: McpManager,
+}
+
+impl McpClient {
+    pub fn new(server_id: &str, manager: McpManager) -> Self {
+        Self {
+            server_id: server_id.to_string(),
+            manager,
+        }
+    }
+}
+
+impl ClientHandler for McpClient {
+    async fn on_tool_list_changed(&self, context: NotificationContext<RoleClient>) {
+        self.manager
+            .catalog_changed(&self.server_id, &context.peer, McpCatalog::Tools)
+            .await;
+    }
+
+    async fn on_resource_list_changed(&self, context: NotificationContext<RoleClient>) {
+        self.manager
+            .catalog_changed(&self.server_id, &context.peer, McpCatalog::Resources)
+            .await;
+    }
+
+    async fn on_resource_updated(
+        &self,
+        params: ResourceUpdatedNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        self.manager
+            .resource_updated(&self.server_id, params.uri)
+            .await;
+    }
+
+    async fn on_prompt_list_changed(&self, context: NotificationContext<RoleClient>) {
+        self.manager
+            .catalog_changed(&self.server_id, &context.peer, McpCatalog::Prompts)
+            .await;
+    }
+
+    fn get_info(&self) -> ClientInfo {
+        ClientInfo {
+            client_info: Implementation {
+                name: "raven".into(),
+                version: env!("CARGO_PKG_VERSION").into(),
+                ..Implementation::default()
+            },
+            ..ClientInfo::default()
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use serde::Deserialize;
+use sqlx::Row;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use tauri::Manager;
+
+use crate::mcp::types::{McpLogEntry, McpLogLevel};
+
+const LOGS_DIR_NAME: &str = "mcp-logs";
+/// Entries kept in memory per server
+const MAX_LOG_ENTRIES: usize = 1000;
+/// Size at which a server's log file is rotated
+const MAX_LOG_FILE_BYTES: u64 = 1024 * 1024;
+/// Rotated files kept per server, `<id>.1.log` being the newest
+const MAX_ROTATED_FILES: usize = 3;
+
+/// Stored in the settings table under `mcp_logs::default`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogSettings {
+    #[serde(default)]
+    persist: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpLogFilter {
+    /// Only entries with one of these levels
+    pub levels: Option<Vec<McpLogLevel>>,
+    /// Only entries at or after this time, in milliseconds since the Unix epoch
+    pub since: Option<i64>,
+    /// Only entries at or before this time
+    pub until: Option<i64>,
+    /// Keep only the newest `limit` matching entries
+    pub limit: Option<usize>,
+}
+
+impl McpLogFilter {
+    fn matches(&self, entry: &McpLogEntry) -> bool {
+        self.levels
+            .as_ref()
+            .is_none_or(|levels| levels.contains(&entry.level))
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp <= until)
+    }
+}
+
+#[derive(Default)]
+struct LogStoreInner {
+    entries: HashMap<String, VecDeque<McpLogEntry>>,
+    /// Number of newest entries per server appended while persistence was
+    /// off, written out once it is turned on
+    unpersisted: HashMap<String, usize>,
+    /// Whether entries are also persisted, so queries read them from disk
+    persisting: bool,
+}
+
+/// Work for the thread that owns the log files
+enum WriterCommand {
+    /// Persist to this directory from now on, or stop with `None`
+    SetDirectory(Option<PathBuf>),
+    Append(String, McpLogEntry),
+    /// Send back every persisted entry of a server
+    Read(String, mpsc::Sender<Vec<McpLogEntry>>),
+}
+
+/// Server logs keyed by server id. Entries outlive the connections that
+/// wrote them, so logs from before a server registered, from failed starts
+/// and from earlier runs stay available. With persistence on they are also
+/// appended to `<app_data>/mcp-logs/<id>.log`, rotated by size. File I/O
+/// happens on a writer thread of its own so logging never blocks the caller.
+pub struct McpLogStore {
+    inner: Mutex<LogStoreInner>,
+    writer: mpsc::Sender<WriterCommand>,
+}
+
+impl Default for McpLogStore {
+    fn default() -> Self {
+        let (writer, commands) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name("mcp-log-writer".to_string())
+            .spawn(move || LogWriter::default().run(commands));
+        if let Err(e) = spawned {
+            eprintln!("[MCP] Failed to start log writer: {}", e);
+        }
+        Self {
+            inner: Mutex::default(),
+            writer,
+        }
+    }
+}
+
+/// Directory to persist logs in when enabled in settings
+pub async fn log_dir_from_settings(app: &tauri::AppHandle) -> Result<Option<PathBuf>, String> {
+    let pool = crate::db_pool(app).await?;
+    let row = sqlx::query("SELECT value FROM settings WHERE id = 'mcp_logs::default'")
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| format!("Failed to read MCP log settings: {}", e))?;
+    let settings = match row {
+        Some(row) => {
+            let value: String = row
+                .try_get("value")
+                .map_err(|e| format!("Failed to read MCP log settings: {}", e))?;
+            serde_json::from_str::<LogSettings>(&value)
+                .map_err(|e| format!("Invalid MCP log settings: {}", e))?
+        }
+        None => LogSettings::default(),
+    };
+    if !settings.persist {
+        return Ok(None);
+    }
+    app.path()
+        .app_data_dir()
+        .map(|dir| Some(dir.join(LOGS_DIR_NAME)))
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))
+}
+
+/// Server ids are free-form. Lowercase letters, digits and `-` are kept and
+/// every other byte is written as `_xx`, so distinct ids never share a file,
+/// not even on case-insensitive file systems.
+fn file_stem(server_id: &str) -> String {
+    let mut stem = String::with_capacity(server_id.len());
+    for byte in server_id.bytes() {
+        if byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'-' {
+            stem.push(byte as char);
+        } else {
+            stem.push_str(&format!("_{:02x}", byte));
+        }
+    }
+    stem
+}
+
+fn file_name(server_id: &str, generation: usize) -> String {
+    let id = file_stem(server_id);
+    match generation {
+        0 => format!("{}.log", id),
+        n => format!("{}.{}.log", id, n),
+    }
+}
+
+/// Shift `<id>.log` to `<id>.1.log` and so on, dropping the oldest
+fn rotate(dir: &Path, server_id: &str) -> std::io::Result<()> {
+    let _ = fs::remove_file(dir.join(file_name(server_id, MAX_ROTATED_FILES)));
+    for generation in (0..MAX_ROTATED_FILES).rev() {
+        let from = dir.join(file_name(server_id, generation));
+        if from.exists() {
+            fs::rename(&from, dir.join(file_name(server_id, generation + 1)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Every persisted entry of a server, oldest first. Unreadable lines are
+/// skipped.
+fn read_persisted(dir: &Path, server_id: &str) -> Vec<McpLogEntry> {
+    let mut entries = Vec::new();
+    for generation in (0..=MAX_ROTATED_FILES).rev() {
+        let Ok(file) = File::open(dir.join(file_name(server_id, generation))) else {
+            continue;
+        };
+        entries.extend(
+            BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter_map(|line| serde_json::from_str(&line).ok()),
+        );
+    }
+    entries
+}
+
+/// Owner of the log files, fed by `McpLogStore` through a channel
+#[derive(Default)]
+struct LogWriter {
+    /// Directory logs are appended to, `None` when persistence is off
+    dir: Option<PathBuf>,
+    files: HashMap<String, File>,
+}
+
+impl LogWriter {
+    /// Handle commands until the store is dropped
+    fn run(mut self, commands: mpsc::Receiver<WriterCommand>) {
+        for command in commands {
+            match command {
+                WriterCommand::SetDirectory(dir) => {
+                    if self.dir != dir {
+                        self.files.clear();
+                        self.dir = dir;
+                    }
+                }
+                WriterCommand::Append(server_id, entry) => {
+                    if let Err(e) = self.persist(&server_id, &entry) {
+                        eprintln!("[MCP] Failed to persist log for {}: {}", server_id, e);
+                    }
+                }
+                WriterCommand::Read(server_id, reply) => {
+                    let entries = match &self.dir {
+                        Some(dir) => read_persisted(dir, &server_id),
+                        None => Vec::new(),
+                    };
+                    let _ = reply.send(entries);
+                }
+            }
+        }
+    }
+
+    fn persist(&mut self, server_id: &str, entry: &McpLogEntry) -> std::io::Result<()> {
+        let Some(dir) = self.dir.clone() else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let size = match self.files.get(server_id) {
+            Some(file) => file.metadata()?.len(),
+            None => fs::metadata(dir.join(file_name(server_id, 0)))
+                .map(|m| m.len())
+                .unwrap_or(0),
+        };
+        if size > 0 && size + line.len() as u64 > MAX_LOG_FILE_BYTES {
+            self.files.remove(server_id);
+            rotate(&dir, server_id)?;
+        }
+
+        let file = match self.files.entry(server_id.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                fs::create_dir_all(&dir)?;
+                entry.insert(
+                    OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(dir.join(file_name(server_id, 0)))?,
+                )
+            }
+        };
+        file.write_all(line.as_bytes())
+    }
+}
+
+impl McpLogStore {
+    /// Turn persistence on with a directory, or off with `None`
+    pub fn set_directory(&self, dir: Option<PathBuf>) {
+        // Sent under the lock so appends reach the writer in the same order
+        let mut inner = self.inner.lock().unwrap();
+        inner.persisting = dir.is_some();
+        let _ = self.writer.send(WriterCommand::SetDirectory(dir));
+        if !inner.persisting {
+            return;
+        }
+        // Queries read from disk from now on, so entries logged while
+        // persistence was off have to get there too
+        let LogStoreInner {
+            entries,
+            unpersisted,
+            ..
+        } = &mut *inner;
+        for (server_id, count) in unpersisted.drain() {
+            let Some(buffer) = entries.get(&server_id) else {
+                continue;
+            };
+            for entry in buffer.iter().skip(buffer.len().saturating_sub(count)) {
+                let _ = self
+                    .writer
+                    .send(WriterCommand::Append(server_id.clone(), entry.clone()));
+            }
+        }
+    }
+
+    pub fn append(&self, server_id: &str, entry: McpLogEntry) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.persisting {
+            let _ = self
+                .writer
+                .send(WriterCommand::Append(server_id.to_string(), entry.clone()));
+        } else {
+            let count = inner.unpersisted.entry(server_id.to_string()).or_default();
+            *count = (*count + 1).min(MAX_LOG_ENTRIES);
+        }
+        let entries = inner.entries.entry(server_id.to_string()).or_default();
+        if entries.len() >= MAX_LOG_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Entries of a server matching `filter`, oldest first. Reads the full
+    /// persisted history when persistence is on, the in-memory buffer
+    /// otherwise. Blocks until the writer has persisted earlier entries.
+    pub fn query(&self, server_id: &str, filter: &McpLogFilter) -> Vec<McpLogEntry> {
+        let in_memory = {
+            let inner = self.inner.lock().unwrap();
+            (!inner.persisting).then(|| {
+                inner
+                    .entries
+                    .get(server_id)
+                    .map(|entries| entries.iter().cloned().collect())
+                    .unwrap_or_default()
+            })
+        };
+        let mut entries: Vec<McpLogEntry> = match in_memory {
+            Some(entries) => entries,
+            None => self.read_persisted(server_id),
+        };
+        entries.retain(|entry| filter.matches(entry));
+        if let Some(limit) = filter.limit {
+            let excess = entries.len().saturating_sub(limit);
+            entries.drain(..excess);
+        }
+        entries
+    }
+
+    /// Persisted entries of a server, read on the writer thread after
+    /// everything queued before
+    fn read_persisted(&self, server_id: &str) -> Vec<McpLogEntry> {
+        let (reply, entries) = mpsc::channel();
+        if self
+            .writer
+            .send(WriterCommand::Read(server_id.to_string(), reply))
+            .is_err()
+        {
+            return Vec::new();
+        }
+        entries.recv().unwrap_or_default()
+    }
+}
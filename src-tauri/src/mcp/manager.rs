@@ -1,31 +1,44 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tauri::Emitter;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::process::ExitStatus;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::task::JoinHandle;
 
 use crate::mcp::{
-    transport::{McpTransport, create_transport},
+    client::McpClient,
+    logs::{McpLogFilter, McpLogStore},
+    sse::SseClientTransport,
+    transport::{McpTransport, StdioProcess, StdioTransport, create_transport},
+    uri_template,
     types::{
         McpServerConfig, McpTool, McpToolCallRequest, McpToolCallResponse,
-        McpResult, McpError, McpContentItem, McpLogEntry, McpLogLevel,
-        McpResource, McpResourceReadRequest, McpResourceReadResponse,
-        McpResourceContent,
+        McpResult, McpError, McpContentItem, McpLogEntry, McpLogLevel, McpServerState, McpCatalog,
+        McpResource, McpResourceReadRequest, McpResourceReadResponse, McpResourceTemplate,
+        McpResourceTemplateReadRequest, McpResourceContent,
         McpPrompt, McpPromptArgument, McpPromptGetRequest, McpPromptGetResponse,
         McpPromptMessage, McpPromptContent,
     },
 };
 use rmcp::{
     RoleClient,
-    service::{ClientInitializeError, RunningService, serve_client},
+    model::{ClientRequest, PaginatedRequestParams},
+    service::{ClientInitializeError, Peer, RunningService, ServiceError, serve_client},
 };
-use rmcp::transport::child_process::TokioChildProcess;
 use rmcp::transport::streamable_http_client::StreamableHttpClientTransport;
 
+type McpService = RunningService<RoleClient, McpClient>;
+
 #[derive(Clone)]
 pub struct McpManager {
     // We store the tools directly since we can't store dyn Service
     servers: Arc<RwLock<HashMap<String, ServerInfo>>>,
+    // Kept apart from `servers` so logs survive restarts and cover startup
+    logs: Arc<McpLogStore>,
     app_handle: tauri::AppHandle,
 }
 
@@ -33,18 +46,49 @@ struct ServerInfo {
     config: McpServerConfig,
     tools: Vec<McpTool>,
     resources: Vec<McpResource>,
+    resource_templates: Vec<McpResourceTemplate>,
     prompts: Vec<McpPrompt>,
-    is_connected: bool,
-    service: Arc<RunningService<RoleClient, ()>>,
-    logs: VecDeque<McpLogEntry>,
+    /// URIs of resources the session is subscribed to
+    subscriptions: HashSet<String>,
+    state: McpServerState,
+    service: Arc<McpService>,
+    monitor: JoinHandle<()>,
+}
+
+impl Drop for ServerInfo {
+    fn drop(&mut self) {
+        self.monitor.abort();
+    }
 }
 
-const MAX_LOG_ENTRIES: usize = 1000;
+/// An initialized session and the catalog fetched from it
+struct Connection {
+    service: McpService,
+    tools: Vec<McpTool>,
+    resources: Vec<McpResource>,
+    resource_templates: Vec<McpResourceTemplate>,
+    prompts: Vec<McpPrompt>,
+}
+
+// Health monitoring: transport closure is noticed within a second, pings
+// catch servers that are alive but no longer answering
+const CLOSE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_MISSED_PINGS: u32 = 3;
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+const MAX_RECONNECT_ATTEMPTS: u32 = 8;
+// Safety cap on pages fetched for one list, against servers that never stop
+const MAX_LIST_PAGES: usize = 100;
+// How long a stdio server may take to exit after its stdin is closed
+const PROCESS_STOP_GRACE: Duration = Duration::from_secs(3);
 
 impl McpManager {
     pub fn new(app_handle: tauri::AppHandle) -> Self {
         Self {
             servers: Arc::new(RwLock::new(HashMap::new())),
+            logs: Arc::new(McpLogStore::default()),
             app_handle,
         }
     }
@@ -94,41 +138,58 @@ impl McpManager {
     ) -> McpResult<Vec<McpTool>> {
         // Log server starting
         self.add_log_entry(&server_id, McpLogLevel::Info, format!("Starting MCP server: {:?}", config));
-        
-        // Emit connection starting event
-        let _ = self.app_handle.emit("mcp-server-status", serde_json::json!({
-            "serverId": server_id,
-            "status": "starting",
-        }));
+        self.emit_state(&server_id, McpServerState::Starting, None);
+
+        let connection = self.connect(&server_id, &config).await
+            .inspect_err(|e| self.emit_state(&server_id, McpServerState::Failed, Some(e.to_string())))?;
+        let tools = connection.tools.clone();
+
+        // A restart keeps the resources the previous session was subscribed to
+        let previous = self.servers.read().await.get(&server_id)
+            .map(|info| info.subscriptions.clone())
+            .unwrap_or_default();
+        let subscriptions = self.resubscribe(&server_id, &connection.service, previous).await;
 
-        // Create transport
-        let transport = create_transport(&config).await
+        // Store the service, tools, resources, and prompts. Replacing an
+        // existing entry drops it, which stops its monitor.
+        let mut servers = self.servers.write().await;
+        servers.insert(server_id.to_string(), ServerInfo {
+            config,
+            tools: connection.tools,
+            resources: connection.resources,
+            resource_templates: connection.resource_templates,
+            prompts: connection.prompts,
+            subscriptions,
+            state: McpServerState::Connected,
+            service: Arc::new(connection.service),
+            monitor: self.spawn_monitor(server_id.clone()),
+        });
+        if let Some(info) = servers.get(&server_id) {
+            self.emit_connected(&server_id, info);
+        }
+
+        Ok(tools)
+    }
+
+    /// Create the transport, initialize the session and fetch the catalog
+    async fn connect(&self, server_id: &str, config: &McpServerConfig) -> McpResult<Connection> {
+        let transport = create_transport(config).await
             .map_err(|e| {
-                self.add_log_entry(&server_id, McpLogLevel::Error, format!("Failed to create transport: {}", e));
-                let _ = self.app_handle.emit("mcp-server-status", serde_json::json!({
-                    "serverId": server_id,
-                    "status": "failed",
-                    "error": e.to_string(),
-                }));
+                self.add_log_entry(server_id, McpLogLevel::Error, format!("Failed to create transport: {}", e));
                 e
             })?;
 
-        // let _client_info = ClientInfo {
-        //     protocol_version: Default::default(),
-        //     capabilities: ClientCapabilities::default(),
-        //     client_info: Implementation {
-        //         name: "raven".into(),
-        //         version: env!("CARGO_PKG_VERSION").into(),
-        //     },
-        // };
-
         // Handle each transport type and get the service
         let service = match transport {
-            McpTransport::Stdio(t) => {
-                self.connect_stdio(t, &server_id).await?
+            McpTransport::Stdio(t, process) => {
+                self.watch_process(server_id, process);
+                self.connect_stdio(t, server_id).await?
             }
             McpTransport::Http(t) => {
-                self.connect_http(t, &server_id).await?
+                self.connect_http(t, server_id).await?
+            }
+            McpTransport::Sse(t) => {
+                self.connect_sse(t, server_id).await?
             }
         };
 
@@ -140,114 +201,353 @@ impl McpManager {
         let has_resources = capabilities.map(|c| c.resources.is_some()).unwrap_or(false);
         let has_prompts = capabilities.map(|c| c.prompts.is_some()).unwrap_or(false);
 
-        self.add_log_entry(&server_id, McpLogLevel::Info, 
+        self.add_log_entry(server_id, McpLogLevel::Info, 
             format!("Server capabilities - tools: {}, resources: {}, prompts: {}", has_tools, has_resources, has_prompts));
 
         // Extract tools, resources, and prompts based on capabilities
         let tools = if has_tools {
-            self.extract_tools(&service, &server_id).await?
+            self.extract_tools(&service, server_id).await?
         } else {
             Vec::new()
         };
 
         let resources = if has_resources {
-            self.extract_resources(&service, &server_id).await?
+            self.extract_resources(&service, server_id).await?
         } else {
             Vec::new()
         };
 
-        let prompts = if has_prompts {
-            self.extract_prompts(&service, &server_id).await?
+        // Templates are optional even for servers with resources; failures are logged
+        let resource_templates = if has_resources {
+            self.extract_resource_templates(&service, server_id).await.unwrap_or_default()
         } else {
             Vec::new()
         };
 
-        self.add_log_entry(&server_id, McpLogLevel::Info, format!("Successfully connected. Found {} tools, {} resources, {} prompts", tools.len(), resources.len(), prompts.len()));
-
-        // Store the service, tools, resources, and prompts
-        let mut servers = self.servers.write().await;
-        servers.insert(server_id.to_string(), ServerInfo {
-            config,
-            tools: tools.clone(),
-            resources: resources.clone(),
-            prompts: prompts.clone(),
-            is_connected: true,
-            service: Arc::new(service),
-            logs: VecDeque::new(),
-        });
+        let prompts = if has_prompts {
+            self.extract_prompts(&service, server_id).await?
+        } else {
+            Vec::new()
+        };
 
-        // Emit success with resources and prompts
-        let _ = self.app_handle.emit("mcp-server-status", serde_json::json!({
-            "serverId": server_id,
-            "status": "connected",
-            "tools": tools,
-            "resources": resources,
-            "prompts": prompts,
-        }));
+        self.add_log_entry(server_id, McpLogLevel::Info, format!("Successfully connected. Found {} tools, {} resources, {} resource templates, {} prompts", tools.len(), resources.len(), resource_templates.len(), prompts.len()));
 
-        Ok(tools)
+        Ok(Connection {
+            service,
+            tools,
+            resources,
+            resource_templates,
+            prompts,
+        })
     }
 
     /// Connect via stdio transport
     async fn connect_stdio(
         &self,
-        transport: TokioChildProcess,
+        transport: StdioTransport,
         server_id: &str,
-    ) -> McpResult<RunningService<RoleClient, ()>> {
+    ) -> McpResult<McpService> {
         self.add_log_entry(server_id, McpLogLevel::Info, "Connecting via stdio transport...".to_string());
 
-        let service = serve_client((), transport).await
+        let service = serve_client(McpClient::new(server_id, self.clone()), transport).await
             .map_err(|e: ClientInitializeError| {
                 self.add_log_entry(server_id, McpLogLevel::Error, format!("Stdio connection failed: {}", e));
-                let _ = self.app_handle.emit("mcp-server-status", serde_json::json!({
-                    "serverId": server_id,
-                    "status": "failed",
-                    "error": e.to_string(),
-                }));
                 McpError::ProtocolError(format!("Failed to connect stdio: {}", e))
             })?;
 
         Ok(service)
     }
 
+    /// Stream a stdio server's stderr into its logs and log how the process
+    /// ends. The child is killed if it outlives its transport.
+    fn watch_process(&self, server_id: &str, process: Box<StdioProcess>) {
+        let StdioProcess { mut child, stderr, stop } = *process;
+
+        if let Some(stderr) = stderr {
+            let manager = self.clone();
+            let server_id = server_id.to_string();
+            tokio::spawn(async move {
+                let mut reader = BufReader::new(stderr);
+                let mut line = Vec::new();
+                // Read raw bytes so output that isn't valid UTF-8 doesn't end the stream
+                while matches!(reader.read_until(b'\n', &mut line).await, Ok(n) if n > 0) {
+                    let text = String::from_utf8_lossy(&line);
+                    let text = text.trim_end_matches(['\r', '\n']);
+                    if !text.is_empty() {
+                        manager.add_log_entry(&server_id, McpLogLevel::Stderr, text.to_string());
+                    }
+                    line.clear();
+                }
+            });
+        }
+
+        let manager = self.clone();
+        let server_id = server_id.to_string();
+        tokio::spawn(async move {
+            let (status, stopped) = tokio::select! {
+                biased;
+                status = child.wait() => (status, false),
+                // Closing stdin asks the server to exit; kill it if it doesn't
+                _ = stop => match tokio::time::timeout(PROCESS_STOP_GRACE, child.wait()).await {
+                    Ok(status) => (status, false),
+                    Err(_) => {
+                        let _ = child.start_kill();
+                        (child.wait().await, true)
+                    }
+                },
+            };
+            match status {
+                Ok(status) => {
+                    let level = if status.success() || stopped {
+                        McpLogLevel::Info
+                    } else {
+                        McpLogLevel::Error
+                    };
+                    manager.add_log_entry(&server_id, level, describe_exit(status, stopped));
+                }
+                Err(e) => {
+                    manager.add_log_entry(&server_id, McpLogLevel::Error, format!("Failed to wait for process: {}", e));
+                }
+            }
+        });
+    }
+
     /// Connect via HTTP transport (streamable HTTP)
     async fn connect_http(
         &self,
         transport: StreamableHttpClientTransport<reqwest::Client>,
         server_id: &str,
-    ) -> McpResult<RunningService<RoleClient, ()>> {
+    ) -> McpResult<McpService> {
         self.add_log_entry(server_id, McpLogLevel::Info, "Connecting via HTTP transport...".to_string());
 
-        let service = serve_client((), transport).await
+        let service = serve_client(McpClient::new(server_id, self.clone()), transport).await
             .map_err(|e: ClientInitializeError| {
                 self.add_log_entry(server_id, McpLogLevel::Error, format!("HTTP connection failed: {}", e));
-                let _ = self.app_handle.emit("mcp-server-status", serde_json::json!({
-                    "serverId": server_id,
-                    "status": "failed",
-                    "error": e.to_string(),
-                }));
                 McpError::ProtocolError(format!("Failed to connect HTTP: {}", e))
             })?;
 
         Ok(service)
     }
 
+    /// Connect via SSE transport (legacy HTTP+SSE)
+    async fn connect_sse(
+        &self,
+        transport: SseClientTransport,
+        server_id: &str,
+    ) -> McpResult<McpService> {
+        self.add_log_entry(server_id, McpLogLevel::Info, "Connecting via SSE transport...".to_string());
+
+        let service = serve_client(McpClient::new(server_id, self.clone()), transport).await
+            .map_err(|e: ClientInitializeError| {
+                self.add_log_entry(server_id, McpLogLevel::Error, format!("SSE connection failed: {}", e));
+                McpError::ProtocolError(format!("Failed to connect SSE: {}", e))
+            })?;
+
+        Ok(service)
+    }
+
+    fn spawn_monitor(&self, server_id: String) -> JoinHandle<()> {
+        let manager = self.clone();
+        tokio::spawn(async move { manager.monitor(server_id).await })
+    }
+
+    /// Watch a connected server until it is stopped or given up on: a closed
+    /// transport triggers a reconnect right away, missed pings first mark the
+    /// server degraded and reconnect after `MAX_MISSED_PINGS` in a row
+    async fn monitor(&self, server_id: String) {
+        let mut last_ping = Instant::now();
+        let mut missed_pings = 0;
+
+        loop {
+            tokio::time::sleep(CLOSE_CHECK_INTERVAL).await;
+            let service = match self.servers.read().await.get(&server_id) {
+                Some(info) => info.service.clone(),
+                None => return,
+            };
+
+            if service.is_transport_closed() {
+                self.add_log_entry(&server_id, McpLogLevel::Error, "Connection closed".to_string());
+            } else if last_ping.elapsed() < PING_INTERVAL {
+                continue;
+            } else {
+                last_ping = Instant::now();
+                match Self::ping(&service).await {
+                    Ok(()) => {
+                        if missed_pings > 0 {
+                            missed_pings = 0;
+                            self.add_log_entry(&server_id, McpLogLevel::Info, "Server is responding again".to_string());
+                            self.set_state(&server_id, McpServerState::Connected, None).await;
+                        }
+                        continue;
+                    }
+                    Err(e) => {
+                        missed_pings += 1;
+                        self.add_log_entry(&server_id, McpLogLevel::Error,
+                            format!("Health check failed ({}/{}): {}", missed_pings, MAX_MISSED_PINGS, e));
+                        if missed_pings < MAX_MISSED_PINGS {
+                            self.set_state(&server_id, McpServerState::Degraded, Some(e)).await;
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            drop(service);
+            if !self.reconnect(&server_id).await {
+                return;
+            }
+            last_ping = Instant::now();
+            missed_pings = 0;
+        }
+    }
+
+    /// Ping the server. Any response counts, including an error from servers
+    /// that don't implement ping.
+    async fn ping(service: &McpService) -> Result<(), String> {
+        let request = ClientRequest::PingRequest(Default::default());
+        match tokio::time::timeout(PING_TIMEOUT, service.send_request(request)).await {
+            Ok(Ok(_)) | Ok(Err(ServiceError::McpError(_))) => Ok(()),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err(format!("No response within {}s", PING_TIMEOUT.as_secs())),
+        }
+    }
+
+    /// Reconnect with exponential backoff, swapping the new session in on
+    /// success. Returns false when the server was stopped meanwhile or every
+    /// attempt failed, leaving it `Failed`.
+    async fn reconnect(&self, server_id: &str) -> bool {
+        let config = match self.servers.read().await.get(server_id) {
+            Some(info) => info.config.clone(),
+            None => return false,
+        };
+        self.set_state(server_id, McpServerState::Reconnecting, None).await;
+
+        let mut delay = RECONNECT_BASE_DELAY;
+        let mut last_error = String::new();
+        for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+            self.add_log_entry(server_id, McpLogLevel::Info,
+                format!("Reconnecting (attempt {}/{})...", attempt, MAX_RECONNECT_ATTEMPTS));
+
+            match self.connect(server_id, &config).await {
+                Ok(connection) => {
+                    let previous = match self.servers.read().await.get(server_id) {
+                        Some(info) => info.subscriptions.clone(),
+                        None => return false,
+                    };
+                    let subscriptions = self.resubscribe(server_id, &connection.service, previous).await;
+
+                    let mut servers = self.servers.write().await;
+                    let Some(info) = servers.get_mut(server_id) else {
+                        return false;
+                    };
+                    info.service = Arc::new(connection.service);
+                    info.subscriptions = subscriptions;
+                    info.tools = connection.tools;
+                    info.resources = connection.resources;
+                    info.resource_templates = connection.resource_templates;
+                    info.prompts = connection.prompts;
+                    info.state = McpServerState::Connected;
+                    self.emit_connected(server_id, info);
+                    return true;
+                }
+                Err(e) => {
+                    self.add_log_entry(server_id, McpLogLevel::Error,
+                        format!("Reconnect attempt {} failed: {}", attempt, e));
+                    last_error = e.to_string();
+                }
+            }
+
+            if attempt < MAX_RECONNECT_ATTEMPTS {
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+            }
+        }
+
+        self.add_log_entry(server_id, McpLogLevel::Error,
+            format!("Giving up after {} reconnect attempts", MAX_RECONNECT_ATTEMPTS));
+        self.set_state(server_id, McpServerState::Failed, Some(last_error)).await;
+        false
+    }
+
+    /// Record a state change of a running server and report it
+    async fn set_state(&self, server_id: &str, state: McpServerState, error: Option<String>) {
+        let mut servers = self.servers.write().await;
+        let Some(info) = servers.get_mut(server_id) else {
+            return;
+        };
+        info.state = state;
+        if state == McpServerState::Connected {
+            self.emit_connected(server_id, info);
+        } else {
+            self.emit_state(server_id, state, error);
+        }
+    }
+
+    fn emit_state(&self, server_id: &str, state: McpServerState, error: Option<String>) {
+        let _ = self.app_handle.emit("mcp-server-status", serde_json::json!({
+            "serverId": server_id,
+            "status": state,
+            "error": error,
+        }));
+    }
+
+    fn emit_connected(&self, server_id: &str, info: &ServerInfo) {
+        let _ = self.app_handle.emit("mcp-server-status", serde_json::json!({
+            "serverId": server_id,
+            "status": McpServerState::Connected,
+            "tools": info.tools,
+            "resources": info.resources,
+            "resourceTemplates": info.resource_templates,
+            "prompts": info.prompts,
+        }));
+    }
+
+    /// Collect every page of a list request by following `next_cursor`.
+    /// Stops after `MAX_LIST_PAGES` pages, or when the server repeats a
+    /// cursor, keeping what was fetched so far.
+    async fn list_all<T, F, Fut>(&self, server_id: &str, what: &str, mut fetch: F) -> McpResult<Vec<T>>
+    where
+        F: FnMut(Option<String>) -> Fut,
+        Fut: Future<Output = Result<(Vec<T>, Option<String>), ServiceError>>,
+    {
+        let mut items = Vec::new();
+        let mut cursor = None;
+        let mut seen = HashSet::new();
+        for _ in 0..MAX_LIST_PAGES {
+            let (page, next_cursor) = fetch(cursor).await
+                .map_err(|e| {
+                    self.add_log_entry(server_id, McpLogLevel::Error, format!("Failed to list {}: {}", what, e));
+                    McpError::ProtocolError(format!("Failed to list {}: {}", what, e))
+                })?;
+            items.extend(page);
+
+            match next_cursor {
+                None => return Ok(items),
+                Some(next) if !seen.insert(next.clone()) => {
+                    self.add_log_entry(server_id, McpLogLevel::Error,
+                        format!("Server repeated a {} cursor; keeping the first {} {}", what, items.len(), what));
+                    return Ok(items);
+                }
+                next => cursor = next,
+            }
+        }
+
+        self.add_log_entry(server_id, McpLogLevel::Error,
+            format!("Stopped listing {} after {} pages; keeping the first {}", what, MAX_LIST_PAGES, items.len()));
+        Ok(items)
+    }
+
     /// Extract tools from a service
     async fn extract_tools(
         &self,
-        service: &RunningService<RoleClient, ()>,
+        service: &Peer<RoleClient>,
         server_id: &str,
     ) -> McpResult<Vec<McpTool>> {        
         // List tools from the service
-        let tools_response = service.list_all_tools().await
-            .map_err(|e| {
-                let _ = self.app_handle.emit("mcp-server-status", serde_json::json!({
-                    "serverId": server_id,
-                    "status": "failed",
-                    "error": e.to_string(),
-                }));
-                McpError::ProtocolError(format!("Failed to list tools: {}", e))
-            })?;
+        let tools_response = self.list_all(server_id, "tools", |cursor| async move {
+            service.list_tools(Some(PaginatedRequestParams { meta: None, cursor })).await
+                .map(|page| (page.tools, page.next_cursor))
+        }).await?;
 
         println!("[MCP] Extracted {} tools for server: {}", tools_response.len(), server_id);
 
@@ -266,14 +566,13 @@ impl McpManager {
     /// Extract resources from a service
     async fn extract_resources(
         &self,
-        service: &RunningService<RoleClient, ()>,
+        service: &Peer<RoleClient>,
         server_id: &str,
     ) -> McpResult<Vec<McpResource>> {
-        let resources_response = service.list_all_resources().await
-            .map_err(|e| {
-                self.add_log_entry(server_id, McpLogLevel::Error, format!("Failed to list resources: {}", e));
-                McpError::ProtocolError(format!("Failed to list resources: {}", e))
-            })?;
+        let resources_response = self.list_all(server_id, "resources", |cursor| async move {
+            service.list_resources(Some(PaginatedRequestParams { meta: None, cursor })).await
+                .map(|page| (page.resources, page.next_cursor))
+        }).await?;
 
         println!("[MCP] Extracted {} resources for server: {}", resources_response.len(), server_id);
 
@@ -293,17 +592,43 @@ impl McpManager {
         Ok(resources)
     }
 
+    /// Extract resource templates from a service
+    async fn extract_resource_templates(
+        &self,
+        service: &Peer<RoleClient>,
+        server_id: &str,
+    ) -> McpResult<Vec<McpResourceTemplate>> {
+        let templates_response = self.list_all(server_id, "resource templates", |cursor| async move {
+            service.list_resource_templates(Some(PaginatedRequestParams { meta: None, cursor })).await
+                .map(|page| (page.resource_templates, page.next_cursor))
+        }).await?;
+
+        println!("[MCP] Extracted {} resource templates for server: {}", templates_response.len(), server_id);
+
+        let templates = templates_response
+            .into_iter()
+            .map(|t| McpResourceTemplate {
+                parameters: uri_template::parameters(&t.raw.uri_template),
+                uri_template: t.raw.uri_template,
+                name: t.raw.name,
+                description: t.raw.description,
+                mime_type: t.raw.mime_type,
+            })
+            .collect();
+
+        Ok(templates)
+    }
+
     /// Extract prompts from a service
     async fn extract_prompts(
         &self,
-        service: &RunningService<RoleClient, ()>,
+        service: &Peer<RoleClient>,
         server_id: &str,
     ) -> McpResult<Vec<McpPrompt>> {
-        let prompts_response = service.list_all_prompts().await
-            .map_err(|e| {
-                self.add_log_entry(server_id, McpLogLevel::Error, format!("Failed to list prompts: {}", e));
-                McpError::ProtocolError(format!("Failed to list prompts: {}", e))
-            })?;
+        let prompts_response = self.list_all(server_id, "prompts", |cursor| async move {
+            service.list_prompts(Some(PaginatedRequestParams { meta: None, cursor })).await
+                .map(|page| (page.prompts, page.next_cursor))
+        }).await?;
 
         println!("[MCP] Extracted {} prompts for server: {}", prompts_response.len(), server_id);
 
@@ -332,13 +657,83 @@ impl McpManager {
         Ok(prompts)
     }
 
+    /// Handle a list_changed notification by re-fetching that catalog
+    pub(crate) async fn catalog_changed(&self, server_id: &str, peer: &Peer<RoleClient>, catalog: McpCatalog) {
+        self.add_log_entry(server_id, McpLogLevel::Info, format!("Server reported that its {} changed", catalog));
+        if let Err(e) = self.refresh_catalogs(server_id, peer, &[catalog]).await {
+            self.add_log_entry(server_id, McpLogLevel::Error, format!("Failed to refresh {}: {}", catalog, e));
+        }
+    }
+
+    /// Re-fetch every catalog a server supports
+    pub async fn refresh_server(&self, server_id: &str) -> McpResult<()> {
+        let service = self.available_service(server_id).await?;
+
+        let capabilities = service.peer_info().map(|info| &info.capabilities);
+        let catalogs: Vec<McpCatalog> = [
+            (McpCatalog::Tools, capabilities.is_some_and(|c| c.tools.is_some())),
+            (McpCatalog::Resources, capabilities.is_some_and(|c| c.resources.is_some())),
+            (McpCatalog::Prompts, capabilities.is_some_and(|c| c.prompts.is_some())),
+        ]
+        .into_iter()
+        .filter_map(|(catalog, supported)| supported.then_some(catalog))
+        .collect();
+
+        self.add_log_entry(server_id, McpLogLevel::Info, "Refreshing catalogs".to_string());
+        self.refresh_catalogs(server_id, service.peer(), &catalogs).await
+    }
+
+    /// Fetch `catalogs` through `peer`, store them and emit the server's
+    /// updated catalog as `mcp-catalog-changed`
+    async fn refresh_catalogs(&self, server_id: &str, peer: &Peer<RoleClient>, catalogs: &[McpCatalog]) -> McpResult<()> {
+        let mut tools = None;
+        let mut resources = None;
+        let mut prompts = None;
+        for catalog in catalogs {
+            match catalog {
+                McpCatalog::Tools => tools = Some(self.extract_tools(peer, server_id).await?),
+                McpCatalog::Resources => {
+                    let templates = self.extract_resource_templates(peer, server_id).await.unwrap_or_default();
+                    resources = Some((self.extract_resources(peer, server_id).await?, templates));
+                }
+                McpCatalog::Prompts => prompts = Some(self.extract_prompts(peer, server_id).await?),
+            }
+        }
+
+        let mut servers = self.servers.write().await;
+        // Still starting: the catalog fetched at the end of startup is current
+        let Some(info) = servers.get_mut(server_id) else {
+            return Ok(());
+        };
+        if let Some(tools) = tools {
+            info.tools = tools;
+        }
+        if let Some((resources, templates)) = resources {
+            info.resources = resources;
+            info.resource_templates = templates;
+        }
+        if let Some(prompts) = prompts {
+            info.prompts = prompts;
+        }
+
+        let _ = self.app_handle.emit("mcp-catalog-changed", serde_json::json!({
+            "serverId": server_id,
+            "changed": catalogs,
+            "tools": info.tools,
+            "resources": info.resources,
+            "resourceTemplates": info.resource_templates,
+            "prompts": info.prompts,
+        }));
+        Ok(())
+    }
+
     /// List all tools from all connected servers
     pub async fn list_tools(&self) -> McpResult<Vec<McpTool>> {
         let servers = self.servers.read().await;
         let mut all_tools = Vec::new();
 
         for info in servers.values() {
-            if info.is_connected {
+            if info.state.is_available() {
                 all_tools.extend(info.tools.clone());
             }
         }
@@ -352,7 +747,7 @@ impl McpManager {
         let mut all_resources = Vec::new();
 
         for info in servers.values() {
-            if info.is_connected {
+            if info.state.is_available() {
                 all_resources.extend(info.resources.clone());
             }
         }
@@ -360,13 +755,27 @@ impl McpManager {
         Ok(all_resources)
     }
 
+    /// List all resource templates from all connected servers
+    pub async fn list_resource_templates(&self) -> McpResult<Vec<McpResourceTemplate>> {
+        let servers = self.servers.read().await;
+        let mut all_templates = Vec::new();
+
+        for info in servers.values() {
+            if info.state.is_available() {
+                all_templates.extend(info.resource_templates.clone());
+            }
+        }
+
+        Ok(all_templates)
+    }
+
     /// List all prompts from all connected servers
     pub async fn list_prompts(&self) -> McpResult<Vec<McpPrompt>> {
         let servers = self.servers.read().await;
         let mut all_prompts = Vec::new();
 
         for info in servers.values() {
-            if info.is_connected {
+            if info.state.is_available() {
                 all_prompts.extend(info.prompts.clone());
             }
         }
@@ -378,13 +787,7 @@ impl McpManager {
     pub async fn read_resource(&self, request: McpResourceReadRequest) -> McpResult<McpResourceReadResponse> {
         use rmcp::model::ReadResourceRequestParams;
 
-        let servers = self.servers.read().await;
-        let server_info = servers.get(&request.server_id)
-            .ok_or_else(|| McpError::ServerNotFound(request.server_id.clone()))?;
-
-        if !server_info.is_connected {
-            return Err(McpError::ServerNotFound(format!("Server {} is not connected", request.server_id)));
-        }
+        let service = self.available_service(&request.server_id).await?;
 
         self.add_log_entry(&request.server_id, McpLogLevel::Info,
             format!("Reading resource: {}", request.uri));
@@ -394,7 +797,7 @@ impl McpManager {
             meta: None,
         };
 
-        let result = server_info.service.read_resource(read_request).await
+        let result = service.read_resource(read_request).await
             .map_err(|e| {
                 self.add_log_entry(&request.server_id, McpLogLevel::Error,
                     format!("Resource read failed: {}", e));
@@ -427,17 +830,129 @@ impl McpManager {
         Ok(McpResourceReadResponse { contents })
     }
 
-    /// Get a prompt from a specific server
-    pub async fn get_prompt(&self, request: McpPromptGetRequest) -> McpResult<McpPromptGetResponse> {
-        use rmcp::model::{GetPromptRequestParams, PromptMessageRole};
+    /// Expand a resource template with the given arguments and read the
+    /// resulting resource
+    pub async fn read_resource_template(&self, request: McpResourceTemplateReadRequest) -> McpResult<McpResourceReadResponse> {
+        let uri = uri_template::expand(&request.uri_template, &request.arguments);
+        self.add_log_entry(&request.server_id, McpLogLevel::Info,
+            format!("Expanded resource template {} to {}", request.uri_template, uri));
+
+        self.read_resource(McpResourceReadRequest {
+            server_id: request.server_id,
+            uri,
+        }).await
+    }
+
+    /// Subscribe to updates of a resource. Updates are emitted as
+    /// `mcp-resource-updated` until unsubscribed, across reconnects.
+    pub async fn subscribe_resource(&self, server_id: &str, uri: &str) -> McpResult<()> {
+        use rmcp::model::SubscribeRequestParams;
+
+        let service = self.subscribable_service(server_id).await?;
+        self.add_log_entry(server_id, McpLogLevel::Info, format!("Subscribing to resource: {}", uri));
+
+        service.subscribe(SubscribeRequestParams { meta: None, uri: uri.to_string() }).await
+            .map_err(|e| {
+                self.add_log_entry(server_id, McpLogLevel::Error,
+                    format!("Resource subscribe failed: {}", e));
+                McpError::ProtocolError(format!("Resource subscribe failed: {}", e))
+            })?;
+
+        if let Some(info) = self.servers.write().await.get_mut(server_id) {
+            info.subscriptions.insert(uri.to_string());
+        }
+        Ok(())
+    }
+
+    /// Stop receiving updates of a resource
+    pub async fn unsubscribe_resource(&self, server_id: &str, uri: &str) -> McpResult<()> {
+        use rmcp::model::UnsubscribeRequestParams;
 
+        let service = self.subscribable_service(server_id).await?;
+        // Forget it first so no further updates are forwarded even if the
+        // server fails to process the request
+        if let Some(info) = self.servers.write().await.get_mut(server_id) {
+            info.subscriptions.remove(uri);
+        }
+        self.add_log_entry(server_id, McpLogLevel::Info, format!("Unsubscribing from resource: {}", uri));
+
+        service.unsubscribe(UnsubscribeRequestParams { meta: None, uri: uri.to_string() }).await
+            .map_err(|e| {
+                self.add_log_entry(server_id, McpLogLevel::Error,
+                    format!("Resource unsubscribe failed: {}", e));
+                McpError::ProtocolError(format!("Resource unsubscribe failed: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// The session of a connected server. Requests are awaited on the clone
+    /// so the server map isn't locked while they are in flight.
+    async fn available_service(&self, server_id: &str) -> McpResult<Arc<McpService>> {
         let servers = self.servers.read().await;
-        let server_info = servers.get(&request.server_id)
-            .ok_or_else(|| McpError::ServerNotFound(request.server_id.clone()))?;
+        let server_info = servers.get(server_id)
+            .ok_or_else(|| McpError::ServerNotFound(server_id.to_string()))?;
+        if !server_info.state.is_available() {
+            return Err(McpError::ServerNotFound(format!("Server {} is not connected", server_id)));
+        }
+        Ok(server_info.service.clone())
+    }
+
+    /// The session of a connected server that supports resource subscriptions
+    async fn subscribable_service(&self, server_id: &str) -> McpResult<Arc<McpService>> {
+        let service = self.available_service(server_id).await?;
+        if !supports_subscriptions(&service) {
+            return Err(McpError::ProtocolError(format!("Server {} does not support resource subscriptions", server_id)));
+        }
+        Ok(service)
+    }
 
-        if !server_info.is_connected {
-            return Err(McpError::ServerNotFound(format!("Server {} is not connected", request.server_id)));
+    /// Subscribe a new session to the resources the previous one was
+    /// subscribed to, returning those that succeeded
+    async fn resubscribe(&self, server_id: &str, service: &McpService, uris: HashSet<String>) -> HashSet<String> {
+        use rmcp::model::SubscribeRequestParams;
+
+        if uris.is_empty() {
+            return uris;
+        }
+        if !supports_subscriptions(service) {
+            self.add_log_entry(server_id, McpLogLevel::Info,
+                format!("Server no longer supports resource subscriptions, dropping {}", uris.len()));
+            return HashSet::new();
+        }
+
+        let mut restored = HashSet::new();
+        for uri in uris {
+            match service.subscribe(SubscribeRequestParams { meta: None, uri: uri.clone() }).await {
+                Ok(()) => {
+                    restored.insert(uri);
+                }
+                Err(e) => self.add_log_entry(server_id, McpLogLevel::Error,
+                    format!("Failed to resubscribe to {}: {}", uri, e)),
+            }
         }
+        restored
+    }
+
+    /// Forward a resources/updated notification for a subscribed resource
+    pub(crate) async fn resource_updated(&self, server_id: &str, uri: String) {
+        let subscribed = self.servers.read().await.get(server_id)
+            .is_some_and(|info| info.subscriptions.contains(&uri));
+        if !subscribed {
+            return;
+        }
+
+        self.add_log_entry(server_id, McpLogLevel::Info, format!("Resource updated: {}", uri));
+        let _ = self.app_handle.emit("mcp-resource-updated", serde_json::json!({
+            "serverId": server_id,
+            "uri": uri,
+        }));
+    }
+
+    /// Get a prompt from a specific server
+    pub async fn get_prompt(&self, request: McpPromptGetRequest) -> McpResult<McpPromptGetResponse> {
+        use rmcp::model::{GetPromptRequestParams, PromptMessageRole};
+
+        let service = self.available_service(&request.server_id).await?;
 
         let args = request.arguments.and_then(|v| {
             if let serde_json::Value::Object(map) = v {
@@ -456,7 +971,7 @@ impl McpManager {
             meta: None,
         };
 
-        let result = server_info.service.get_prompt(prompt_request).await
+        let result = service.get_prompt(prompt_request).await
             .map_err(|e| {
                 self.add_log_entry(&request.server_id, McpLogLevel::Error,
                     format!("Prompt get failed: {}", e));
@@ -521,13 +1036,7 @@ impl McpManager {
         use rmcp::model::CallToolRequestParams;
 
         // Get the service from the stored connection and call the tool
-        let servers = self.servers.read().await;
-        let server_info = servers.get(&request.server_id)
-            .ok_or_else(|| McpError::ServerNotFound(request.server_id.clone()))?;
-
-        if !server_info.is_connected {
-            return Err(McpError::ServerNotFound(format!("Server {} is not connected", request.server_id)));
-        }
+        let service = self.available_service(&request.server_id).await?;
 
         // Convert arguments
         let args = match request.arguments {
@@ -549,7 +1058,7 @@ impl McpManager {
 
         self.add_log_entry(&request.server_id, McpLogLevel::Info, format!("Calling tool: {}", request.tool_name));
 
-        let result = server_info.service.call_tool(tool_request).await
+        let result = service.call_tool(tool_request).await
             .map_err(|e| {
                 self.add_log_entry(&request.server_id, McpLogLevel::Error, format!("Tool call failed: {}", e));
                 McpError::ToolExecutionError(format!("Tool execution failed: {}", e))
@@ -616,7 +1125,7 @@ impl McpManager {
     pub async fn stop_server(&self, server_id: &str) -> McpResult<()> {
         let mut servers = self.servers.write().await;
         
-        // Remove the server, which will drop the Arc<RunningService>
+        // Remove the server, which stops its monitor and drops the Arc<RunningService>
         // When the last Arc reference is dropped, the service and its child processes are cleaned up
         if let Some(_server_info) = servers.remove(server_id) {
             // Emit disconnection event
@@ -651,45 +1160,63 @@ impl McpManager {
 
     /// Add a log entry for a server
     fn add_log_entry(&self, server_id: &str, level: McpLogLevel, message: String) {
-        let servers = self.servers.clone();
-        let app_handle = self.app_handle.clone();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+
+        let log_entry = McpLogEntry {
+            timestamp,
+            level,
+            message,
+        };
+        self.logs.append(server_id, log_entry.clone());
+
+        // Emit log event to frontend
+        let _ = self.app_handle.emit("mcp-server-log", serde_json::json!({
+            "serverId": server_id,
+            "log": log_entry,
+        }));
+    }
+
+    /// Persist logs to `dir` from now on, or keep them in memory only
+    pub fn set_log_directory(&self, dir: Option<PathBuf>) {
+        self.logs.set_directory(dir);
+    }
+
+    /// Get logs for a server, including ones from before it started or
+    /// after it stopped
+    pub async fn get_server_logs(&self, server_id: &str, filter: McpLogFilter) -> McpResult<Vec<McpLogEntry>> {
+        let logs = self.logs.clone();
         let server_id = server_id.to_string();
-        
-        tokio::spawn(async move {
-            let mut servers = servers.write().await;
-            if let Some(server_info) = servers.get_mut(&server_id) {
-                let timestamp = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis() as i64;
-                
-                let log_entry = McpLogEntry {
-                    timestamp,
-                    level: level.clone(),
-                    message: message.clone(),
-                };
-                
-                // Add to circular buffer
-                if server_info.logs.len() >= MAX_LOG_ENTRIES {
-                    server_info.logs.pop_front();
-                }
-                server_info.logs.push_back(log_entry.clone());
-                
-                // Emit log event to frontend
-                let _ = app_handle.emit("mcp-server-log", serde_json::json!({
-                    "serverId": server_id,
-                    "log": log_entry,
-                }));
-            }
-        });
+        // Persisted history is read from disk
+        tokio::task::spawn_blocking(move || logs.query(&server_id, &filter))
+            .await
+            .map_err(|e| McpError::IoError(std::io::Error::other(e)))
     }
+}
 
-    /// Get logs for a specific server
-    pub async fn get_server_logs(&self, server_id: &str) -> McpResult<Vec<McpLogEntry>> {
-        let servers = self.servers.read().await;
-        let server_info = servers.get(server_id)
-            .ok_or_else(|| McpError::ServerNotFound(server_id.to_string()))?;
-        
-        Ok(server_info.logs.iter().cloned().collect())
+/// Human-readable exit code or signal of a server process
+fn describe_exit(status: ExitStatus, stopped: bool) -> String {
+    let prefix = if stopped { "Process stopped" } else { "Process exited" };
+    if let Some(code) = status.code() {
+        return format!("{} with code {}", prefix, code);
     }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return format!("{} by signal {}", prefix, signal);
+        }
+    }
+    format!("{}: {}", prefix, status)
+}
+
+/// Whether the server advertised `resources.subscribe` when it initialized
+fn supports_subscriptions(service: &McpService) -> bool {
+    service
+        .peer_info()
+        .and_then(|info| info.capabilities.resources.as_ref())
+        .and_then(|resources| resources.subscribe)
+        .unwrap_or(false)
 }
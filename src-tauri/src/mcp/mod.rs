@@ -1,6 +1,14 @@
+mod client;
+mod logs;
 mod manager;
+mod sse;
 mod transport;
 mod types;
+mod uri_template;
 
+pub use logs::{log_dir_from_settings, McpLogFilter};
 pub use manager::McpManager;
-pub use types::{McpServerConfig, McpToolCallRequest, McpResourceReadRequest, McpPromptGetRequest};
+pub use types::{
+    McpServerConfig, McpToolCallRequest, McpResourceReadRequest, McpResourceTemplateReadRequest,
+    McpPromptGetRequest,
+};
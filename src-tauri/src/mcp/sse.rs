@@ -0,0 +1,492 @@
+use std::future::Future;
+use std::time::Duration;
+
+use reqwest::header::{ACCEPT, CACHE_CONTROL};
+use reqwest::{Client, Response, Url};
+use rmcp::model::{ClientJsonRpcMessage, ServerJsonRpcMessage};
+use rmcp::transport::Transport;
+use rmcp::RoleClient;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::mcp::types::{McpError, McpResult};
+
+/// How long the server may take to announce where to post messages
+const ENDPOINT_TIMEOUT: Duration = Duration::from_secs(30);
+const MESSAGE_BUFFER: usize = 64;
+
+/// A dispatched server-sent event
+#[derive(Debug, Default)]
+struct SseEvent {
+    event: String,
+    data: String,
+}
+
+/// Incremental `text/event-stream` parser fed with chunks as they arrive
+#[derive(Debug, Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    /// Start of the part of `buffer` not parsed yet
+    offset: usize,
+    event: SseEvent,
+    /// Skip a `\n` right after a `\r` line ending split across chunks
+    after_cr: bool,
+}
+
+impl SseParser {
+    fn feed(&mut self, chunk: &[u8]) {
+        // Drop parsed lines once per chunk rather than once per line
+        self.buffer.drain(..self.offset);
+        self.offset = 0;
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Next event completed by what was fed so far
+    fn next_event(&mut self) -> Option<SseEvent> {
+        while let Some(line) = self.take_line() {
+            if let Some(event) = self.process_line(&line) {
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    fn take_line(&mut self) -> Option<String> {
+        if self.after_cr {
+            // Whether the `\r` was a CRLF is only known once more arrives
+            match self.buffer.get(self.offset) {
+                None => return None,
+                Some(b'\n') => self.offset += 1,
+                Some(_) => {}
+            }
+            self.after_cr = false;
+        }
+        let rest = &self.buffer[self.offset..];
+        let end = rest.iter().position(|b| *b == b'\n' || *b == b'\r')?;
+        let line = String::from_utf8_lossy(&rest[..end]).into_owned();
+        self.after_cr = rest[end] == b'\r';
+        self.offset += end + 1;
+        Some(line)
+    }
+
+    /// Apply a line to the pending event, returning it on the blank line that
+    /// ends it
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            let event = std::mem::take(&mut self.event);
+            if event.data.is_empty() {
+                return None;
+            }
+            return Some(SseEvent {
+                event: if event.event.is_empty() {
+                    "message".to_string()
+                } else {
+                    event.event
+                },
+                data: event
+                    .data
+                    .strip_suffix('\n')
+                    .unwrap_or(&event.data)
+                    .to_string(),
+            });
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event.event = value.to_string(),
+            "data" => {
+                self.event.data.push_str(value);
+                self.event.data.push('\n');
+            }
+            // "id" and "retry" only matter for reconnecting, which MCP
+            // doesn't do on this transport
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Events of a response body
+struct SseReader {
+    response: Response,
+    parser: SseParser,
+}
+
+impl SseReader {
+    fn new(response: Response) -> Self {
+        Self {
+            response,
+            parser: SseParser::default(),
+        }
+    }
+
+    /// Next event, or `None` once the stream ends
+    async fn next(&mut self) -> McpResult<Option<SseEvent>> {
+        loop {
+            if let Some(event) = self.parser.next_event() {
+                return Ok(Some(event));
+            }
+            match self.response.chunk().await? {
+                Some(chunk) => self.parser.feed(&chunk),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Client side of the MCP HTTP+SSE transport (protocol 2024-11-05): server
+/// messages arrive as `message` events on a long-lived GET, client messages
+/// are POSTed to the endpoint the server announces in its first event.
+pub struct SseClientTransport {
+    client: Client,
+    endpoint: Url,
+    messages: mpsc::Receiver<ServerJsonRpcMessage>,
+    reader: JoinHandle<()>,
+}
+
+impl SseClientTransport {
+    pub async fn connect(client: Client, url: &str) -> McpResult<Self> {
+        let url = Url::parse(url)
+            .map_err(|e| McpError::InvalidConfig(format!("Invalid SSE URL: {}", e)))?;
+        let response = client
+            .get(url.clone())
+            .header(ACCEPT, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache")
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(McpError::TransportError(format!(
+                "SSE connection failed with status {}",
+                response.status()
+            )));
+        }
+
+        let mut reader = SseReader::new(response);
+        let endpoint = tokio::time::timeout(ENDPOINT_TIMEOUT, async {
+            while let Some(event) = reader.next().await? {
+                if event.event == "endpoint" {
+                    return Ok(event.data);
+                }
+            }
+            Err(McpError::TransportError(
+                "SSE stream ended before the server sent its endpoint".to_string(),
+            ))
+        })
+        .await
+        .map_err(|_| McpError::ConnectionTimeout)??;
+
+        let endpoint = url
+            .join(endpoint.trim())
+            .map_err(|e| McpError::ProtocolError(format!("Invalid SSE endpoint: {}", e)))?;
+        if endpoint.origin() != url.origin() {
+            return Err(McpError::ProtocolError(format!(
+                "SSE endpoint {} is not on the server's origin",
+                endpoint
+            )));
+        }
+
+        let (sender, messages) = mpsc::channel(MESSAGE_BUFFER);
+        let reader = tokio::spawn(async move {
+            loop {
+                let event = match reader.next().await {
+                    Ok(Some(event)) => event,
+                    Ok(None) => break,
+                    Err(e) => {
+                        eprintln!("[MCP] SSE stream failed: {}", e);
+                        break;
+                    }
+                };
+                if event.event != "message" {
+                    continue;
+                }
+                match serde_json::from_str::<ServerJsonRpcMessage>(&event.data) {
+                    Ok(message) => {
+                        if sender.send(message).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => eprintln!("[MCP] Ignoring invalid SSE message: {}", e),
+                }
+            }
+        });
+
+        Ok(Self {
+            client,
+            endpoint,
+            messages,
+            reader,
+        })
+    }
+}
+
+impl Transport<RoleClient> for SseClientTransport {
+    type Error = McpError;
+
+    fn send(
+        &mut self,
+        item: ClientJsonRpcMessage,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static {
+        let request = self.client.post(self.endpoint.clone()).json(&item);
+        async move {
+            let response = request.send().await?;
+            if !response.status().is_success() {
+                return Err(McpError::TransportError(format!(
+                    "SSE message post failed with status {}",
+                    response.status()
+                )));
+            }
+            Ok(())
+        }
+    }
+
+    fn receive(&mut self) -> impl Future<Output = Option<ServerJsonRpcMessage>> {
+        self.messages.recv()
+    }
+
+    async fn close(&mut self) -> Result<(), Self::Error> {
+        self.reader.abort();
+        Ok(())
+    }
+}
+
+impl Drop for SseClientTransport {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::{JsonRpcMessage, NumberOrString};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// `(event, data)` of every event in `chunks`, fed one at a time
+    fn parse(chunks: &[&str]) -> Vec<(String, String)> {
+        let mut parser = SseParser::default();
+        let mut events = Vec::new();
+        for chunk in chunks {
+            parser.feed(chunk.as_bytes());
+            while let Some(event) = parser.next_event() {
+                events.push((event.event, event.data));
+            }
+        }
+        events
+    }
+
+    fn event(event: &str, data: &str) -> (String, String) {
+        (event.to_string(), data.to_string())
+    }
+
+    #[test]
+    fn parses_every_line_ending() {
+        assert_eq!(
+            parse(&["data: a\n\ndata: b\r\n\r\ndata: c\r\r"]),
+            vec![
+                event("message", "a"),
+                event("message", "b"),
+                event("message", "c")
+            ]
+        );
+    }
+
+    #[test]
+    fn joins_crlf_split_across_chunks() {
+        assert_eq!(
+            parse(&["data: a\r", "\ndata: b\r", "\n\r", "\n"]),
+            vec![event("message", "a\nb")]
+        );
+    }
+
+    #[test]
+    fn ends_line_at_cr_followed_by_other_chunk() {
+        assert_eq!(
+            parse(&["data: a\r", "\r", "data: b\n\n"]),
+            vec![event("message", "a"), event("message", "b")]
+        );
+    }
+
+    #[test]
+    fn joins_multi_line_data() {
+        assert_eq!(
+            parse(&["event: message\ndata: {\"a\":\ndata:1}\n\n"]),
+            vec![event("message", "{\"a\":\n1}")]
+        );
+    }
+
+    #[test]
+    fn skips_comments_and_events_without_data() {
+        assert_eq!(
+            parse(&[
+                ": keep-alive\n\n",
+                "event: ping\n\n",
+                "id: 1\nevent: endpoint\n",
+                ": note\ndata: /messages\n\n"
+            ]),
+            vec![event("endpoint", "/messages")]
+        );
+    }
+
+    #[test]
+    fn parses_large_bursts() {
+        let burst = "data: x\n\n".repeat(10_000);
+        assert_eq!(parse(&[&burst, "data: y\n\n"]).len(), 10_001);
+    }
+
+    /// Read a request from `stream`, returning its body
+    async fn read_request(stream: &mut TcpStream) -> String {
+        let mut data = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+                let head = String::from_utf8_lossy(&data[..end]).to_lowercase();
+                let length: usize = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map(|value| value.trim().parse().unwrap())
+                    .unwrap_or(0);
+                if data.len() >= end + 4 + length {
+                    return String::from_utf8_lossy(&data[end + 4..end + 4 + length]).into_owned();
+                }
+            }
+            let read = stream.read(&mut buffer).await.unwrap();
+            assert!(read > 0, "connection closed mid-request");
+            data.extend_from_slice(&buffer[..read]);
+        }
+    }
+
+    /// Accept the event stream request and open it with `events`
+    async fn accept_stream(listener: &TcpListener, events: &str) -> TcpStream {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        read_request(&mut stream).await;
+        stream
+            .write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n{}",
+                    events
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        stream
+    }
+
+    async fn listen() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/sse", listener.local_addr().unwrap());
+        (listener, url)
+    }
+
+    #[tokio::test]
+    async fn resolves_endpoint_against_stream_url() {
+        let (listener, url) = listen().await;
+        let server = tokio::spawn(async move {
+            accept_stream(
+                &listener,
+                ": hello\n\nevent: endpoint\ndata: /messages?session=1\n\n",
+            )
+            .await
+        });
+
+        let transport = SseClientTransport::connect(Client::new(), &url)
+            .await
+            .unwrap();
+        assert_eq!(
+            transport.endpoint.as_str(),
+            url.replace("/sse", "/messages?session=1")
+        );
+        drop(server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn rejects_endpoint_on_other_origin() {
+        let (listener, url) = listen().await;
+        let server = tokio::spawn(async move {
+            accept_stream(
+                &listener,
+                "event: endpoint\ndata: http://example.com/messages\n\n",
+            )
+            .await
+        });
+
+        let result = SseClientTransport::connect(Client::new(), &url).await;
+        assert!(matches!(result, Err(McpError::ProtocolError(_))));
+        drop(server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn fails_when_stream_ends_before_endpoint() {
+        let (listener, url) = listen().await;
+        let server = tokio::spawn(async move {
+            drop(accept_stream(&listener, "event: message\ndata: {}\n\n").await);
+        });
+
+        let result = SseClientTransport::connect(Client::new(), &url).await;
+        assert!(matches!(result, Err(McpError::TransportError(_))));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn round_trips_messages() {
+        let (listener, url) = listen().await;
+        let server = tokio::spawn(async move {
+            let mut events = accept_stream(&listener, "event: endpoint\ndata: /messages\n\n").await;
+
+            let (mut post, _) = listener.accept().await.unwrap();
+            let body = read_request(&mut post).await;
+            post.write_all(b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+
+            let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(request["method"], "ping");
+            let response = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": {},
+            });
+            // Split mid-line to exercise buffering across chunks
+            let event = format!("event: message\r\ndata: {}\r\n\r\n", response);
+            let (first, second) = event.split_at(event.len() / 2);
+            events.write_all(first.as_bytes()).await.unwrap();
+            events.flush().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            events.write_all(second.as_bytes()).await.unwrap();
+            events
+        });
+
+        let mut transport = SseClientTransport::connect(Client::new(), &url)
+            .await
+            .unwrap();
+        let ping: ClientJsonRpcMessage = serde_json::from_value(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "ping",
+        }))
+        .unwrap();
+        tokio::time::timeout(TIMEOUT, transport.send(ping))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let message = tokio::time::timeout(TIMEOUT, transport.receive())
+            .await
+            .unwrap()
+            .expect("stream ended without a response");
+        match message {
+            JsonRpcMessage::Response(response) => {
+                assert_eq!(response.id, NumberOrString::Number(7))
+            }
+            other => panic!("expected a response, got {:?}", other),
+        }
+        drop(server.await.unwrap());
+    }
+}
@@ -1,11 +1,21 @@
+use crate::mcp::sse::SseClientTransport;
 use crate::mcp::types::{McpServerConfig, McpResult, McpError, McpHttpHeader};
-use rmcp::transport::child_process::TokioChildProcess;
+use rmcp::RoleClient;
+use rmcp::model::{ClientJsonRpcMessage, ServerJsonRpcMessage};
+use rmcp::transport::Transport;
+use rmcp::transport::async_rw::AsyncRwTransport;
 use rmcp::transport::streamable_http_client::{StreamableHttpClientTransport, StreamableHttpClientTransportConfig};
+use std::future::Future;
+use std::process::Stdio;
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout};
+use tokio::sync::oneshot;
 
-/// Enum to hold different transport types
+/// Enum to hold different transport types. A stdio server comes with its
+/// process so the caller can watch its stderr and exit status.
 pub enum McpTransport {
-    Stdio(TokioChildProcess),
+    Stdio(StdioTransport, Box<StdioProcess>),
     Http(StreamableHttpClientTransport<reqwest::Client>),
+    Sse(SseClientTransport),
 }
 
 /// Creates appropriate transport for each server type
@@ -13,37 +23,108 @@ pub async fn create_transport(config: &McpServerConfig) -> McpResult<McpTranspor
     match config {
         McpServerConfig::Stdio { command, args, env, .. } => {
             create_stdio_transport(command, args, env).await
-                .map(McpTransport::Stdio)
+                .map(|(transport, process)| McpTransport::Stdio(transport, process))
         }
         McpServerConfig::Http { url, headers, .. } => {
             create_http_transport(url, headers).await
                 .map(McpTransport::Http)
         }
-        McpServerConfig::Sse { .. } => {
-            Err(McpError::InvalidConfig("SSE transport is not supported in rmcp 0.14".to_string()))
+        McpServerConfig::Sse { url, headers, .. } => {
+            create_sse_transport(url, headers).await
+                .map(McpTransport::Sse)
         }
     }
 }
 
-/// Create stdio transport (spawn child process)
+/// JSON-RPC over a child process's stdin and stdout
+pub struct StdioTransport {
+    transport: AsyncRwTransport<RoleClient, ChildStdout, ChildStdin>,
+    // Dropped with the transport, which tells the process watcher to stop
+    // the child
+    _stop: oneshot::Sender<()>,
+}
+
+/// The server process behind a [`StdioTransport`]
+pub struct StdioProcess {
+    pub child: Child,
+    pub stderr: Option<ChildStderr>,
+    /// Resolves once the transport is gone and the child should exit
+    pub stop: oneshot::Receiver<()>,
+}
+
+impl Transport<RoleClient> for StdioTransport {
+    type Error = std::io::Error;
+
+    fn send(
+        &mut self,
+        item: ClientJsonRpcMessage,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static {
+        self.transport.send(item)
+    }
+
+    fn receive(&mut self) -> impl Future<Output = Option<ServerJsonRpcMessage>> + Send {
+        self.transport.receive()
+    }
+
+    fn close(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.transport.close()
+    }
+}
+
+/// Create stdio transport (spawn child process with stderr piped)
 async fn create_stdio_transport(
     command: &str,
     args: &[String],
     env: &[crate::mcp::types::McpEnvVar],
-) -> McpResult<TokioChildProcess> {
+) -> McpResult<(StdioTransport, Box<StdioProcess>)> {
     let mut cmd = tokio::process::Command::new(command);
-    cmd.args(args);
+    cmd.args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
 
     // Set environment variables
     for var in env {
         cmd.env(&var.name, &var.value);
     }
 
-    // Use TokioChildProcess from rmcp
-    let transport = TokioChildProcess::new(cmd)
+    let mut child = cmd.spawn()
         .map_err(|e| McpError::TransportError(format!("Failed to spawn process: {}", e)))?;
+    let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        return Err(McpError::TransportError("Failed to open process stdio".to_string()));
+    };
+    let stderr = child.stderr.take();
+    let (stop_tx, stop_rx) = oneshot::channel();
 
-    Ok(transport)
+    let transport = StdioTransport {
+        transport: AsyncRwTransport::new_client(stdout, stdin),
+        _stop: stop_tx,
+    };
+    Ok((transport, Box::new(StdioProcess {
+        child,
+        stderr,
+        stop: stop_rx,
+    })))
+}
+
+/// Build a reqwest client that sends the configured headers with every request
+fn http_client(headers: &[McpHttpHeader]) -> McpResult<reqwest::Client> {
+    use reqwest::Client;
+
+    if headers.is_empty() {
+        return Ok(Client::new());
+    }
+    let mut header_map = reqwest::header::HeaderMap::new();
+    for header in headers {
+        let header_name = header.name.parse::<reqwest::header::HeaderName>()
+            .map_err(|e| McpError::TransportError(format!("Invalid header name: {}", e)))?;
+        let header_value = header.value.parse::<reqwest::header::HeaderValue>()
+            .map_err(|e| McpError::TransportError(format!("Invalid header value: {}", e)))?;
+        header_map.insert(header_name, header_value);
+    }
+    Client::builder().default_headers(header_map).build()
+        .map_err(|e| McpError::TransportError(format!("Failed to build HTTP client: {}", e)))
 }
 
 /// Create HTTP transport using streamable HTTP client
@@ -51,26 +132,19 @@ async fn create_http_transport(
     url: &str,
     headers: &[McpHttpHeader],
 ) -> McpResult<StreamableHttpClientTransport<reqwest::Client>> {
-    use reqwest::Client;
-
-    // Build reqwest client with custom headers if provided
-    let client = if headers.is_empty() {
-        Client::new()
-    } else {
-        let mut header_map = reqwest::header::HeaderMap::new();
-        for header in headers {
-            let header_name = header.name.parse::<reqwest::header::HeaderName>()
-                .map_err(|e| McpError::TransportError(format!("Invalid header name: {}", e)))?;
-            let header_value = header.value.parse::<reqwest::header::HeaderValue>()
-                .map_err(|e| McpError::TransportError(format!("Invalid header value: {}", e)))?;
-            header_map.insert(header_name, header_value);
-        }
-        Client::builder().default_headers(header_map).build()
-            .map_err(|e| McpError::TransportError(format!("Failed to build HTTP client: {}", e)))?
-    };
-
+    let client = http_client(headers)?;
     let config = StreamableHttpClientTransportConfig::with_uri(url);
     let transport = StreamableHttpClientTransport::with_client(client, config);
 
     Ok(transport)
 }
+
+/// Create SSE transport: opens the event stream and waits for the server's
+/// message endpoint
+async fn create_sse_transport(
+    url: &str,
+    headers: &[McpHttpHeader],
+) -> McpResult<SseClientTransport> {
+    let client = http_client(headers)?;
+    SseClientTransport::connect(client, url).await
+}
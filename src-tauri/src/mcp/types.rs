@@ -1,9 +1,9 @@
+use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 // MCP Server Configuration (mirrors frontend types)
-// Supported transports: stdio, http (streamable)
-// SSE transport is not supported in rmcp 0.14
+// Supported transports: stdio, http (streamable), sse (legacy HTTP+SSE)
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(tag = "type")]
 pub enum McpServerConfig {
@@ -26,8 +26,8 @@ pub enum McpServerConfig {
         headers: Vec<McpHttpHeader>,
     },
 
-    // SSE (Server-Sent Events) transport - NOT SUPPORTED in rmcp 0.14
-    // Will return an error if used
+    // SSE (Server-Sent Events) transport from the 2024-11-05 protocol,
+    // implemented in-crate since rmcp 0.14 has no SSE client
     #[serde(rename = "sse")]
     Sse {
         name: String,
@@ -94,6 +94,17 @@ pub struct McpResource {
     pub mime_type: Option<String>,
 }
 
+// Parameterized resources, e.g. "file:///{path}" (RFC 6570 URI templates)
+#[derive(Debug, Clone, Serialize)]
+pub struct McpResourceTemplate {
+    pub uri_template: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+    /// Variables of the template, in order of appearance
+    pub parameters: Vec<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct McpResourceReadRequest {
     #[serde(rename = "serverId")]
@@ -101,6 +112,16 @@ pub struct McpResourceReadRequest {
     pub uri: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct McpResourceTemplateReadRequest {
+    #[serde(rename = "serverId")]
+    pub server_id: String,
+    #[serde(rename = "uriTemplate")]
+    pub uri_template: String,
+    #[serde(default)]
+    pub arguments: HashMap<String, String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct McpResourceReadResponse {
     pub contents: Vec<McpResourceContent>,
@@ -161,6 +182,44 @@ pub enum McpPromptContent {
     Resource { uri: String },
 }
 
+// Connection state reported in "mcp-server-status" events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum McpServerState {
+    Starting,
+    Connected,
+    // Health pings are failing but the transport is still open
+    Degraded,
+    Reconnecting,
+    Failed,
+}
+
+impl McpServerState {
+    /// Whether requests should still be sent to the server
+    pub fn is_available(self) -> bool {
+        matches!(self, McpServerState::Connected | McpServerState::Degraded)
+    }
+}
+
+// Lists a server can report as changed, named as in "mcp-catalog-changed" logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum McpCatalog {
+    Tools,
+    Resources,
+    Prompts,
+}
+
+impl std::fmt::Display for McpCatalog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            McpCatalog::Tools => "tools",
+            McpCatalog::Resources => "resources",
+            McpCatalog::Prompts => "prompts",
+        })
+    }
+}
+
 // Error handling
 #[derive(Error, Debug)]
 pub enum McpError {
@@ -202,7 +261,7 @@ pub struct McpLogEntry {
     pub message: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum McpLogLevel {
     Info,
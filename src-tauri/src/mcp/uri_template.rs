@@ -0,0 +1,333 @@
+//! RFC 6570 URI template expansion for MCP resource templates. Values are
+//! plain strings, so list and map explosion don't apply; everything else up
+//! to level 4 (operators, prefixes) is supported.
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::collections::HashMap;
+
+/// Everything but unreserved characters is encoded
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Reserved characters pass through too, for `{+var}` and `{#var}`. `%` is
+/// still encoded here; see `Operator::encode` for pct-encoded triples.
+const RESERVED: &AsciiSet = &UNRESERVED
+    .remove(b':')
+    .remove(b'/')
+    .remove(b'?')
+    .remove(b'#')
+    .remove(b'[')
+    .remove(b']')
+    .remove(b'@')
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=')
+    .remove(b'%');
+
+/// How an expression's operator joins and encodes its variables
+struct Operator {
+    first: &'static str,
+    separator: &'static str,
+    named: bool,
+    if_empty: &'static str,
+    allow_reserved: bool,
+}
+
+impl Operator {
+    fn parse(expression: &str) -> (Self, &str) {
+        let (first, separator, named, if_empty, allow_reserved) = match expression.chars().next() {
+            Some('+') => ("", ",", false, "", true),
+            Some('#') => ("#", ",", false, "", true),
+            Some('.') => (".", ".", false, "", false),
+            Some('/') => ("/", "/", false, "", false),
+            Some(';') => (";", ";", true, "", false),
+            Some('?') => ("?", "&", true, "=", false),
+            Some('&') => ("&", "&", true, "=", false),
+            _ => return (Self::simple(), expression),
+        };
+        (
+            Self {
+                first,
+                separator,
+                named,
+                if_empty,
+                allow_reserved,
+            },
+            &expression[1..],
+        )
+    }
+
+    fn simple() -> Self {
+        Self {
+            first: "",
+            separator: ",",
+            named: false,
+            if_empty: "",
+            allow_reserved: false,
+        }
+    }
+
+    fn encode(&self, value: &str) -> String {
+        if !self.allow_reserved {
+            return utf8_percent_encode(value, UNRESERVED).to_string();
+        }
+        // Reserved expansion keeps pct-encoded triples as they are; any other
+        // `%` is encoded
+        let mut result = String::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(index) = rest.find('%') {
+            result.extend(utf8_percent_encode(&rest[..index], RESERVED));
+            let digits = rest.as_bytes().get(index + 1..index + 3);
+            if digits.is_some_and(|digits| digits.iter().all(u8::is_ascii_hexdigit)) {
+                result.push_str(&rest[index..index + 3]);
+                rest = &rest[index + 3..];
+            } else {
+                result.push_str("%25");
+                rest = &rest[index + 1..];
+            }
+        }
+        result.extend(utf8_percent_encode(rest, RESERVED));
+        result
+    }
+}
+
+/// A variable reference inside an expression, e.g. `path:10` or `list*`
+struct VarSpec<'a> {
+    name: &'a str,
+    prefix: Option<usize>,
+}
+
+fn var_specs(variables: &str) -> impl Iterator<Item = VarSpec<'_>> {
+    variables.split(',').filter_map(|spec| {
+        let spec = spec.trim().trim_end_matches('*');
+        let (name, prefix) = match spec.split_once(':') {
+            Some((name, length)) => (name, length.parse().ok()),
+            None => (spec, None),
+        };
+        (!name.is_empty()).then_some(VarSpec { name, prefix })
+    })
+}
+
+/// The `{...}` expressions of a template, without braces. Unclosed braces
+/// are treated as literal text.
+fn expressions(template: &str) -> impl Iterator<Item = &str> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}').map(|(expression, _)| expression))
+}
+
+/// Variable names in order of first appearance
+pub fn parameters(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for expression in expressions(template) {
+        let (_, variables) = Operator::parse(expression);
+        for spec in var_specs(variables) {
+            if !names.iter().any(|name| name == spec.name) {
+                names.push(spec.name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Expand `template` with `values`. Variables without a value are left out,
+/// as RFC 6570 specifies for undefined variables.
+pub fn expand(template: &str, values: &HashMap<String, String>) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let Some(length) = rest[start..].find('}') else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        let (operator, variables) = Operator::parse(&rest[start + 1..start + length]);
+
+        let items: Vec<String> = var_specs(variables)
+            .filter_map(|spec| {
+                let value = values.get(spec.name)?;
+                let value = match spec.prefix {
+                    Some(prefix) => value.chars().take(prefix).collect(),
+                    None => value.clone(),
+                };
+                Some(match (operator.named, value.is_empty()) {
+                    (false, _) => operator.encode(&value),
+                    (true, true) => format!("{}{}", spec.name, operator.if_empty),
+                    (true, false) => format!("{}={}", spec.name, operator.encode(&value)),
+                })
+            })
+            .collect();
+        if !items.is_empty() {
+            result.push_str(operator.first);
+            result.push_str(&items.join(operator.separator));
+        }
+
+        rest = &rest[start + length + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Variables of the RFC 6570 examples that are plain strings
+    fn values() -> HashMap<String, String> {
+        [
+            ("var", "value"),
+            ("hello", "Hello World!"),
+            ("path", "/foo/bar"),
+            ("empty", ""),
+            ("x", "1024"),
+            ("y", "768"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+    }
+
+    fn assert_expands(cases: &[(&str, &str)]) {
+        let values = values();
+        for (template, expected) in cases {
+            assert_eq!(expand(template, &values), *expected, "{}", template);
+        }
+    }
+
+    #[test]
+    fn expands_simple_strings() {
+        assert_expands(&[
+            ("{var}", "value"),
+            ("{hello}", "Hello%20World%21"),
+            ("{x,hello,y}", "1024,Hello%20World%21,768"),
+            ("{path}", "%2Ffoo%2Fbar"),
+            ("map?{x,y}", "map?1024,768"),
+            ("{empty}", ""),
+        ]);
+    }
+
+    #[test]
+    fn expands_reserved() {
+        assert_expands(&[
+            ("{+var}", "value"),
+            ("{+hello}", "Hello%20World!"),
+            ("{+path}/here", "/foo/bar/here"),
+            ("here?ref={+path}", "here?ref=/foo/bar"),
+            ("{+x,hello,y}", "1024,Hello%20World!,768"),
+            ("{+path,x}/here", "/foo/bar,1024/here"),
+        ]);
+    }
+
+    #[test]
+    fn expands_fragments() {
+        assert_expands(&[
+            ("X{#var}", "X#value"),
+            ("X{#hello}", "X#Hello%20World!"),
+            ("{#x,hello,y}", "#1024,Hello%20World!,768"),
+            ("{#path,x}/here", "#/foo/bar,1024/here"),
+        ]);
+    }
+
+    #[test]
+    fn expands_labels_and_path_segments() {
+        assert_expands(&[
+            ("X{.var}", "X.value"),
+            ("X{.x,y}", "X.1024.768"),
+            ("{/var}", "/value"),
+            ("{/var,x}/here", "/value/1024/here"),
+            ("{/path}", "/%2Ffoo%2Fbar"),
+        ]);
+    }
+
+    #[test]
+    fn expands_named_parameters() {
+        assert_expands(&[
+            ("{;x,y}", ";x=1024;y=768"),
+            ("{;x,y,empty}", ";x=1024;y=768;empty"),
+            ("{?x,y}", "?x=1024&y=768"),
+            ("{?x,y,empty}", "?x=1024&y=768&empty="),
+            ("?fixed=yes{&x}", "?fixed=yes&x=1024"),
+            ("{&x,y,empty}", "&x=1024&y=768&empty="),
+        ]);
+    }
+
+    #[test]
+    fn applies_prefix_modifiers() {
+        assert_expands(&[
+            ("{var:3}", "val"),
+            ("{var:30}", "value"),
+            ("{+path:6}/here", "/foo/b/here"),
+            ("{#path:6}/here", "#/foo/b/here"),
+            ("X{.var:3}", "X.val"),
+            ("{/var:1,var}", "/v/value"),
+            ("{;hello:5}", ";hello=Hello"),
+            ("{?var:3}", "?var=val"),
+            ("{&var:3}", "&var=val"),
+        ]);
+    }
+
+    #[test]
+    fn prefix_counts_characters() {
+        let values = HashMap::from([("name".to_string(), "héllo".to_string())]);
+        assert_eq!(expand("{name:2}", &values), "h%C3%A9");
+    }
+
+    #[test]
+    fn leaves_out_undefined_variables() {
+        assert_expands(&[
+            ("{undef}", ""),
+            ("{+undef}", ""),
+            ("X{#undef}", "X"),
+            ("X{.undef}", "X"),
+            ("{/undef}", ""),
+            ("{;undef}", ""),
+            ("{?undef}", ""),
+            ("{?x,undef,y}", "?x=1024&y=768"),
+            ("{&undef}", ""),
+            ("{undef:3}", ""),
+        ]);
+    }
+
+    #[test]
+    fn keeps_only_valid_pct_triples_in_reserved_expansion() {
+        let values = HashMap::from([
+            ("encoded".to_string(), "a%2Fb".to_string()),
+            ("stray".to_string(), "50%".to_string()),
+            ("short".to_string(), "%4".to_string()),
+            ("invalid".to_string(), "%zz".to_string()),
+        ]);
+        assert_eq!(expand("{+encoded}", &values), "a%2Fb");
+        assert_eq!(expand("{#encoded}", &values), "#a%2Fb");
+        assert_eq!(expand("{+stray}", &values), "50%25");
+        assert_eq!(expand("{+short}", &values), "%254");
+        assert_eq!(expand("{+invalid}", &values), "%25zz");
+        assert_eq!(expand("{encoded}", &values), "a%252Fb");
+    }
+
+    #[test]
+    fn keeps_unclosed_braces_literal() {
+        assert_expands(&[("a{var", "a{var"), ("{var}{x", "value{x")]);
+    }
+
+    #[test]
+    fn lists_parameters_in_order() {
+        assert_eq!(
+            parameters("file:///{+path}{?x,path:3,y}{&list*}{#x}"),
+            vec!["path", "x", "y", "list"]
+        );
+        assert!(parameters("static://uri").is_empty());
+    }
+}
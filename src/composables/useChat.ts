@@ -45,6 +45,7 @@ export class ChatSdkTransport implements ChatTransport<UIMessage> {
         webSearch: body?.webSearch,
         temperature: body?.temperature,
         contextSize: body?.contextSize,
+        resources: body?.resources,
       };
       
       if (messages.length === 1) {
@@ -82,6 +83,8 @@ export class ChatSdkTransport implements ChatTransport<UIMessage> {
     const result = streamText({
       model: getModel(body?.model),
       messages: modelMessages,
+      // Current contents of the attached MCP resources
+      system: body?.resourceContext,
       stopWhen: stepCountIs(30),
       temperature: body?.temperature,
       tools: Object.keys(allTools).length > 0 ? allTools : undefined,
@@ -0,0 +1,108 @@
+import { ref, onUnmounted } from "vue";
+import { useMcpStore } from "@/stores/mcp";
+
+/** MCP resource attached to a chat, persisted in the chat's ext */
+export interface ResourceRef {
+  serverId: string;
+  uri: string;
+  name: string;
+}
+
+export interface AttachedResource extends ResourceRef {
+  /** Text of the resource as last read; blobs are left out */
+  text?: string;
+  error?: string;
+  /** Whether the server pushes updates, so `text` stays current */
+  live: boolean;
+  updatedAt?: Date;
+}
+
+/**
+ * Resources attached to a chat. Each is read on attach and, where the server
+ * supports it, subscribed to so its contents are re-read on every
+ * `notifications/resources/updated`. The latest contents go to the model as
+ * context with every message.
+ */
+export function useMcpResources(initial: ResourceRef[] = []) {
+  const mcpStore = useMcpStore();
+  const attached = ref<AttachedResource[]>([]);
+
+  const find = (serverId: string, uri: string) =>
+    attached.value.find((item) => item.serverId === serverId && item.uri === uri);
+
+  async function read(item: AttachedResource) {
+    try {
+      const contents = await mcpStore.readResource(item.serverId, item.uri);
+      item.text = contents
+        .filter((content) => content.type === "text")
+        .map((content) => content.text ?? "")
+        .join("\n");
+      item.error = undefined;
+      item.updatedAt = new Date();
+    } catch (err) {
+      console.error(`[MCP] Failed to read resource ${item.uri}:`, err);
+      item.error = String(err);
+    }
+  }
+
+  async function attach(resource: ResourceRef) {
+    if (find(resource.serverId, resource.uri)) return;
+    attached.value.push({ ...resource, live: false });
+    const item = find(resource.serverId, resource.uri)!;
+    await read(item);
+    try {
+      await mcpStore.subscribeResource(item.serverId, item.uri);
+      item.live = true;
+    } catch (err) {
+      // Not every server supports subscriptions; the contents stay as read
+      console.warn(`[MCP] No live updates for ${item.uri}:`, err);
+    }
+  }
+
+  async function detach(serverId: string, uri: string) {
+    const item = find(serverId, uri);
+    if (!item) return;
+    attached.value = attached.value.filter((other) => other !== item);
+    if (item.live) {
+      await mcpStore.unsubscribeResource(serverId, uri).catch((err) =>
+        console.error(`[MCP] Failed to unsubscribe from ${uri}:`, err)
+      );
+    }
+  }
+
+  const removeHandler = mcpStore.onResourceUpdated((serverId, uri) => {
+    const item = find(serverId, uri);
+    if (item) {
+      read(item);
+    }
+  });
+
+  onUnmounted(() => {
+    removeHandler();
+    for (const item of attached.value) {
+      if (item.live) {
+        mcpStore.unsubscribeResource(item.serverId, item.uri).catch(() => {});
+      }
+    }
+  });
+
+  /** References to store with the chat */
+  function refs(): ResourceRef[] {
+    return attached.value.map(({ serverId, uri, name }) => ({ serverId, uri, name }));
+  }
+
+  /** System prompt with the current contents of every attached resource */
+  function context(): string | undefined {
+    const parts = attached.value
+      .filter((item) => item.text)
+      .map((item) => `<resource uri="${item.uri}" name="${item.name}">\n${item.text}\n</resource>`);
+    if (parts.length === 0) return undefined;
+    return `The user attached these resources. Their contents are current as of this message:\n\n${parts.join("\n\n")}`;
+  }
+
+  for (const resource of initial) {
+    attach(resource);
+  }
+
+  return { attached, attach, detach, refs, context };
+}
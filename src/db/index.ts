@@ -1,4 +1,6 @@
 import Database from '@tauri-apps/plugin-sql';
+import { invoke } from '@tauri-apps/api/core';
+import { confirm } from '@tauri-apps/plugin-dialog';
 import { UIMessage } from 'ai';
 import { ROOT_NODE_ID } from '@/constants';
 
@@ -21,12 +23,14 @@ export interface Chat {
   ext?: {
     // AgentChat ext fields
     sessionId?: string;
+    worktree?: string;
     // SimpleChat ext fields
     model?: string;
     mcpServers?: string[];
     webSearch?: boolean;
     temperature?: number;
     contextSize?: number;
+    resources?: { serverId: string; uri: string; name: string }[];
   };
 }
 
@@ -50,6 +54,8 @@ export interface Agent {
   updatedAt: Date;
   program: AgentProgram;
   directory: string;
+  // Run each chat in a git worktree of its own instead of the directory itself
+  worktree?: boolean;
 }
 
 export interface FileStore {
@@ -156,6 +162,27 @@ export async function getChat(chatId: string): Promise<Chat | undefined> {
 }
 
 export async function deleteChat(chatId: string): Promise<void> {
+  if (!db) throw new Error('Database not initialized');
+  // Agent chats may run in a worktree of their own. Work not merged back is
+  // only dropped once confirmed; otherwise, or when the worktree can't be
+  // removed, the chat is kept so the worktree isn't left without an owner.
+  try {
+    await invoke('discard_task_worktree', { taskId: chatId });
+  } catch (err) {
+    if (!String(err).startsWith('Unmerged:')) {
+      throw new Error(`Chat kept: failed to discard its worktree: ${err}`);
+    }
+    const ok = await confirm(
+      `${String(err).replace(/^Unmerged:\s*/, '')}. Deleting the chat discards this work. Continue?`,
+      { title: 'Discard agent work', kind: 'warning' }
+    );
+    if (!ok) throw new Error('Chat kept: its worktree has unmerged work');
+    await invoke('discard_task_worktree', { taskId: chatId, force: true });
+  }
+  await deleteChatRows(chatId);
+}
+
+async function deleteChatRows(chatId: string): Promise<void> {
   if (!db) throw new Error('Database not initialized');
   await db.execute('DELETE FROM message WHERE chatId = $1', [chatId]);
   await db.execute('DELETE FROM chat WHERE id = $1', [chatId]);
@@ -286,8 +313,8 @@ export interface ChatData {
 export async function writeAgent(data: Agent): Promise<void> {
   if (!db) throw new Error('Database not initialized');
   await db.execute(
-    `INSERT OR REPLACE INTO agent (id, name, icon, createdAt, updatedAt, program, directory)
-     VALUES ($1, $2, $3, $4, $5, $6, $7)`,
+    `INSERT OR REPLACE INTO agent (id, name, icon, createdAt, updatedAt, program, directory, worktree)
+     VALUES ($1, $2, $3, $4, $5, $6, $7, $8)`,
     [
       data.id,
       data.name,
@@ -296,6 +323,7 @@ export async function writeAgent(data: Agent): Promise<void> {
       dateToString(data.updatedAt),
       data.program,
       data.directory,
+      data.worktree ? 1 : 0,
     ]
   );
 }
@@ -333,6 +361,7 @@ export async function getAgents(): Promise<Agent[]> {
     ...row,
     createdAt: stringToDate(row.createdAt),
     updatedAt: stringToDate(row.updatedAt),
+    worktree: !!row.worktree,
   }));
 }
 
@@ -348,6 +377,7 @@ export async function getAgent(id: string): Promise<Agent | null> {
     ...row,
     createdAt: stringToDate(row.createdAt),
     updatedAt: stringToDate(row.updatedAt),
+    worktree: !!row.worktree,
   };
 }
 
@@ -26,6 +26,17 @@ interface WebSearchSettings {
   apiKey: string;
 }
 
+/** Author of commits made from the app; falls back to the repository's git config */
+export interface GitSettings {
+  authorName?: string;
+  authorEmail?: string;
+}
+
+/** Whether MCP server logs are also written to disk, with rotation */
+export interface McpLogSettings {
+  persist?: boolean;
+}
+
 interface SettingRecord {
   id: string;
   type: string;
@@ -240,6 +251,56 @@ export async function getWebSearchSettings(): Promise<WebSearchSettings | null>
   return JSON.parse(result[0].value) as WebSearchSettings;
 }
 
+// ============== GIT SETTINGS ==============
+
+export async function writeGitSettings(settings: GitSettings): Promise<void> {
+  if (!db) throw new Error('Database not initialized');
+  const id = 'git::default';
+  const value = JSON.stringify(settings);
+
+  await db.execute(
+    `INSERT OR REPLACE INTO settings (id, type, key, value)
+     VALUES ($1, $2, $3, $4)`,
+    [id, 'git', 'default', value]
+  );
+}
+
+export async function getGitSettings(): Promise<GitSettings | null> {
+  if (!db) throw new Error('Database not initialized');
+  const id = 'git::default';
+  const result = await db.select<SettingRecord[]>(
+    `SELECT * FROM settings WHERE id = $1`,
+    [id]
+  );
+  if (result.length === 0) return null;
+  return JSON.parse(result[0].value) as GitSettings;
+}
+
+// ============== MCP LOG SETTINGS ==============
+
+export async function writeMcpLogSettings(settings: McpLogSettings): Promise<void> {
+  if (!db) throw new Error('Database not initialized');
+  const id = 'mcp_logs::default';
+  const value = JSON.stringify(settings);
+
+  await db.execute(
+    `INSERT OR REPLACE INTO settings (id, type, key, value)
+     VALUES ($1, $2, $3, $4)`,
+    [id, 'mcp_logs', 'default', value]
+  );
+}
+
+export async function getMcpLogSettings(): Promise<McpLogSettings | null> {
+  if (!db) throw new Error('Database not initialized');
+  const id = 'mcp_logs::default';
+  const result = await db.select<SettingRecord[]>(
+    `SELECT * FROM settings WHERE id = $1`,
+    [id]
+  );
+  if (result.length === 0) return null;
+  return JSON.parse(result[0].value) as McpLogSettings;
+}
+
 // ============== UTILITY FUNCTIONS ==============
 
 export async function clearAllSettings(): Promise<void> {
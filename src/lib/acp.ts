@@ -463,6 +463,10 @@ export class TauriACPClient implements acp.Client {
     return result;
   }
 
+  setDirectory(directory: string) {
+    this.config.directory = directory;
+  }
+
   async dispose() {
     this.onDispose?.();
   }
@@ -19,6 +19,15 @@ export interface McpResource {
   mimeType?: string;
 }
 
+export interface McpResourceTemplate {
+  uri_template: string;
+  name: string;
+  description?: string;
+  mime_type?: string;
+  /** Variables of the URI template, in order of appearance */
+  parameters: string[];
+}
+
 export interface McpResourceContent {
   type: 'text' | 'blob';
   uri: string;
@@ -56,18 +65,29 @@ export interface McpLogEntry {
   message: string;
 }
 
+export interface McpLogFilter {
+  levels?: McpLogEntry['level'][];
+  /** Milliseconds since the Unix epoch */
+  since?: number;
+  until?: number;
+  /** Newest entries to keep */
+  limit?: number;
+}
+
 export interface McpConnection {
   serverId: string;
   tools: McpTool[];
   resources: McpResource[];
+  resourceTemplates: McpResourceTemplate[];
   prompts: McpPrompt[];
-  status: 'starting' | 'connected' | 'failed' | 'disconnected';
+  status: 'starting' | 'connected' | 'degraded' | 'reconnecting' | 'failed' | 'disconnected';
   error?: string;
 }
 
 export const useMcpStore = defineStore('mcp', () => {
   const connections = ref<Map<string, McpConnection>>(new Map());
   let eventListenerUnsubscribe: (() => void) | null = null;
+  const resourceUpdateHandlers = new Set<(serverId: string, uri: string) => void>();
 
   const setupEventListeners = async () => {
     if (eventListenerUnsubscribe) {
@@ -79,10 +99,11 @@ export const useMcpStore = defineStore('mcp', () => {
       status: string;
       tools?: McpTool[];
       resources?: McpResource[];
+      resourceTemplates?: McpResourceTemplate[];
       prompts?: McpPrompt[];
       error?: string
     }>('mcp-server-status', (event) => {
-      const { serverId, status, tools, resources, prompts, error } = event.payload;
+      const { serverId, status, tools, resources, resourceTemplates, prompts, error } = event.payload;
 
       console.log(`[MCP Store] Server ${serverId} status: ${status}`, { tools, resources, prompts, error });
 
@@ -91,6 +112,7 @@ export const useMcpStore = defineStore('mcp', () => {
           serverId,
           tools: tools ?? [],
           resources: resources ?? [],
+          resourceTemplates: resourceTemplates ?? [],
           prompts: prompts ?? [],
           status: 'connected',
         });
@@ -99,6 +121,7 @@ export const useMcpStore = defineStore('mcp', () => {
           serverId,
           tools: [],
           resources: [],
+          resourceTemplates: [],
           prompts: [],
           status: 'failed',
           error,
@@ -108,15 +131,58 @@ export const useMcpStore = defineStore('mcp', () => {
           serverId,
           tools: [],
           resources: [],
+          resourceTemplates: [],
           prompts: [],
           status: 'starting',
         });
+      } else if (status === 'degraded' || status === 'reconnecting') {
+        // Keep the last known catalog while the server recovers
+        const existing = connections.value.get(serverId);
+        connections.value.set(serverId, {
+          serverId,
+          tools: existing?.tools ?? [],
+          resources: existing?.resources ?? [],
+          resourceTemplates: existing?.resourceTemplates ?? [],
+          prompts: existing?.prompts ?? [],
+          status,
+          error,
+        });
       } else if (status === 'disconnected') {
         connections.value.delete(serverId);
       }
     });
 
-    eventListenerUnsubscribe = unlisten;
+    const unlistenCatalog = await listen<{
+      serverId: string;
+      changed: ('tools' | 'resources' | 'prompts')[];
+      tools: McpTool[];
+      resources: McpResource[];
+      resourceTemplates: McpResourceTemplate[];
+      prompts: McpPrompt[];
+    }>('mcp-catalog-changed', (event) => {
+      const { serverId, changed, tools, resources, resourceTemplates, prompts } = event.payload;
+
+      console.log(`[MCP Store] Server ${serverId} catalog changed: ${changed.join(', ')}`);
+
+      const existing = connections.value.get(serverId);
+      if (existing) {
+        connections.value.set(serverId, { ...existing, tools, resources, resourceTemplates, prompts });
+      }
+    });
+
+    const unlistenResource = await listen<{
+      serverId: string;
+      uri: string;
+    }>('mcp-resource-updated', (event) => {
+      const { serverId, uri } = event.payload;
+      resourceUpdateHandlers.forEach((handler) => handler(serverId, uri));
+    });
+
+    eventListenerUnsubscribe = () => {
+      unlisten();
+      unlistenCatalog();
+      unlistenResource();
+    };
   };
 
   const getConnection = (serverId: string): McpConnection | undefined => {
@@ -175,24 +241,84 @@ export const useMcpStore = defineStore('mcp', () => {
     return result;
   };
 
-  const getServerLogs = async (serverId: string): Promise<McpLogEntry[]> => {
-    const logs = await invoke<McpLogEntry[]>('mcp_get_server_logs', { serverId });
+  /** Re-fetch a server's catalog; connections update from the resulting event */
+  const refreshServer = async (serverId: string): Promise<void> => {
+    await invoke('mcp_refresh_server', { serverId });
+  };
+
+  const getServerLogs = async (serverId: string, filter?: McpLogFilter): Promise<McpLogEntry[]> => {
+    const logs = await invoke<McpLogEntry[]>('mcp_get_server_logs', { serverId, filter });
     return logs;
   };
 
+  /** Apply saved log persistence settings to the running manager */
+  const reloadLogSettings = async (): Promise<void> => {
+    await invoke('mcp_reload_log_settings');
+  };
+
   const getAllTools = async (): Promise<McpTool[]> => {
     const tools = await invoke<McpTool[]>('mcp_list_tools');
     return tools;
   };
 
   const readResource = async (serverId: string, uri: string): Promise<McpResourceContent[]> => {
-    const result = await invoke('mcp_read_resource', {
+    const result = await invoke<{ contents: McpResourceContent[] }>('mcp_read_resource', {
       request: {
         serverId,
         uri,
       },
     });
-    return result as McpResourceContent[];
+    return result.contents;
+  };
+
+  /** Expand a resource template with `args` and read the resulting resource */
+  const readResourceTemplate = async (
+    serverId: string,
+    uriTemplate: string,
+    args: Record<string, string>,
+  ): Promise<McpResourceContent[]> => {
+    const result = await invoke<{ contents: McpResourceContent[] }>('mcp_read_resource_template', {
+      request: {
+        serverId,
+        uriTemplate,
+        arguments: args,
+      },
+    });
+    return result.contents;
+  };
+
+  // Subscribers per resource, so one chat detaching doesn't end another's updates
+  const subscriptionCounts = new Map<string, number>();
+  const subscriptionKey = (serverId: string, uri: string) => `${serverId}\n${uri}`;
+
+  /** Subscribe to a resource; its handlers registered with onResourceUpdated fire on changes */
+  const subscribeResource = async (serverId: string, uri: string): Promise<void> => {
+    const key = subscriptionKey(serverId, uri);
+    const count = subscriptionCounts.get(key) ?? 0;
+    if (count === 0) {
+      await invoke('mcp_subscribe_resource', { serverId, uri });
+    }
+    subscriptionCounts.set(key, count + 1);
+  };
+
+  /** Drop a subscription made with subscribeResource; the last one unsubscribes */
+  const unsubscribeResource = async (serverId: string, uri: string): Promise<void> => {
+    const key = subscriptionKey(serverId, uri);
+    const count = subscriptionCounts.get(key) ?? 0;
+    if (count > 1) {
+      subscriptionCounts.set(key, count - 1);
+      return;
+    }
+    subscriptionCounts.delete(key);
+    await invoke('mcp_unsubscribe_resource', { serverId, uri });
+  };
+
+  /** Register a handler for updates of subscribed resources; returns a function removing it */
+  const onResourceUpdated = (handler: (serverId: string, uri: string) => void): (() => void) => {
+    resourceUpdateHandlers.add(handler);
+    return () => {
+      resourceUpdateHandlers.delete(handler);
+    };
   };
 
   const getPrompt = async (serverId: string, name: string, args?: any): Promise<McpPromptMessage[]> => {
@@ -211,6 +337,11 @@ export const useMcpStore = defineStore('mcp', () => {
     return resources;
   };
 
+  const getAllResourceTemplates = async (): Promise<McpResourceTemplate[]> => {
+    const templates = await invoke<McpResourceTemplate[]>('mcp_list_resource_templates');
+    return templates;
+  };
+
   const getAllPrompts = async (): Promise<McpPrompt[]> => {
     const prompts = await invoke<McpPrompt[]>('mcp_list_prompts');
     return prompts;
@@ -237,10 +368,17 @@ export const useMcpStore = defineStore('mcp', () => {
     stopAllServers,
     callTool,
     readResource,
+    readResourceTemplate,
+    subscribeResource,
+    unsubscribeResource,
+    onResourceUpdated,
     getPrompt,
     getServerLogs,
+    reloadLogSettings,
+    refreshServer,
     getAllTools,
     getAllResources,
+    getAllResourceTemplates,
     getAllPrompts,
     cleanup,
   };
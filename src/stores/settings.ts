@@ -108,6 +108,16 @@ const defaultWebSearchSettings = {
 
 const defaultMcpSettings: Record<string, McpServer> = {};
 
+// Empty fields fall back to user.name / user.email from the repository's git config
+const defaultGitSettings: settingsDb.GitSettings = {
+  authorName: '',
+  authorEmail: '',
+};
+
+const defaultMcpLogSettings: settingsDb.McpLogSettings = {
+  persist: false,
+};
+
 export async function loadAgentSettings() {
   const dbSettings = await settingsDb.getAllAgentSettings();
   return defaultsDeep({}, dbSettings, defaultAgentSettings) as Record<string, AgentConfig>;
@@ -128,6 +138,16 @@ export async function loadWebSearchSettings() {
   return defaultsDeep({}, dbSettings, defaultWebSearchSettings) as typeof defaultWebSearchSettings;
 }
 
+export async function loadGitSettings() {
+  const dbSettings = await settingsDb.getGitSettings();
+  return defaultsDeep({}, dbSettings, defaultGitSettings) as settingsDb.GitSettings;
+}
+
+export async function loadMcpLogSettings() {
+  const dbSettings = await settingsDb.getMcpLogSettings();
+  return defaultsDeep({}, dbSettings, defaultMcpLogSettings) as settingsDb.McpLogSettings;
+}
+
 export async function loadMcpSettings() {
   const dbSettings = await settingsDb.getAllMcpServers();
   return defaultsDeep({}, dbSettings, defaultMcpSettings) as Record<string, McpServer>;
@@ -149,6 +169,8 @@ export const useSettingsStore = defineStore("settings", () => {
   const chatSettings = ref<typeof defaultChatSettings>({} as typeof defaultChatSettings);
   const webSearchSettings = ref<typeof defaultWebSearchSettings>({} as typeof defaultWebSearchSettings);
   const mcpServers = ref<Record<string, McpServer>>({});
+  const gitSettings = ref<settingsDb.GitSettings>({ ...defaultGitSettings });
+  const mcpLogSettings = ref<settingsDb.McpLogSettings>({ ...defaultMcpLogSettings });
   const isRestartingMcp = ref(false);
   const isMerging = ref(false);
 
@@ -214,6 +236,8 @@ export const useSettingsStore = defineStore("settings", () => {
     agentSettings.value = await loadAgentSettings();
     chatSettings.value = await loadChatSettings();
     webSearchSettings.value = await loadWebSearchSettings();
+    gitSettings.value = await loadGitSettings();
+    mcpLogSettings.value = await loadMcpLogSettings();
     mcpServers.value = await loadMcpSettings();
     imageSettings.value = await loadImageModelSettings();
 
@@ -295,6 +319,25 @@ export const useSettingsStore = defineStore("settings", () => {
     deep: true,
   });
 
+  // Machine specific, so not synced
+  watch(gitSettings, async (v) => {
+    await settingsDb.writeGitSettings(v);
+  }, {
+    deep: true,
+  });
+
+  // Machine specific too
+  watch(mcpLogSettings, async (v) => {
+    await settingsDb.writeMcpLogSettings(v);
+    try {
+      await useMcpStore().reloadLogSettings();
+    } catch (error) {
+      console.error("[MCP] Failed to apply log settings:", error);
+    }
+  }, {
+    deep: true,
+  });
+
   watch(imageSettings, async (v) => {
     if (isMerging.value) return;
 
@@ -383,6 +426,8 @@ export const useSettingsStore = defineStore("settings", () => {
     agentSettings,
     chatSettings,
     webSearchSettings,
+    gitSettings,
+    mcpLogSettings,
     mcpServers,
     imageSettings,
     initialize,